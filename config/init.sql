@@ -1,19 +0,0 @@
-DROP TABLE IF EXISTS market_data;
-
-CREATE TABLE market_data (
-    id BIGSERIAL PRIMARY KEY,
-    timestamp TIMESTAMPTZ NOT NULL,
-    symbol VARCHAR(20) NOT NULL,
-    price DOUBLE PRECISION NOT NULL,
-    volume DOUBLE PRECISION NOT NULL,
-    high DOUBLE PRECISION NOT NULL,
-    low DOUBLE PRECISION NOT NULL,
-    open DOUBLE PRECISION NOT NULL,
-    close DOUBLE PRECISION NOT NULL,
-    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
-);
-
-CREATE INDEX IF NOT EXISTS idx_market_data_symbol ON market_data(symbol);
-CREATE INDEX IF NOT EXISTS idx_market_data_timestamp ON market_data(timestamp);
-CREATE INDEX IF NOT EXISTS idx_market_data_symbol_timestamp ON market_data(symbol, timestamp);
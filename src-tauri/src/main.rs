@@ -13,7 +13,21 @@ mod commands;
 mod state;
 
 use commands::run_backtest;
+use serde::Serialize;
 use state::AppState;
+use tauri::{Emitter, Manager};
+use tokio::sync::broadcast::error::RecvError;
+use trading_core::exchange::types::KlineUpdate;
+
+// 推送给前端的实时 K 线事件名
+const KLINE_EVENT: &str = "kline-update";
+
+#[derive(Clone, Serialize)]
+struct KlineEvent {
+    exchange: String,
+    #[serde(flatten)]
+    update: KlineUpdate,
+}
 
 fn main() {
     // 初始化日志，设置更详细的级别
@@ -53,11 +67,40 @@ fn main() {
     });
 
     // 构建和运行 Tauri 应用
+    let runtime_handle = runtime.handle().clone();
     let result = tauri::Builder::default()
         .manage(app_state)
         .invoke_handler(tauri::generate_handler![run_backtest])
-        .setup(|app| {
+        .setup(move |app| {
             tracing::info!("Tauri setup started");
+
+            // 运行实时 K 线订阅，并把每次更新（包括未收盘的 K 线）转发给前端
+            let state = app.state::<AppState>();
+            for (exchange, manager) in &state.kline_managers {
+                let runner = manager.clone();
+                runtime_handle.spawn(async move { runner.run().await });
+
+                let app_handle = app.handle().clone();
+                let exchange = exchange.clone();
+                let mut updates = manager.subscribe();
+                runtime_handle.spawn(async move {
+                    loop {
+                        match updates.recv().await {
+                            Ok(update) => {
+                                let event = KlineEvent { exchange: exchange.clone(), update };
+                                if let Err(e) = app_handle.emit(KLINE_EVENT, event) {
+                                    tracing::warn!("Failed to emit kline update: {}", e);
+                                }
+                            }
+                            Err(RecvError::Lagged(skipped)) => {
+                                tracing::warn!("Skipped {} kline updates for the frontend", skipped);
+                            }
+                            Err(RecvError::Closed) => break,
+                        }
+                    }
+                });
+            }
+
             #[cfg(debug_assertions)]
             {
                 let app_handle = app.handle();
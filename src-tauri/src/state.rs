@@ -1,23 +1,52 @@
 use std::sync::Arc;
+use tracing::warn;
 use trading_core::{
     data::{
+        cache::MarketDataCache,
         database::Database,
         types::MarketDataManager,
     },
     config::Settings,
+    exchange::kline::LiveKlineManager,
 };
 
 pub struct AppState {
     pub market_manager: Arc<MarketDataManager>,
+    // 实时 K 线写入的行情缓存，未收盘的 K 线同时作为事件推送给前端
+    pub cache: Arc<MarketDataCache>,
+    // 每个配置了 kline_intervals 的交易所一个，附带交易所名称
+    pub kline_managers: Vec<(String, Arc<LiveKlineManager>)>,
 }
 
 impl AppState {
     pub async fn new() -> Result<Self, Box<dyn std::error::Error>> {
         let settings = Settings::new()?;
         let database = Database::new(&settings.database).await?;
-        
+        database.check_schema_version().await?;
+        if MarketDataManager::new(database.pool.clone()).candles_missing().await? {
+            warn!("candles table is empty but tick_data has trades; run `db migrate` to build candles from stored trades");
+        }
+
+        let cache = Arc::new(MarketDataCache::new(
+            settings.collector.feeds.iter().map(|feed| feed.symbols.len()).sum(),
+        ));
+        let mut kline_managers = Vec::new();
+        for feed in settings.collector.feeds.iter().filter(|feed| !feed.kline_intervals.is_empty()) {
+            let manager = LiveKlineManager::new(
+                Arc::from(settings.exchange.client(feed.exchange)?),
+                MarketDataManager::new(database.pool.clone()),
+                &feed.symbols,
+                &feed.kline_intervals,
+            )
+            .with_cache(cache.clone())
+            .with_reconnect(settings.collector.reconnect.clone());
+            kline_managers.push((feed.exchange.name().to_string(), Arc::new(manager)));
+        }
+
         Ok(Self {
             market_manager: Arc::new(MarketDataManager::new(database.pool)),
+            cache,
+            kline_managers,
         })
     }
-}
\ No newline at end of file
+}
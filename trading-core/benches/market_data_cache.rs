@@ -7,6 +7,7 @@ use std::time::Duration;
 
 fn create_test_tick(symbol: &str, price: f64, volume: f64) -> TickData {
     TickData {
+        exchange: "binance".to_string(),
         timestamp: Utc::now(),
         symbol: symbol.to_string(),
         price,
@@ -18,7 +19,7 @@ fn create_test_tick(symbol: &str, price: f64, volume: f64) -> TickData {
 }
 
 fn bench_single_update(c: &mut Criterion) {
-    let mut cache = MarketDataCache::new(100);
+    let cache = MarketDataCache::new(100);
     let tick = create_test_tick("BTC/USDT", 50000.0, 1.0);
 
     c.bench_function("single_update", |b| {
@@ -29,7 +30,7 @@ fn bench_single_update(c: &mut Criterion) {
 }
 
 fn bench_batch_update(c: &mut Criterion) {
-    let mut cache = MarketDataCache::new(100);
+    let cache = MarketDataCache::new(100);
     
     let mut group = c.benchmark_group("batch_update");
     group.sample_size(50);
@@ -54,7 +55,7 @@ fn bench_batch_update(c: &mut Criterion) {
 }
 
 fn bench_get_history(c: &mut Criterion) {
-    let mut cache = MarketDataCache::new(100);
+    let cache = MarketDataCache::new(100);
     let symbol = "BTC/USDT";
     
     // 预填充数据
@@ -72,7 +73,7 @@ fn bench_get_history(c: &mut Criterion) {
     for size in [10, 100, 500].iter() {
         group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
             b.iter(|| {
-                black_box(cache.get_history(symbol, size));
+                black_box(cache.get_history("binance", symbol, size));
             });
         });
     }
@@ -81,9 +82,9 @@ fn bench_get_history(c: &mut Criterion) {
 
 fn bench_concurrent_operations(c: &mut Criterion) {
     use std::thread;
-    use std::sync::{Arc, RwLock};
-    
-    let cache = Arc::new(RwLock::new(MarketDataCache::new(100)));
+    use std::sync::Arc;
+
+    let cache = Arc::new(MarketDataCache::new(100));
     let symbol = "BTC/USDT";
 
     let mut group = c.benchmark_group("concurrent_operations");
@@ -95,22 +96,18 @@ fn bench_concurrent_operations(c: &mut Criterion) {
             let cache_clone = Arc::clone(&cache);
             let write_thread = thread::spawn(move || {
                 for i in 0..100 {
-                    if let Ok(mut cache) = cache_clone.write() {
-                        cache.update(create_test_tick(
-                            symbol,
-                            50000.0 + i as f64,
-                            1.0
-                        ));
-                    }
+                    cache_clone.update(create_test_tick(
+                        symbol,
+                        50000.0 + i as f64,
+                        1.0
+                    ));
                 }
             });
 
             let cache_clone = Arc::clone(&cache);
             let read_thread = thread::spawn(move || {
                 for _ in 0..100 {
-                    if let Ok(cache) = cache_clone.read() {
-                        black_box(cache.get_history(symbol, 10));
-                    }
+                    black_box(cache_clone.get_history("binance", symbol, 10));
                 }
             });
 
@@ -122,8 +119,96 @@ fn bench_concurrent_operations(c: &mut Criterion) {
     group.finish();
 }
 
+// 每个线程交替写入和读取 OPS_PER_THREAD 次，一半线程写、一半线程读；
+// 与把整个缓存放在一把 RwLock 后面的做法对比分片锁在竞争下的表现
+fn bench_contention(c: &mut Criterion) {
+    use std::sync::{Arc, Barrier, RwLock};
+    use std::thread;
+    use std::time::Instant;
+
+    const OPS_PER_THREAD: usize = 1000;
+    const SYMBOLS: usize = 32;
+
+    let symbols: Arc<Vec<String>> = Arc::new((0..SYMBOLS).map(|i| format!("SYMBOL{}/USDT", i)).collect());
+    let ticks: Arc<Vec<TickData>> = Arc::new(
+        symbols.iter().map(|symbol| create_test_tick(symbol, 50000.0, 1.0)).collect()
+    );
+
+    let mut group = c.benchmark_group("contention");
+    group.sample_size(20);
+    group.measurement_time(Duration::from_secs(5));
+
+    for threads in [1, 2, 4, 8].iter() {
+        let run = |iters: u64, op: Arc<dyn Fn(usize, usize) + Send + Sync>| {
+            let mut elapsed = Duration::ZERO;
+            for _ in 0..iters {
+                let barrier = Arc::new(Barrier::new(*threads + 1));
+                let handles: Vec<_> = (0..*threads)
+                    .map(|t| {
+                        let barrier = Arc::clone(&barrier);
+                        let op = Arc::clone(&op);
+                        thread::spawn(move || {
+                            barrier.wait();
+                            for i in 0..OPS_PER_THREAD {
+                                op(t, i);
+                            }
+                        })
+                    })
+                    .collect();
+                // 线程创建不计入耗时
+                barrier.wait();
+                let start = Instant::now();
+                for handle in handles {
+                    handle.join().unwrap();
+                }
+                elapsed += start.elapsed();
+            }
+            elapsed
+        };
+
+        let sharded = Arc::new(MarketDataCache::new(SYMBOLS));
+        let (s, t) = (Arc::clone(&symbols), Arc::clone(&ticks));
+        let op: Arc<dyn Fn(usize, usize) + Send + Sync> = Arc::new(move |thread, i| {
+            let index = (thread * 7 + i) % SYMBOLS;
+            if thread % 2 == 0 {
+                sharded.update(t[index].clone());
+            } else {
+                black_box(sharded.get_history("binance", &s[index], 10));
+            }
+        });
+        group.bench_with_input(BenchmarkId::new("sharded", threads), threads, |b, _| {
+            b.iter_custom(|iters| run(iters, Arc::clone(&op)));
+        });
+
+        let global = Arc::new(RwLock::new(MarketDataCache::new(SYMBOLS)));
+        let (s, t) = (Arc::clone(&symbols), Arc::clone(&ticks));
+        let op: Arc<dyn Fn(usize, usize) + Send + Sync> = Arc::new(move |thread, i| {
+            let index = (thread * 7 + i) % SYMBOLS;
+            if thread % 2 == 0 {
+                global.write().unwrap().update(t[index].clone());
+            } else {
+                black_box(global.read().unwrap().get_history("binance", &s[index], 10));
+            }
+        });
+        group.bench_with_input(BenchmarkId::new("global_lock", threads), threads, |b, _| {
+            b.iter_custom(|iters| run(iters, Arc::clone(&op)));
+        });
+
+        // 交易对数量超过容量时，LRU 淘汰和插入的开销
+        let evicting = Arc::new(MarketDataCache::new(SYMBOLS / 2));
+        let t = Arc::clone(&ticks);
+        let op: Arc<dyn Fn(usize, usize) + Send + Sync> = Arc::new(move |thread, i| {
+            evicting.update(t[(thread * 7 + i) % SYMBOLS].clone());
+        });
+        group.bench_with_input(BenchmarkId::new("evicting", threads), threads, |b, _| {
+            b.iter_custom(|iters| run(iters, Arc::clone(&op)));
+        });
+    }
+    group.finish();
+}
+
 fn bench_market_data_aggregation(c: &mut Criterion) {
-    let mut cache = MarketDataCache::new(100);
+    let cache = MarketDataCache::new(100);
     let symbol = "BTC/USDT";
 
     c.bench_function("market_data_aggregation", |b| {
@@ -135,7 +220,7 @@ fn bench_market_data_aggregation(c: &mut Criterion) {
                     1.0
                 ));
             }
-            black_box(cache.get_market_data(symbol));
+            black_box(cache.get_market_data("binance", symbol));
         });
     });
 }
@@ -146,6 +231,7 @@ criterion_group!(
     bench_batch_update,
     bench_get_history,
     bench_concurrent_operations,
+    bench_contention,
     bench_market_data_aggregation
 );
 criterion_main!(benches);
\ No newline at end of file
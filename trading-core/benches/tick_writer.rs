@@ -0,0 +1,87 @@
+// 对比逐条 INSERT 与批量写入的吞吐，需要设置 DATABASE_URL
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use trading_core::data::types::{MarketDataManager, TickData};
+use sqlx::postgres::PgPoolOptions;
+use chrono::Utc;
+use uuid::Uuid;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+const BENCH_SYMBOL: &str = "BENCH/USDT";
+
+fn create_test_tick(price: f64) -> TickData {
+    TickData {
+        exchange: "binance".to_string(),
+        timestamp: Utc::now(),
+        symbol: BENCH_SYMBOL.to_string(),
+        price,
+        volume: 1.0,
+        side: "BUY".to_string(),
+        trade_id: Uuid::new_v4().to_string(),
+        is_maker: false,
+    }
+}
+
+fn create_ticks(count: usize) -> Vec<TickData> {
+    (0..count).map(|i| create_test_tick(50000.0 + i as f64)).collect()
+}
+
+fn bench_tick_writes(c: &mut Criterion) {
+    dotenv::dotenv().ok();
+    let Ok(database_url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL not set, skipping tick_writer benchmarks");
+        return;
+    };
+
+    let rt = Runtime::new().unwrap();
+    let manager = rt.block_on(async {
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .expect("Failed to create database pool");
+        MarketDataManager::new(pool)
+    });
+
+    let mut group = c.benchmark_group("tick_writes");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(10));
+
+    for size in [100, 1000].iter() {
+        group.throughput(Throughput::Elements(*size as u64));
+
+        group.bench_with_input(BenchmarkId::new("single_row_insert", size), size, |b, &size| {
+            b.iter_batched(
+                || create_ticks(size),
+                |ticks| rt.block_on(async {
+                    for tick in &ticks {
+                        manager.store_tick_data(tick).await.unwrap();
+                    }
+                }),
+                criterion::BatchSize::SmallInput,
+            );
+        });
+
+        group.bench_with_input(BenchmarkId::new("batch_insert", size), size, |b, &size| {
+            b.iter_batched(
+                || create_ticks(size),
+                |ticks| rt.block_on(async {
+                    manager.store_tick_batch(&ticks).await.unwrap();
+                }),
+                criterion::BatchSize::SmallInput,
+            );
+        });
+    }
+    group.finish();
+
+    rt.block_on(async {
+        sqlx::query("DELETE FROM tick_data WHERE symbol = $1")
+            .bind(BENCH_SYMBOL)
+            .execute(&manager.pool)
+            .await
+            .expect("Failed to clean up benchmark data");
+    });
+}
+
+criterion_group!(benches, bench_tick_writes);
+criterion_main!(benches);
@@ -0,0 +1,4 @@
+// 迁移脚本在编译时嵌入，修改后需要重新编译
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS tick_data;
+DROP TABLE IF EXISTS market_data;
@@ -0,0 +1,51 @@
+-- 初始表结构；使用 IF NOT EXISTS 以便接管由旧 SQL 脚本创建的数据库
+CREATE TABLE IF NOT EXISTS market_data (
+    id BIGSERIAL PRIMARY KEY,
+    timestamp TIMESTAMPTZ NOT NULL,
+    symbol VARCHAR(20) NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    volume DOUBLE PRECISION NOT NULL,
+    high DOUBLE PRECISION NOT NULL,
+    low DOUBLE PRECISION NOT NULL,
+    open DOUBLE PRECISION NOT NULL,
+    close DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_market_data_symbol ON market_data(symbol);
+CREATE INDEX IF NOT EXISTS idx_market_data_timestamp ON market_data(timestamp);
+CREATE INDEX IF NOT EXISTS idx_market_data_symbol_timestamp ON market_data(symbol, timestamp);
+
+CREATE TABLE IF NOT EXISTS tick_data (
+    id BIGSERIAL PRIMARY KEY,
+    timestamp TIMESTAMPTZ NOT NULL,
+    symbol VARCHAR(20) NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    volume DOUBLE PRECISION NOT NULL,
+    side CHAR(4) NOT NULL CHECK (side IN ('BUY', 'SELL')),
+    trade_id VARCHAR(50) NOT NULL,
+    is_maker BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_tick_data_symbol ON tick_data(symbol);
+CREATE INDEX IF NOT EXISTS idx_tick_data_timestamp ON tick_data(timestamp);
+CREATE INDEX IF NOT EXISTS idx_tick_data_symbol_timestamp ON tick_data(symbol, timestamp);
+CREATE INDEX IF NOT EXISTS idx_tick_data_trade_id ON tick_data(trade_id);
+
+-- 同一笔成交只保留最早写入的一条；已由旧脚本建成分区表时唯一索引已存在
+DO $$
+BEGIN
+    IF (SELECT relkind FROM pg_class WHERE oid = 'tick_data'::regclass) = 'p' THEN
+        RETURN;
+    END IF;
+
+    DELETE FROM tick_data a
+    USING tick_data b
+    WHERE a.symbol = b.symbol
+    AND a.trade_id = b.trade_id
+    AND a.id > b.id;
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_tick_data_symbol_trade_id ON tick_data(symbol, trade_id);
+END
+$$;
@@ -0,0 +1,23 @@
+-- 恢复为未分区的 tick_data，按天/月的分区一并删除
+ALTER TABLE tick_data RENAME TO tick_data_partitioned;
+ALTER TABLE tick_data_partitioned RENAME CONSTRAINT tick_data_pkey TO tick_data_partitioned_pkey;
+DROP INDEX IF EXISTS idx_tick_data_symbol;
+DROP INDEX IF EXISTS idx_tick_data_timestamp;
+DROP INDEX IF EXISTS idx_tick_data_symbol_timestamp;
+DROP INDEX IF EXISTS idx_tick_data_trade_id;
+DROP INDEX IF EXISTS idx_tick_data_symbol_trade_id;
+
+CREATE TABLE tick_data (
+    LIKE tick_data_partitioned INCLUDING DEFAULTS INCLUDING CONSTRAINTS,
+    PRIMARY KEY (id)
+);
+ALTER SEQUENCE tick_data_id_seq OWNED BY tick_data.id;
+
+CREATE INDEX idx_tick_data_symbol ON tick_data(symbol);
+CREATE INDEX idx_tick_data_timestamp ON tick_data(timestamp);
+CREATE INDEX idx_tick_data_symbol_timestamp ON tick_data(symbol, timestamp);
+CREATE INDEX idx_tick_data_trade_id ON tick_data(trade_id);
+CREATE UNIQUE INDEX idx_tick_data_symbol_trade_id ON tick_data(symbol, trade_id);
+
+INSERT INTO tick_data SELECT * FROM tick_data_partitioned ON CONFLICT DO NOTHING;
+DROP TABLE tick_data_partitioned;
@@ -0,0 +1,34 @@
+-- 将 tick_data 转换为按时间范围分区的表。数据先全部进入默认分区，
+-- PartitionManager 创建按天/月的分区时再从默认分区迁出。
+-- 分区表的唯一索引必须包含分区键；同一笔成交的时间戳不变，去重效果不变。
+DO $$
+BEGIN
+    IF (SELECT relkind FROM pg_class WHERE oid = 'tick_data'::regclass) = 'p' THEN
+        RETURN;
+    END IF;
+
+    ALTER TABLE tick_data RENAME TO tick_data_legacy;
+    ALTER TABLE tick_data_legacy RENAME CONSTRAINT tick_data_pkey TO tick_data_legacy_pkey;
+    DROP INDEX IF EXISTS idx_tick_data_symbol;
+    DROP INDEX IF EXISTS idx_tick_data_timestamp;
+    DROP INDEX IF EXISTS idx_tick_data_symbol_timestamp;
+    DROP INDEX IF EXISTS idx_tick_data_trade_id;
+    DROP INDEX IF EXISTS idx_tick_data_symbol_trade_id;
+
+    CREATE TABLE tick_data (
+        LIKE tick_data_legacy INCLUDING DEFAULTS INCLUDING CONSTRAINTS,
+        PRIMARY KEY (id, timestamp)
+    ) PARTITION BY RANGE (timestamp);
+    ALTER SEQUENCE tick_data_id_seq OWNED BY tick_data.id;
+    CREATE TABLE tick_data_default PARTITION OF tick_data DEFAULT;
+
+    CREATE INDEX idx_tick_data_symbol ON tick_data(symbol);
+    CREATE INDEX idx_tick_data_timestamp ON tick_data(timestamp);
+    CREATE INDEX idx_tick_data_symbol_timestamp ON tick_data(symbol, timestamp);
+    CREATE INDEX idx_tick_data_trade_id ON tick_data(trade_id);
+    CREATE UNIQUE INDEX idx_tick_data_symbol_trade_id ON tick_data(symbol, trade_id, timestamp);
+
+    INSERT INTO tick_data SELECT * FROM tick_data_legacy ON CONFLICT DO NOTHING;
+    DROP TABLE tick_data_legacy;
+END
+$$;
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS candles (
+    symbol VARCHAR(20) NOT NULL,
+    timeframe VARCHAR(4) NOT NULL,
+    open_time TIMESTAMPTZ NOT NULL,
+    open DOUBLE PRECISION NOT NULL,
+    high DOUBLE PRECISION NOT NULL,
+    low DOUBLE PRECISION NOT NULL,
+    close DOUBLE PRECISION NOT NULL,
+    volume DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (symbol, timeframe, open_time)
+);
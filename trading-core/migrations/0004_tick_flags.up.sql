@@ -0,0 +1,11 @@
+-- 数据质量检查标记的问题成交，一笔成交可以有多种问题
+CREATE TABLE IF NOT EXISTS tick_flags (
+    symbol VARCHAR(20) NOT NULL,
+    trade_id VARCHAR(50) NOT NULL,
+    timestamp TIMESTAMPTZ NOT NULL,
+    kind VARCHAR(20) NOT NULL,
+    reason TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (symbol, trade_id, timestamp, kind)
+);
+CREATE INDEX IF NOT EXISTS idx_tick_flags_symbol_timestamp ON tick_flags(symbol, timestamp);
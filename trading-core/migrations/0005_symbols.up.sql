@@ -0,0 +1,15 @@
+-- 交易对元数据，来自交易所 exchangeInfo 的交易规则
+CREATE TABLE IF NOT EXISTS symbols (
+    symbol VARCHAR(20) PRIMARY KEY,
+    base_asset VARCHAR(20) NOT NULL,
+    quote_asset VARCHAR(20) NOT NULL,
+    status VARCHAR(20) NOT NULL,
+    tick_size NUMERIC NOT NULL,
+    min_price NUMERIC NOT NULL,
+    max_price NUMERIC NOT NULL,
+    step_size NUMERIC NOT NULL,
+    min_qty NUMERIC NOT NULL,
+    max_qty NUMERIC NOT NULL,
+    min_notional NUMERIC NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
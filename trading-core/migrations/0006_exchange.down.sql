@@ -0,0 +1,19 @@
+-- 恢复单交易所结构，只保留 Binance 的数据，否则旧的唯一约束会冲突
+DELETE FROM tick_flags WHERE exchange <> 'binance';
+DROP INDEX IF EXISTS idx_tick_flags_exchange_symbol_timestamp;
+ALTER TABLE tick_flags DROP CONSTRAINT IF EXISTS tick_flags_pkey;
+ALTER TABLE tick_flags DROP COLUMN IF EXISTS exchange;
+ALTER TABLE tick_flags ADD PRIMARY KEY (symbol, trade_id, timestamp, kind);
+CREATE INDEX IF NOT EXISTS idx_tick_flags_symbol_timestamp ON tick_flags(symbol, timestamp);
+
+DELETE FROM candles WHERE exchange <> 'binance';
+ALTER TABLE candles DROP CONSTRAINT IF EXISTS candles_pkey;
+ALTER TABLE candles DROP COLUMN IF EXISTS exchange;
+ALTER TABLE candles ADD PRIMARY KEY (symbol, timeframe, open_time);
+
+DELETE FROM tick_data WHERE exchange <> 'binance';
+DROP INDEX IF EXISTS idx_tick_data_exchange_symbol_trade_id;
+DROP INDEX IF EXISTS idx_tick_data_exchange_symbol_timestamp;
+ALTER TABLE tick_data DROP COLUMN IF EXISTS exchange;
+CREATE INDEX IF NOT EXISTS idx_tick_data_symbol_timestamp ON tick_data(symbol, timestamp);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_tick_data_symbol_trade_id ON tick_data(symbol, trade_id, timestamp);
@@ -0,0 +1,24 @@
+-- 为成交、K 线和问题标记增加交易所维度，同一交易对可以同时保存多个交易所的数据。
+-- 已有数据都来自 Binance；回填后去掉默认值，写入时必须显式指定交易所。
+ALTER TABLE tick_data ADD COLUMN IF NOT EXISTS exchange VARCHAR(20) NOT NULL DEFAULT 'binance';
+ALTER TABLE tick_data ALTER COLUMN exchange DROP DEFAULT;
+
+DROP INDEX IF EXISTS idx_tick_data_symbol_trade_id;
+DROP INDEX IF EXISTS idx_tick_data_symbol_timestamp;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_tick_data_exchange_symbol_trade_id
+    ON tick_data(exchange, symbol, trade_id, timestamp);
+CREATE INDEX IF NOT EXISTS idx_tick_data_exchange_symbol_timestamp
+    ON tick_data(exchange, symbol, timestamp);
+
+ALTER TABLE candles ADD COLUMN IF NOT EXISTS exchange VARCHAR(20) NOT NULL DEFAULT 'binance';
+ALTER TABLE candles ALTER COLUMN exchange DROP DEFAULT;
+ALTER TABLE candles DROP CONSTRAINT IF EXISTS candles_pkey;
+ALTER TABLE candles ADD PRIMARY KEY (exchange, symbol, timeframe, open_time);
+
+ALTER TABLE tick_flags ADD COLUMN IF NOT EXISTS exchange VARCHAR(20) NOT NULL DEFAULT 'binance';
+ALTER TABLE tick_flags ALTER COLUMN exchange DROP DEFAULT;
+ALTER TABLE tick_flags DROP CONSTRAINT IF EXISTS tick_flags_pkey;
+ALTER TABLE tick_flags ADD PRIMARY KEY (exchange, symbol, trade_id, timestamp, kind);
+DROP INDEX IF EXISTS idx_tick_flags_symbol_timestamp;
+CREATE INDEX IF NOT EXISTS idx_tick_flags_exchange_symbol_timestamp
+    ON tick_flags(exchange, symbol, timestamp);
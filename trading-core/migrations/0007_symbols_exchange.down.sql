@@ -0,0 +1,9 @@
+-- 恢复单交易所结构，只保留 Binance 的交易规则，否则旧的主键会冲突
+ALTER TABLE symbols DROP COLUMN IF EXISTS market_max_qty;
+ALTER TABLE symbols DROP COLUMN IF EXISTS market_min_qty;
+ALTER TABLE symbols DROP COLUMN IF EXISTS market_step_size;
+
+DELETE FROM symbols WHERE exchange <> 'binance';
+ALTER TABLE symbols DROP CONSTRAINT IF EXISTS symbols_pkey;
+ALTER TABLE symbols DROP COLUMN IF EXISTS exchange;
+ALTER TABLE symbols ADD PRIMARY KEY (symbol);
@@ -0,0 +1,17 @@
+-- 交易规则按交易所区分，并单独保存市价单的数量规则（Binance 的 MARKET_LOT_SIZE）。
+-- 已有数据都来自 Binance；市价单规则在下次 `symbols refresh` 之前沿用限价单的数量规则。
+ALTER TABLE symbols ADD COLUMN IF NOT EXISTS exchange VARCHAR(20) NOT NULL DEFAULT 'binance';
+ALTER TABLE symbols ALTER COLUMN exchange DROP DEFAULT;
+ALTER TABLE symbols DROP CONSTRAINT IF EXISTS symbols_pkey;
+ALTER TABLE symbols ADD PRIMARY KEY (exchange, symbol);
+
+ALTER TABLE symbols ADD COLUMN IF NOT EXISTS market_step_size NUMERIC;
+ALTER TABLE symbols ADD COLUMN IF NOT EXISTS market_min_qty NUMERIC;
+ALTER TABLE symbols ADD COLUMN IF NOT EXISTS market_max_qty NUMERIC;
+UPDATE symbols SET
+    market_step_size = step_size,
+    market_min_qty = min_qty,
+    market_max_qty = max_qty;
+ALTER TABLE symbols ALTER COLUMN market_step_size SET NOT NULL;
+ALTER TABLE symbols ALTER COLUMN market_min_qty SET NOT NULL;
+ALTER TABLE symbols ALTER COLUMN market_max_qty SET NOT NULL;
@@ -0,0 +1,6 @@
+-- 记录 K 线来源：exchange 为交易所收盘 K 线，ticks 由成交聚合，rollup 由小周期汇总。
+-- 由成交或汇总得到的数据不能覆盖交易所 K 线。已有数据无法区分来源，
+-- 1m 按成交聚合处理，其余按汇总处理，重新回补后会被交易所 K 线覆盖。
+ALTER TABLE candles ADD COLUMN IF NOT EXISTS source VARCHAR(10) NOT NULL DEFAULT 'ticks';
+UPDATE candles SET source = 'rollup' WHERE timeframe <> '1m';
+ALTER TABLE candles ALTER COLUMN source DROP DEFAULT;
@@ -3,6 +3,8 @@
 use super::{types::*, Strategy};
 use super::metrics::MetricsCalculator;
 use crate::data::types::{MarketDataManager, MarketDataPoint};
+use crate::exchange::symbols::SymbolInfo;
+use crate::risk::RiskManager;
 use bigdecimal::{FromPrimitive, Zero};
 use chrono::{DateTime,Utc};
 use rust_decimal::Decimal;
@@ -16,6 +18,10 @@ pub struct BacktestEngine {
     trades: Vec<Trade>,
     metrics_calculator: MetricsCalculator,
     equity_points: Vec<EquityPoint>,
+    // 配置后按交易所规则取整数量并拒绝不合规的订单
+    symbol_info: Option<SymbolInfo>,
+    // 配置后每笔订单先经过风控检查，与实盘使用相同的限额
+    risk: Option<RiskManager>,
 }
 
 impl BacktestEngine {
@@ -33,9 +39,21 @@ impl BacktestEngine {
             trades: Vec::new(),
             metrics_calculator: MetricsCalculator::new(),
             equity_points: Vec::new(),
+            symbol_info: None,
+            risk: None,
         }
     }
 
+    pub fn with_symbol_info(mut self, symbol_info: SymbolInfo) -> Self {
+        self.symbol_info = Some(symbol_info);
+        self
+    }
+
+    pub fn with_risk_manager(mut self, risk: RiskManager) -> Self {
+        self.risk = Some(risk);
+        self
+    }
+
     pub async fn run_strategy(
         &mut self,
         mut strategy: Box<dyn Strategy>,
@@ -45,13 +63,30 @@ impl BacktestEngine {
         // 记录初始权益点
         self.record_equity_point(self.config.start_time, self.portfolio.total_value);
 
-        let historical_data = self.market_data
-            .get_market_data(
-                &self.config.symbol,
-                self.config.start_time,
-                self.config.end_time,
-            )
-            .await?;
+        let historical_data = match &self.config.interval {
+            Some(interval) => self.market_data
+                .get_candles(
+                    &self.config.symbol,
+                    interval,
+                    self.config.start_time,
+                    self.config.end_time,
+                )
+                .await?,
+            None if self.config.exclude_flagged => self.market_data
+                .get_clean_market_data(
+                    &self.config.symbol,
+                    self.config.start_time,
+                    self.config.end_time,
+                )
+                .await?,
+            None => self.market_data
+                .get_market_data(
+                    &self.config.symbol,
+                    self.config.start_time,
+                    self.config.end_time,
+                )
+                .await?,
+        };
 
         info!("Loaded {} historical data points", historical_data.len());
 
@@ -99,11 +134,55 @@ impl BacktestEngine {
 
     fn execute_order(&mut self, order: &Order, data: &MarketDataPoint) -> Option<Trade> {
         let price = Decimal::from_f64(data.price)?;
-        let commission = self.config.commission_rate * order.quantity * price;
+        let quantity = match &self.symbol_info {
+            Some(info) => {
+                let quantity = info.round_quantity(order.quantity);
+                if let Err(e) = info.check_market_order(price, quantity) {
+                    warn!("Order rejected by exchange rules: {}", e);
+                    return None;
+                }
+                quantity
+            }
+            None => order.quantity,
+        };
+
+        // 回测订单按当前价格立即成交或被拒绝，不会留下挂单
+        let Some(risk) = &mut self.risk else {
+            return self.fill_order(order, quantity, price, data.timestamp);
+        };
+        risk.update_price(&order.symbol, price);
+        risk.update_equity(self.portfolio.total_value, data.timestamp);
+        let order = Order { quantity, ..order.clone() };
+        if let Err(e) = risk.check_order(&order) {
+            warn!("Order rejected by risk manager: {}", e);
+            return None;
+        }
+
+        let trade = self.fill_order(&order, quantity, price, data.timestamp);
+        if let Some(risk) = &mut self.risk {
+            match &trade {
+                Some(trade) => {
+                    risk.on_fill(trade);
+                    risk.on_order_closed(&order, Decimal::ZERO);
+                }
+                None => risk.on_order_closed(&order, quantity),
+            }
+        }
+        trade
+    }
+
+    fn fill_order(
+        &mut self,
+        order: &Order,
+        quantity: Decimal,
+        price: Decimal,
+        timestamp: DateTime<Utc>,
+    ) -> Option<Trade> {
+        let commission = self.config.commission_rate * quantity * price;
 
         match order.side {
             OrderSide::Buy => {
-                let cost = order.quantity * price + commission;
+                let cost = quantity * price + commission;
                 if cost <= self.portfolio.cash {
                     self.portfolio.cash -= cost;
                     let position = self.portfolio.positions
@@ -114,18 +193,18 @@ impl BacktestEngine {
                             average_entry_price: Decimal::zero(),
                         });
                     
-                    let new_quantity = position.quantity + order.quantity;
+                    let new_quantity = position.quantity + quantity;
                     position.average_entry_price = 
-                        (position.average_entry_price * position.quantity + price * order.quantity) 
+                        (position.average_entry_price * position.quantity + price * quantity) 
                         / new_quantity;
                     position.quantity = new_quantity;
 
                     Some(Trade {
                         symbol: order.symbol.clone(),
                         side: OrderSide::Buy,
-                        quantity: order.quantity,
+                        quantity,
                         price,
-                        timestamp: data.timestamp,
+                        timestamp,
                         commission,
                     })
                 } else {
@@ -135,9 +214,9 @@ impl BacktestEngine {
             },
             OrderSide::Sell => {
                 if let Some(position) = self.portfolio.positions.get_mut(&order.symbol) {
-                    if position.quantity >= order.quantity {
-                        position.quantity -= order.quantity;
-                        self.portfolio.cash += order.quantity * price - commission;
+                    if position.quantity >= quantity {
+                        position.quantity -= quantity;
+                        self.portfolio.cash += quantity * price - commission;
                         
                         if position.quantity.is_zero() {
                             self.portfolio.positions.remove(&order.symbol);
@@ -146,9 +225,9 @@ impl BacktestEngine {
                         Some(Trade {
                             symbol: order.symbol.clone(),
                             side: OrderSide::Sell,
-                            quantity: order.quantity,
+                            quantity,
                             price,
-                            timestamp: data.timestamp,
+                            timestamp,
                             commission,
                         })
                     } else {
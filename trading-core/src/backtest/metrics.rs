@@ -10,6 +10,12 @@ pub struct MetricsCalculator {
     risk_free_rate: f64,
 }
 
+impl Default for MetricsCalculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MetricsCalculator {
     pub fn new() -> Self {
         Self {
@@ -13,6 +13,12 @@ pub struct BacktestConfig {
     pub initial_capital: Decimal,
     pub symbol: String,
     pub commission_rate: Decimal,
+    // K 线周期，设置时从 candles 表读取回测数据，否则使用逐笔成交
+    #[serde(default)]
+    pub interval: Option<String>,
+    // 排除被数据质量检查标记的成交；K 线聚合时已排除
+    #[serde(default)]
+    pub exclude_flagged: bool,
 }
 
 // 策略类型
@@ -1,5 +1,13 @@
 use serde::Deserialize;
 use config::{Config, ConfigError, Environment, File};
+use std::collections::HashMap;
+use crate::data::partition::{PartitionConfig, PartitionInterval, RetentionPolicy};
+use crate::data::quality::QualityConfig;
+use crate::exchange::binance::{BinanceNetwork, BinanceSpot};
+use crate::exchange::okx::OkxSpot;
+use crate::exchange::reconnect::ReconnectConfig;
+use crate::exchange::types::{ExchangeEndpoints, ExchangeKind};
+use reqwest::Url;
 
 #[derive(Debug, Deserialize)]
 pub struct Database {
@@ -7,6 +15,9 @@ pub struct Database {
     pub max_connections: u32,
     pub min_connections: u32,
     pub max_lifetime: u64,
+    // 连接时自动执行未执行的迁移；关闭时需手动运行 `db migrate`
+    #[serde(default)]
+    pub auto_migrate: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -14,10 +25,148 @@ pub struct Api {
     pub port: u16,
 }
 
+// 一个采集的交易所及其交易对
+#[derive(Debug, Clone, Deserialize)]
+pub struct CollectorFeed {
+    pub exchange: ExchangeKind,
+    pub symbols: Vec<String>,
+    // 订阅交易所推送的这些周期的 K 线，收盘后直接写入 candles 表
+    #[serde(default)]
+    pub kline_intervals: Vec<String>,
+}
+
+fn default_feeds() -> Vec<CollectorFeed> {
+    vec![CollectorFeed {
+        exchange: ExchangeKind::Binance,
+        symbols: vec!["BTCUSDT".into()],
+        kline_intervals: Vec::new(),
+    }]
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Collector {
+    // 数据库不可用时的本地落盘目录
+    pub spool_dir: String,
+    pub spool_segment_bytes: u64,
+    // 同时采集的交易所，未配置时只采集 Binance 的 BTCUSDT
+    #[serde(default = "default_feeds")]
+    pub feeds: Vec<CollectorFeed>,
+    #[serde(default)]
+    pub reconnect: ReconnectConfig,
+}
+
+impl Default for Collector {
+    fn default() -> Self {
+        Self {
+            spool_dir: "data/spool".into(),
+            spool_segment_bytes: 64 * 1024 * 1024,
+            feeds: default_feeds(),
+            reconnect: ReconnectConfig::default(),
+        }
+    }
+}
+
+// 交易所接入地址：Binance 按 network 选择预置环境，rest_url/ws_url 可单独覆盖（如本地模拟服务器）；
+// 其他交易所在各自的子表中覆盖默认地址
+#[derive(Debug, Default, Deserialize)]
+pub struct Exchange {
+    #[serde(default)]
+    pub network: BinanceNetwork,
+    pub rest_url: Option<String>,
+    pub ws_url: Option<String>,
+    #[serde(default)]
+    pub okx: EndpointOverrides,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct EndpointOverrides {
+    pub rest_url: Option<String>,
+    // OKX 填写 public 频道地址，K 线所在的 business 地址由它推出
+    pub ws_url: Option<String>,
+}
+
+impl Exchange {
+    pub fn binance_endpoints(&self) -> Result<ExchangeEndpoints, ConfigError> {
+        override_endpoints(self.network.endpoints(), &self.rest_url, &self.ws_url)
+    }
+
+    pub fn okx_endpoints(&self) -> Result<ExchangeEndpoints, ConfigError> {
+        override_endpoints(OkxSpot::default_endpoints(), &self.okx.rest_url, &self.okx.ws_url)
+    }
+
+    /// 按配置的接入地址创建该交易所的客户端
+    pub fn client(&self, kind: ExchangeKind) -> Result<Box<dyn crate::exchange::types::Exchange>, ConfigError> {
+        Ok(match kind {
+            ExchangeKind::Binance => Box::new(BinanceSpot::new(None).with_endpoints(self.binance_endpoints()?)),
+            ExchangeKind::Okx => Box::new(OkxSpot::new().with_endpoints(self.okx_endpoints()?)),
+        })
+    }
+}
+
+fn override_endpoints(
+    mut endpoints: ExchangeEndpoints,
+    rest_url: &Option<String>,
+    ws_url: &Option<String>,
+) -> Result<ExchangeEndpoints, ConfigError> {
+    let parse = |url: &str| {
+        Url::parse(url).map_err(|e| ConfigError::Message(format!("Invalid exchange URL {}: {}", url, e)))
+    };
+    if let Some(url) = rest_url {
+        endpoints.rest_url = parse(url)?;
+    }
+    if let Some(url) = ws_url {
+        endpoints.ws_url = parse(url)?;
+    }
+    Ok(endpoints)
+}
+
+// tick_data 分区与保留策略
+#[derive(Debug, Deserialize)]
+pub struct Partitioning {
+    pub interval: PartitionInterval,
+    pub premake: u32,
+    // 未配置时永久保留
+    pub retention_days: Option<u32>,
+    #[serde(default)]
+    pub retention_per_symbol: HashMap<String, u32>,
+}
+
+impl Default for Partitioning {
+    fn default() -> Self {
+        Self {
+            interval: PartitionInterval::Day,
+            premake: 7,
+            retention_days: None,
+            retention_per_symbol: HashMap::new(),
+        }
+    }
+}
+
+impl Partitioning {
+    pub fn partition_config(&self) -> PartitionConfig {
+        PartitionConfig {
+            interval: self.interval,
+            premake: self.premake,
+            retention: RetentionPolicy {
+                default_days: self.retention_days,
+                per_symbol: self.retention_per_symbol.clone(),
+            },
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Settings {
     pub database: Database,
     pub api: Api,
+    #[serde(default)]
+    pub exchange: Exchange,
+    #[serde(default)]
+    pub collector: Collector,
+    #[serde(default)]
+    pub partitioning: Partitioning,
+    #[serde(default)]
+    pub quality: QualityConfig,
 }
 
 impl Settings {
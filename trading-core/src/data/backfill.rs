@@ -0,0 +1,390 @@
+// trading-core/src/data/backfill.rs
+// 历史 K 线回补：按交易所单次 1000 根的上限分页拉取并写入 candles 表，
+// 中断后再次执行会从起点开始连续存储的最后一根交易所 K 线继续
+
+use super::market_data::MarketDataError;
+use super::types::{MarketDataManager, MarketDataPoint};
+use crate::exchange::types::{Exchange, ExchangeError};
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+use thiserror::Error;
+use tracing::{info, warn};
+
+// Binance 单次请求最多返回 1000 根 K 线
+pub const MAX_KLINES_PER_REQUEST: u32 = 1000;
+
+#[derive(Error, Debug)]
+pub enum BackfillError {
+    #[error("Exchange error: {0}")]
+    Exchange(#[from] ExchangeError),
+    #[error("Data error: {0}")]
+    Data(#[from] MarketDataError),
+    #[error("Unsupported interval: {0}")]
+    UnsupportedInterval(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct BackfillConfig {
+    // 两次请求之间的间隔，避免触发交易所限频
+    pub request_delay: std::time::Duration,
+    // 限频或网络错误时的重试次数，每次重试等待时间翻倍
+    pub max_retries: u32,
+    pub retry_delay: std::time::Duration,
+}
+
+impl Default for BackfillConfig {
+    fn default() -> Self {
+        Self {
+            request_delay: std::time::Duration::from_millis(250),
+            max_retries: 5,
+            retry_delay: std::time::Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BackfillReport {
+    pub requests: usize,
+    pub candles: u64,
+    // 从已有数据续传时的起点
+    pub resumed_from: Option<DateTime<Utc>>,
+}
+
+/// K 线周期对应的时长；不支持按自然月计算的 1M
+pub fn interval_duration(interval: &str) -> Option<Duration> {
+    let unit = interval.chars().last()?;
+    let count: i64 = interval[..interval.len() - unit.len_utf8()].parse().ok()?;
+    match unit {
+        'm' => Some(Duration::minutes(count)),
+        'h' => Some(Duration::hours(count)),
+        'd' => Some(Duration::days(count)),
+        'w' => Some(Duration::weeks(count)),
+        _ => None,
+    }
+}
+
+pub struct KlineBackfill {
+    exchange: Arc<dyn Exchange>,
+    manager: MarketDataManager,
+    config: BackfillConfig,
+}
+
+impl KlineBackfill {
+    /// K 线写入该交易所名下
+    pub fn new(exchange: Arc<dyn Exchange>, manager: MarketDataManager) -> Self {
+        Self {
+            manager: manager.with_exchange(exchange.name()),
+            exchange,
+            config: BackfillConfig::default(),
+        }
+    }
+
+    pub fn with_config(mut self, config: BackfillConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// 回补 [start_time, end_time) 内的 K 线
+    pub async fn run(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<BackfillReport, BackfillError> {
+        let step = interval_duration(interval)
+            .ok_or_else(|| BackfillError::UnsupportedInterval(interval.to_string()))?;
+        let mut report = BackfillReport::default();
+
+        // 从起点开始连续回补的最后一根 K 线重新开始，它可能是写入时尚未收盘的 K 线
+        let mut cursor = start_time;
+        if let Some(last) = self
+            .manager
+            .get_backfilled_until(symbol, interval, start_time, end_time, step)
+            .await?
+        {
+            if last > cursor {
+                info!("Resuming {} {} backfill from {}", symbol, interval, last);
+                cursor = last;
+                report.resumed_from = Some(last);
+            }
+        }
+
+        while cursor < end_time {
+            let klines = self.fetch_page(symbol, interval, cursor, end_time).await?;
+            report.requests += 1;
+            let Some(last) = klines.last() else {
+                break;
+            };
+            let next = last.timestamp + step;
+
+            report.candles += self.manager.store_candles(symbol, interval, &klines).await?;
+            // 1m K 线同时汇总出更大周期
+            if interval == "1m" {
+                self.manager.rollup_candles(symbol, klines[0].timestamp, last.timestamp).await?;
+            }
+            info!(
+                "Backfilled {} {} candles for {} up to {}",
+                klines.len(), interval, symbol, last.timestamp
+            );
+
+            if klines.len() < MAX_KLINES_PER_REQUEST as usize || next <= cursor {
+                break;
+            }
+            cursor = next;
+            tokio::time::sleep(self.config.request_delay).await;
+        }
+
+        Ok(report)
+    }
+
+    async fn fetch_page(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<MarketDataPoint>, BackfillError> {
+        let mut delay = self.config.retry_delay;
+        let mut attempt = 0;
+        loop {
+            let result = self
+                .exchange
+                .get_klines(
+                    symbol,
+                    interval,
+                    Some(start_time),
+                    // endTime 包含在结果内，减 1 毫秒保持区间右开
+                    Some(end_time - Duration::milliseconds(1)),
+                    Some(MAX_KLINES_PER_REQUEST),
+                )
+                .await;
+
+            match result {
+                Err(e @ (ExchangeError::RateLimitExceeded | ExchangeError::NetworkError(_)))
+                    if attempt < self.config.max_retries =>
+                {
+                    attempt += 1;
+                    warn!(
+                        "Kline request failed ({}), retry {}/{} in {:?}",
+                        e, attempt, self.config.max_retries, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                result => return Ok(result?),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::types::TickData;
+    use crate::exchange::symbols::SymbolInfo;
+    use crate::exchange::stream::{MarketStream, Topic};
+    use crate::exchange::types::{ExchangeTrade, OrderBook, Ticker};
+    use dotenv::dotenv;
+    use sqlx::postgres::PgPoolOptions;
+    use std::sync::Mutex;
+
+    // 按请求区间生成 1 分钟 K 线，记录请求次数
+    struct KlineExchange {
+        requests: Mutex<usize>,
+    }
+
+    // 回填只请求 K 线，其他接口不应被调用
+    fn unsupported() -> ExchangeError {
+        ExchangeError::ApiError("not supported by kline mock".to_string())
+    }
+
+    #[async_trait::async_trait]
+    impl Exchange for KlineExchange {
+        fn name(&self) -> &'static str {
+            "mock"
+        }
+
+        async fn get_ticker(&self, _: &str) -> Result<Ticker, ExchangeError> {
+            Err(unsupported())
+        }
+
+        async fn get_orderbook(&self, _: &str, _: u32) -> Result<OrderBook, ExchangeError> {
+            Err(unsupported())
+        }
+
+        async fn get_recent_trades(&self, _: &str, _: u32) -> Result<Vec<ExchangeTrade>, ExchangeError> {
+            Err(unsupported())
+        }
+
+        async fn get_historical_trades(&self, _: &str, _: u64, _: u32) -> Result<Vec<TickData>, ExchangeError> {
+            Err(unsupported())
+        }
+
+        async fn get_symbols(&self) -> Result<Vec<SymbolInfo>, ExchangeError> {
+            Err(unsupported())
+        }
+
+        async fn get_klines(
+            &self,
+            symbol: &str,
+            _interval: &str,
+            start_time: Option<DateTime<Utc>>,
+            end_time: Option<DateTime<Utc>>,
+            limit: Option<u32>,
+        ) -> Result<Vec<MarketDataPoint>, ExchangeError> {
+            *self.requests.lock().unwrap() += 1;
+            let (start, end) = (start_time.unwrap(), end_time.unwrap());
+            Ok((0..limit.unwrap() as i64)
+                .map(|i| start + Duration::minutes(i))
+                .take_while(|open_time| *open_time <= end)
+                .map(|open_time| MarketDataPoint::new(
+                    open_time, symbol.to_string(), 100.0, 1.0, 101.0, 99.0, 100.0, 100.0,
+                ))
+                .collect())
+        }
+
+        fn stream(&self, _: &[Topic]) -> Result<MarketStream, ExchangeError> {
+            Err(unsupported())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backfill_pages_and_resumes() {
+        dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL")
+            .expect("DATABASE_URL must be set for tests");
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&database_url)
+            .await
+            .expect("Failed to create test database pool");
+        let manager = MarketDataManager::new(pool);
+        let symbol = "TEST/BACKFILL";
+        sqlx::query!("DELETE FROM candles WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .unwrap();
+
+        let exchange = Arc::new(KlineExchange { requests: Mutex::new(0) });
+        let config = BackfillConfig {
+            request_delay: std::time::Duration::ZERO,
+            ..BackfillConfig::default()
+        };
+        let backfill = KlineBackfill::new(exchange.clone(), manager.clone()).with_config(config);
+
+        // 2500 根 1 分钟 K 线需要分 3 页
+        let start = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let report = backfill
+            .run(symbol, "1m", start, start + Duration::minutes(2500))
+            .await
+            .unwrap();
+        assert_eq!(report.candles, 2500);
+        assert_eq!(report.requests, 3);
+        assert!(report.resumed_from.is_none());
+
+        // 扩大区间后从最后一根 K 线续传，只请求新增部分
+        let report = backfill
+            .run(symbol, "1m", start, start + Duration::minutes(3000))
+            .await
+            .unwrap();
+        assert_eq!(report.resumed_from, Some(start + Duration::minutes(2499)));
+        assert_eq!(report.candles, 501);
+        assert_eq!(report.requests, 1);
+        assert_eq!(*exchange.requests.lock().unwrap(), 4);
+
+        let candles = manager
+            .clone()
+            .with_exchange("mock")
+            .get_candles(symbol, "1m", start, start + Duration::minutes(3000))
+            .await
+            .unwrap();
+        assert_eq!(candles.len(), 3000);
+
+        sqlx::query!("DELETE FROM candles WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_backfill_ignores_tick_candles_and_holes() {
+        dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL")
+            .expect("DATABASE_URL must be set for tests");
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&database_url)
+            .await
+            .expect("Failed to create test database pool");
+        let manager = MarketDataManager::new(pool);
+        let symbol = "TEST/BACKFILLHOLE";
+        sqlx::query!("DELETE FROM candles WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .unwrap();
+
+        let exchange = Arc::new(KlineExchange { requests: Mutex::new(0) });
+        let config = BackfillConfig {
+            request_delay: std::time::Duration::ZERO,
+            ..BackfillConfig::default()
+        };
+        let backfill = KlineBackfill::new(exchange.clone(), manager.clone()).with_config(config);
+        let start = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+
+        // 采集器由成交聚合出的最新 K 线
+        sqlx::query!(
+            r#"
+            INSERT INTO candles (exchange, symbol, timeframe, open_time, open, high, low, close, volume, source)
+            VALUES ('mock', $1, '1m', $2, 100, 100, 100, 100, 1, 'ticks')
+            "#,
+            symbol,
+            start + Duration::minutes(1900)
+        )
+        .execute(&manager.pool)
+        .await
+        .unwrap();
+
+        // 先回补中间一段，再回补更大的区间时要从起点开始补上前面的缺口
+        backfill
+            .run(symbol, "1m", start + Duration::minutes(1000), start + Duration::minutes(1500))
+            .await
+            .unwrap();
+        let report = backfill
+            .run(symbol, "1m", start, start + Duration::minutes(2000))
+            .await
+            .unwrap();
+        assert!(report.resumed_from.is_none());
+        assert_eq!(report.requests, 2);
+
+        let report = backfill
+            .run(symbol, "1m", start, start + Duration::minutes(2500))
+            .await
+            .unwrap();
+        assert_eq!(report.resumed_from, Some(start + Duration::minutes(1999)));
+
+        let candles = manager
+            .clone()
+            .with_exchange("mock")
+            .get_candles(symbol, "1m", start, start + Duration::minutes(2500))
+            .await
+            .unwrap();
+        assert_eq!(candles.len(), 2500);
+
+        sqlx::query!("DELETE FROM candles WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_interval_duration() {
+        assert_eq!(interval_duration("1m"), Some(Duration::minutes(1)));
+        assert_eq!(interval_duration("15m"), Some(Duration::minutes(15)));
+        assert_eq!(interval_duration("4h"), Some(Duration::hours(4)));
+        assert_eq!(interval_duration("1d"), Some(Duration::days(1)));
+        assert_eq!(interval_duration("1w"), Some(Duration::weeks(1)));
+        assert_eq!(interval_duration("1M"), None);
+        assert_eq!(interval_duration(""), None);
+    }
+}
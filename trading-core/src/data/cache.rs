@@ -1,55 +1,85 @@
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, VecDeque};
-use std::sync::RwLock;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use chrono::{DateTime, TimeZone, Utc};
+use tracing::{debug, warn};
+use super::backfill::interval_duration;
 use super::types::{TickData, MarketDataPoint};
+use crate::exchange::types::{KlineUpdate, OrderBook};
 
 const MAX_HISTORY_SIZE: usize = 1000;
+const DEFAULT_INTERVAL: &str = "1m";
+// 默认分片数量，足以让采集任务和多个读取方很少落在同一分片上
+const DEFAULT_SHARDS: usize = 16;
+// 每个周期默认保留的已收盘 K 线数量
+const DEFAULT_CANDLE_HISTORY: usize = 500;
+// 与数据库汇总 K 线相同的对齐起点 2000-01-03（周一），周线从周一开始
+const CANDLE_ORIGIN_MILLIS: i64 = 946_857_600_000;
 
+// 一个周期的滚动 K 线：当前未收盘的一根和最多 max_closed 根已收盘的 K 线。
+// 没有成交的周期不生成 K 线
 #[derive(Debug)]
-pub struct TickBuffer {
-    data: VecDeque<TickData>,
-    latest_market_data: Option<MarketDataPoint>,
+struct CandleSeries {
+    interval: String,
+    millis: i64,
+    current: Option<MarketDataPoint>,
+    closed: VecDeque<MarketDataPoint>,
+    max_closed: usize,
 }
 
-impl TickBuffer {
-    pub fn new() -> Self {
-        Self {
-            data: VecDeque::with_capacity(MAX_HISTORY_SIZE),
-            latest_market_data: None,
+impl CandleSeries {
+    fn new(interval: &str, max_closed: usize) -> Option<Self> {
+        let millis = interval_duration(interval)?.num_milliseconds();
+        if millis <= 0 {
+            return None;
         }
+        Some(Self {
+            interval: interval.to_string(),
+            millis,
+            current: None,
+            closed: VecDeque::with_capacity(max_closed.min(MAX_HISTORY_SIZE)),
+            max_closed,
+        })
     }
 
-    pub fn push(&mut self, tick: TickData) {
-        if self.data.len() >= MAX_HISTORY_SIZE {
-            self.data.pop_front();
-        }
-        self.update_market_data(&tick);
-        self.data.push_back(tick);
+    fn open_time(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        let offset = (timestamp.timestamp_millis() - CANDLE_ORIGIN_MILLIS).div_euclid(self.millis);
+        Utc.timestamp_millis_opt(CANDLE_ORIGIN_MILLIS + offset * self.millis)
+            .single()
+            .unwrap_or(timestamp)
     }
 
-    fn update_market_data(&mut self, tick: &TickData) {
-        match &mut self.latest_market_data {
-            Some(market_data) => {
-                if market_data.timestamp.timestamp() == tick.timestamp.timestamp() {
-                    market_data.high = market_data.high.max(tick.price);
-                    market_data.low = market_data.low.min(tick.price);
-                    market_data.close = tick.price;
-                    market_data.volume += tick.volume;
-                } else {
-                    self.latest_market_data = Some(MarketDataPoint {
-                        timestamp: tick.timestamp,
-                        symbol: tick.symbol.clone(),
-                        price: tick.price,
-                        volume: tick.volume,
-                        high: tick.price,
-                        low: tick.price,
-                        open: tick.price,
-                        close: tick.price,
-                    });
+    fn push(&mut self, tick: &TickData) {
+        let open_time = self.open_time(tick.timestamp);
+        match &mut self.current {
+            Some(current) if current.timestamp == open_time => {
+                current.high = current.high.max(tick.price);
+                current.low = current.low.min(tick.price);
+                current.close = tick.price;
+                current.price = tick.price;
+                current.volume += tick.volume;
+            }
+            // 迟到的成交只更新对应已收盘 K 线的高低点和成交量，开盘价和收盘价保持不变
+            Some(current) if open_time < current.timestamp => {
+                if let Some(bar) = self.closed.iter_mut().rev().find(|bar| bar.timestamp == open_time) {
+                    bar.high = bar.high.max(tick.price);
+                    bar.low = bar.low.min(tick.price);
+                    bar.volume += tick.volume;
                 }
             }
-            None => {
-                self.latest_market_data = Some(MarketDataPoint {
-                    timestamp: tick.timestamp,
+            _ => {
+                if let Some(finished) = self.current.take() {
+                    if self.closed.len() >= self.max_closed {
+                        self.closed.pop_front();
+                    }
+                    if self.max_closed > 0 {
+                        self.closed.push_back(finished);
+                    }
+                }
+                self.current = Some(MarketDataPoint {
+                    timestamp: open_time,
                     symbol: tick.symbol.clone(),
                     price: tick.price,
                     volume: tick.volume,
@@ -62,13 +92,79 @@ impl TickBuffer {
         }
     }
 
+    // 最近 n 根已收盘的 K 线，按时间从旧到新
+    fn closed(&self, n: usize) -> Vec<MarketDataPoint> {
+        let skip = self.closed.len().saturating_sub(n);
+        self.closed.iter().skip(skip).cloned().collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct TickBuffer {
+    data: VecDeque<TickData>,
+    // 按周期从短到长排列
+    candles: Vec<CandleSeries>,
+}
+
+impl Default for TickBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TickBuffer {
+    pub fn new() -> Self {
+        Self::with_intervals(&[DEFAULT_INTERVAL.to_string()], DEFAULT_CANDLE_HISTORY)
+    }
+
+    /// 按这些周期汇总 K 线，每个周期保留最多 history 根已收盘的 K 线；不支持的周期被忽略
+    pub fn with_intervals(intervals: &[String], history: usize) -> Self {
+        let mut candles: Vec<CandleSeries> = intervals
+            .iter()
+            .filter_map(|interval| {
+                let series = CandleSeries::new(interval, history);
+                if series.is_none() {
+                    warn!("Unsupported candle interval: {}", interval);
+                }
+                series
+            })
+            .collect();
+        candles.sort_by_key(|series| series.millis);
+        candles.dedup_by_key(|series| series.millis);
+        Self {
+            data: VecDeque::with_capacity(MAX_HISTORY_SIZE),
+            candles,
+        }
+    }
+
+    pub fn push(&mut self, tick: TickData) {
+        if self.data.len() >= MAX_HISTORY_SIZE {
+            self.data.pop_front();
+        }
+        for series in &mut self.candles {
+            series.push(&tick);
+        }
+        self.data.push_back(tick);
+    }
+
     pub fn latest(&self, n: usize) -> Vec<TickData> {
         let n = n.min(self.data.len());
         self.data.iter().rev().take(n).cloned().collect()
     }
 
+    /// 最短周期的当前 K 线，price 为最新成交价
     pub fn get_market_data(&self) -> Option<MarketDataPoint> {
-        self.latest_market_data.clone()
+        self.candles.first()?.current.clone()
+    }
+
+    /// 该周期当前未收盘的 K 线
+    pub fn current_candle(&self, interval: &str) -> Option<MarketDataPoint> {
+        self.series(interval)?.current.clone()
+    }
+
+    /// 该周期最近 n 根已收盘的 K 线，按时间从旧到新
+    pub fn candles(&self, interval: &str, n: usize) -> Option<Vec<MarketDataPoint>> {
+        Some(self.series(interval)?.closed(n))
     }
 
     pub fn len(&self) -> usize {
@@ -78,81 +174,382 @@ impl TickBuffer {
     pub fn is_empty(&self) -> bool {
         self.data.is_empty()
     }
+
+    fn series(&self, interval: &str) -> Option<&CandleSeries> {
+        self.candles.iter().find(|series| series.interval == interval)
+    }
+}
+
+/// 交易对数量达到 max_symbols 后，新交易对的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictionPolicy {
+    /// 淘汰最久没有更新的交易对，为新交易对腾出位置
+    #[default]
+    LeastRecentlyUsed,
+    /// 保留已有的交易对，丢弃新交易对的数据
+    RejectNew,
+}
+
+// 一个交易所上一个交易对的全部缓存数据，各部分分别加锁，写成交不会阻塞读订单簿
+#[derive(Debug)]
+struct SymbolEntry {
+    ticks: RwLock<TickBuffer>,
+    order_book: RwLock<Option<OrderBook>>,
+    // 交易所推送的最新 K 线，按周期保存
+    klines: RwLock<HashMap<String, KlineUpdate>>,
+    // 最近一次更新时的访问序号，用于 LRU 淘汰
+    last_used: AtomicU64,
 }
 
+// 按交易所、再按交易对索引，查找时不需要为组合键分配字符串
+type ShardMap = HashMap<String, HashMap<String, Arc<SymbolEntry>>>;
+type Shard = RwLock<ShardMap>;
+
+/// 按 (交易所, 交易对) 分片加锁的行情缓存，不同交易所的同名交易对分别保存成交、K 线和订单簿。
+/// 所有方法都只需要 `&self`，可以用 `Arc` 在采集任务和读取方之间共享。
+/// 分片锁只在查找和增删交易对时短暂持有，同一交易对的读写才会互相等待
+#[derive(Debug)]
 pub struct MarketDataCache {
-    data: HashMap<String, RwLock<TickBuffer>>,
+    shards: Box<[Shard]>,
+    // 已缓存的 (交易所, 交易对) 数量。占位和插入在同一次分片写锁内完成，保证不超过 max_symbols
+    len: AtomicUsize,
     max_symbols: usize,
+    policy: EvictionPolicy,
+    // 缓存已满时同一时间只有一个线程淘汰，避免为同一个新交易对重复淘汰
+    evicting: Mutex<()>,
+    // 逻辑时钟，每次更新交易对时递增，不依赖系统时间的精度
+    clock: AtomicU64,
+    // 新交易对按这些周期汇总 K 线
+    intervals: Vec<String>,
+    candle_history: usize,
 }
 
 impl MarketDataCache {
     pub fn new(max_symbols: usize) -> Self {
         Self {
-            data: HashMap::with_capacity(max_symbols),
+            shards: Self::build_shards(DEFAULT_SHARDS),
+            len: AtomicUsize::new(0),
             max_symbols,
+            policy: EvictionPolicy::default(),
+            evicting: Mutex::new(()),
+            clock: AtomicU64::new(0),
+            intervals: vec![DEFAULT_INTERVAL.to_string()],
+            candle_history: DEFAULT_CANDLE_HISTORY,
         }
     }
 
-    pub fn update(&mut self, tick: TickData) {
-        let symbol = tick.symbol.clone();
-        if let Some(buffer) = self.data.get(&symbol) {
-            if let Ok(mut buffer) = buffer.write() {
+    /// 每个交易对按这些周期维护滚动 K 线，每个周期保留最多 history 根已收盘的 K 线
+    pub fn with_candles(mut self, intervals: &[&str], history: usize) -> Self {
+        self.intervals = intervals.iter().map(|interval| interval.to_string()).collect();
+        self.candle_history = history;
+        self
+    }
+
+    pub fn with_eviction(mut self, policy: EvictionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// 分片数量，至少为 1；只应在缓存写入数据之前调用
+    pub fn with_shards(mut self, shards: usize) -> Self {
+        self.shards = Self::build_shards(shards.max(1));
+        self
+    }
+
+    /// 按成交的 exchange 和 symbol 写入对应的缓存
+    pub fn update(&self, tick: TickData) {
+        let (exchange, symbol) = (tick.exchange.clone(), tick.symbol.clone());
+        self.write_entry(&exchange, &symbol, |entry| {
+            if let Ok(mut buffer) = entry.ticks.write() {
                 buffer.push(tick);
             }
-        } else if self.data.len() < self.max_symbols {
-            let mut buffer = TickBuffer::new();
-            buffer.push(tick);
-            self.data.insert(symbol, RwLock::new(buffer));
-        }
+        });
     }
 
-    pub fn batch_update(&mut self, ticks: Vec<TickData>) {
+    pub fn batch_update(&self, ticks: Vec<TickData>) {
         for tick in ticks {
             self.update(tick);
         }
     }
 
-    pub fn get_history(&self, symbol: &str, n: usize) -> Option<Vec<TickData>> {
-        self.data.get(symbol).and_then(|buffer| {
-            buffer.read().ok().map(|guard| guard.latest(n))
-        })
+    pub fn get_history(&self, exchange: &str, symbol: &str, n: usize) -> Option<Vec<TickData>> {
+        self.read_ticks(exchange, symbol, |buffer| Some(buffer.latest(n)))
     }
 
-    pub fn get_market_data(&self, symbol: &str) -> Option<MarketDataPoint> {
-        self.data.get(symbol).and_then(|buffer| {
-            buffer.read().ok().and_then(|guard| guard.get_market_data())
-        })
+    pub fn get_market_data(&self, exchange: &str, symbol: &str) -> Option<MarketDataPoint> {
+        self.read_ticks(exchange, symbol, TickBuffer::get_market_data)
     }
 
-    pub fn get_all_market_data(&self) -> Vec<MarketDataPoint> {
-        self.data.values()
-            .filter_map(|buffer| {
-                buffer.read().ok().and_then(|guard| guard.get_market_data())
+    /// 该交易对在该周期上当前未收盘的 K 线
+    pub fn get_current_candle(&self, exchange: &str, symbol: &str, interval: &str) -> Option<MarketDataPoint> {
+        self.read_ticks(exchange, symbol, |buffer| buffer.current_candle(interval))
+    }
+
+    /// 该交易对在该周期上最近 n 根已收盘的 K 线，按时间从旧到新
+    pub fn get_candles(
+        &self,
+        exchange: &str,
+        symbol: &str,
+        interval: &str,
+        n: usize,
+    ) -> Option<Vec<MarketDataPoint>> {
+        self.read_ticks(exchange, symbol, |buffer| buffer.candles(interval, n))
+    }
+
+    /// 所有交易对的最新行情，附带所属交易所
+    pub fn get_all_market_data(&self) -> Vec<(String, MarketDataPoint)> {
+        self.entries()
+            .into_iter()
+            .filter_map(|(exchange, _, entry)| {
+                let market_data = entry.ticks.read().ok()?.get_market_data()?;
+                Some((exchange, market_data))
             })
             .collect()
     }
 
-    pub fn get_symbols(&self) -> Vec<String> {
-        self.data.keys().cloned().collect()
+    pub fn update_order_book(&self, exchange: &str, book: OrderBook) {
+        let symbol = book.symbol.clone();
+        self.write_entry(exchange, &symbol, |entry| {
+            if let Ok(mut order_book) = entry.order_book.write() {
+                *order_book = Some(book);
+            }
+        });
     }
 
-    pub fn clear_symbol(&mut self, symbol: &str) {
-        self.data.remove(symbol);
+    pub fn get_order_book(&self, exchange: &str, symbol: &str) -> Option<OrderBook> {
+        self.get_entry(exchange, symbol)?.order_book.read().ok()?.clone()
     }
 
-    pub fn clear(&mut self) {
-        self.data.clear();
+    pub fn update_kline(&self, exchange: &str, update: KlineUpdate) {
+        let symbol = update.symbol.clone();
+        self.write_entry(exchange, &symbol, |entry| {
+            if let Ok(mut klines) = entry.klines.write() {
+                klines.insert(update.interval.clone(), update);
+            }
+        });
+    }
+
+    pub fn get_kline(&self, exchange: &str, symbol: &str, interval: &str) -> Option<KlineUpdate> {
+        self.get_entry(exchange, symbol)?.klines.read().ok()?.get(interval).cloned()
+    }
+
+    /// 已缓存的 (交易所, 交易对)
+    pub fn get_symbols(&self) -> Vec<(String, String)> {
+        self.entries()
+            .into_iter()
+            .map(|(exchange, symbol, _)| (exchange, symbol))
+            .collect()
     }
+
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear_symbol(&self, exchange: &str, symbol: &str) {
+        self.remove(exchange, symbol);
+    }
+
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            let mut shard = write_shard(shard);
+            let removed: usize = shard.values().map(HashMap::len).sum();
+            self.len.fetch_sub(removed, Ordering::AcqRel);
+            shard.clear();
+        }
+    }
+
+    fn build_shards(count: usize) -> Box<[Shard]> {
+        (0..count).map(|_| RwLock::new(HashMap::new())).collect()
+    }
+
+    fn shard(&self, exchange: &str, symbol: &str) -> &Shard {
+        let mut hasher = DefaultHasher::new();
+        (exchange, symbol).hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+
+    fn now(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn get_entry(&self, exchange: &str, symbol: &str) -> Option<Arc<SymbolEntry>> {
+        read_shard(self.shard(exchange, symbol)).get(exchange)?.get(symbol).cloned()
+    }
+
+    fn read_ticks<T>(
+        &self,
+        exchange: &str,
+        symbol: &str,
+        read: impl FnOnce(&TickBuffer) -> Option<T>,
+    ) -> Option<T> {
+        let entry = self.get_entry(exchange, symbol)?;
+        let buffer = entry.ticks.read().ok()?;
+        // 只有订单簿或 K 线的交易对没有成交历史
+        if buffer.is_empty() {
+            return None;
+        }
+        read(&buffer)
+    }
+
+    fn entries(&self) -> Vec<(String, String, Arc<SymbolEntry>)> {
+        self.shards
+            .iter()
+            .flat_map(|shard| {
+                read_shard(shard)
+                    .iter()
+                    .flat_map(|(exchange, symbols)| {
+                        symbols
+                            .iter()
+                            .map(move |(symbol, entry)| (exchange.clone(), symbol.clone(), entry.clone()))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    // 查找交易对并刷新更新时间，不存在时按淘汰策略插入，然后写入。写入期间持有分片锁，
+    // 交易对不会在写入过程中被淘汰；新交易对被拒绝时丢弃这次更新
+    fn write_entry(&self, exchange: &str, symbol: &str, write: impl FnOnce(&SymbolEntry)) {
+        let now = self.now();
+        if let Some(entry) = read_shard(self.shard(exchange, symbol))
+            .get(exchange)
+            .and_then(|symbols| symbols.get(symbol))
+        {
+            entry.last_used.store(now, Ordering::Relaxed);
+            write(entry);
+            return;
+        }
+
+        let Some(mut shard) = self.lock_for_insert(exchange, symbol) else {
+            debug!("Market data cache is full, dropping update for {} {}", exchange, symbol);
+            return;
+        };
+        let entry = shard
+            .entry(exchange.to_string())
+            .or_default()
+            .entry(symbol.to_string())
+            .or_insert_with(|| {
+                Arc::new(SymbolEntry {
+                    ticks: RwLock::new(TickBuffer::with_intervals(&self.intervals, self.candle_history)),
+                    order_book: RwLock::new(None),
+                    klines: RwLock::new(HashMap::new()),
+                    last_used: AtomicU64::new(now),
+                })
+            });
+        entry.last_used.store(now, Ordering::Relaxed);
+        write(entry);
+    }
+
+    // 返回交易对所在分片的写锁：交易对已存在，或已经为它占好位置、需要在同一把锁内插入。
+    // 缓存已满且按策略不能插入时返回 None
+    fn lock_for_insert(&self, exchange: &str, symbol: &str) -> Option<RwLockWriteGuard<'_, ShardMap>> {
+        let shard = self.shard(exchange, symbol);
+        let try_lock = || {
+            let guard = write_shard(shard);
+            let exists = guard.get(exchange).is_some_and(|symbols| symbols.contains_key(symbol));
+            (exists || self.try_reserve()).then_some(guard)
+        };
+        if let Some(guard) = try_lock() {
+            return Some(guard);
+        }
+        if self.policy == EvictionPolicy::RejectNew || self.max_symbols == 0 {
+            return None;
+        }
+
+        // 拿到淘汰锁后先重新检查，其他线程可能已经插入了该交易对或释放了位置
+        let _evicting = self.evicting.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            if let Some(guard) = try_lock() {
+                return Some(guard);
+            }
+            // 占位只在持有分片写锁时短暂存在，找不到可淘汰的交易对时等它插入完成
+            if !self.evict_least_recently_used() {
+                std::thread::yield_now();
+            }
+        }
+    }
+
+    fn try_reserve(&self) -> bool {
+        self.len
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |len| (len < self.max_symbols).then_some(len + 1))
+            .is_ok()
+    }
+
+    // 逐个分片查找最久没有更新的交易对并移除，不同时持有多个分片锁。
+    // 查找之后被更新过的交易对不移除，由调用方重新查找
+    fn evict_least_recently_used(&self) -> bool {
+        let oldest = self
+            .shards
+            .iter()
+            .filter_map(|shard| {
+                read_shard(shard)
+                    .iter()
+                    .flat_map(|(exchange, symbols)| {
+                        symbols.iter().map(move |(symbol, entry)| {
+                            (entry.last_used.load(Ordering::Relaxed), exchange.clone(), symbol.clone())
+                        })
+                    })
+                    .min()
+            })
+            .min();
+        let Some((last_used, exchange, symbol)) = oldest else {
+            return false;
+        };
+
+        let mut shard = write_shard(self.shard(&exchange, &symbol));
+        let Some(symbols) = shard.get_mut(&exchange) else {
+            return false;
+        };
+        let unchanged = symbols
+            .get(&symbol)
+            .is_some_and(|entry| entry.last_used.load(Ordering::Relaxed) == last_used);
+        if !unchanged {
+            return false;
+        }
+        debug!("Evicting {} {} from market data cache", exchange, symbol);
+        symbols.remove(&symbol);
+        self.len.fetch_sub(1, Ordering::AcqRel);
+        if symbols.is_empty() {
+            shard.remove(&exchange);
+        }
+        true
+    }
+
+    fn remove(&self, exchange: &str, symbol: &str) {
+        let mut shard = write_shard(self.shard(exchange, symbol));
+        let Some(symbols) = shard.get_mut(exchange) else {
+            return;
+        };
+        if symbols.remove(symbol).is_some() {
+            self.len.fetch_sub(1, Ordering::AcqRel);
+        }
+        if symbols.is_empty() {
+            shard.remove(exchange);
+        }
+    }
+}
+
+// 分片锁中只有 HashMap 的增删，持锁线程 panic 也不会留下不一致的数据，直接取回即可
+fn read_shard(shard: &Shard) -> RwLockReadGuard<'_, ShardMap> {
+    shard.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write_shard(shard: &Shard) -> RwLockWriteGuard<'_, ShardMap> {
+    shard.write().unwrap_or_else(PoisonError::into_inner)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use uuid::Uuid;
-    use chrono::Utc;
 
     fn create_test_tick(symbol: &str, price: f64, volume: f64) -> TickData {
         TickData {
+            exchange: "binance".to_string(),
             timestamp: Utc::now(),
             symbol: symbol.to_string(),
             price,
@@ -176,9 +573,56 @@ mod tests {
         assert_eq!(market_data.price, 50000.0);
     }
 
+    fn tick_at(seconds: i64, price: f64, volume: f64) -> TickData {
+        TickData {
+            timestamp: Utc.timestamp_opt(1_700_000_000 + seconds, 0).unwrap(),
+            ..create_test_tick("BTCUSDT", price, volume)
+        }
+    }
+
+    #[test]
+    fn test_rolling_candles() {
+        let mut buffer = TickBuffer::with_intervals(&["5m".to_string(), "1m".to_string()], 2);
+        // 1_700_000_000 是 22:13:20，所在的 1 分钟 K 线从 22:13:00 开始
+        for (seconds, price) in [(0, 100.0), (1, 103.0), (30, 99.0), (39, 101.0), (40, 102.0), (90, 104.0), (161, 105.0)] {
+            buffer.push(tick_at(seconds, price, 1.0));
+        }
+
+        // 跨秒的成交合并到同一根 K 线，开盘价是该分钟的第一笔成交
+        let closed = buffer.candles("1m", 10).unwrap();
+        assert_eq!(closed.len(), 2);
+        assert_eq!(closed[0].timestamp, Utc.timestamp_opt(1_699_999_980, 0).unwrap());
+        assert_eq!((closed[0].open, closed[0].high, closed[0].low, closed[0].close, closed[0].volume), (100.0, 103.0, 99.0, 101.0, 4.0));
+        assert_eq!((closed[1].open, closed[1].close, closed[1].volume), (102.0, 104.0, 2.0));
+
+        let current = buffer.current_candle("1m").unwrap();
+        assert_eq!((current.open, current.close, current.price), (105.0, 105.0, 105.0));
+        assert_eq!(buffer.get_market_data().unwrap().timestamp, current.timestamp);
+
+        // 5 分钟 K 线从 22:10:00 开始，22:16:01 的成交开始新的一根
+        let five = buffer.candles("5m", 10).unwrap();
+        assert_eq!(five.len(), 1);
+        assert_eq!(five[0].timestamp, Utc.timestamp_opt(1_699_999_800, 0).unwrap());
+        assert_eq!((five[0].open, five[0].high, five[0].low, five[0].close, five[0].volume), (100.0, 104.0, 99.0, 104.0, 6.0));
+        assert_eq!(buffer.current_candle("5m").unwrap().open, 105.0);
+        assert!(buffer.candles("1h", 10).is_none());
+
+        // 迟到的成交计入对应的已收盘 K 线
+        buffer.push(tick_at(10, 110.0, 0.5));
+        let closed = buffer.candles("1m", 10).unwrap();
+        assert_eq!((closed[0].high, closed[0].close, closed[0].volume), (110.0, 101.0, 4.5));
+
+        // 只保留最近 2 根已收盘的 K 线
+        buffer.push(tick_at(230, 106.0, 1.0));
+        let closed = buffer.candles("1m", 10).unwrap();
+        assert_eq!(closed.len(), 2);
+        assert_eq!(closed[1].open, 105.0);
+        assert_eq!(buffer.candles("1m", 1).unwrap()[0].open, 105.0);
+    }
+
     #[test]
     fn test_market_data_cache() {
-        let mut cache = MarketDataCache::new(10);
+        let cache = MarketDataCache::new(10);
         
         let tick = create_test_tick("BTC/USDT", 50000.0, 1.0);
         cache.update(tick);
@@ -189,15 +633,136 @@ mod tests {
         ];
         cache.batch_update(ticks);
         
-        let history = cache.get_history("BTC/USDT", 1).unwrap();
+        let history = cache.get_history("binance", "BTC/USDT", 1).unwrap();
         assert_eq!(history.len(), 1);
         assert_eq!(history[0].price, 50000.0);
         
-        let market_data = cache.get_market_data("BTC/USDT").unwrap();
+        let market_data = cache.get_market_data("binance", "BTC/USDT").unwrap();
         assert_eq!(market_data.symbol, "BTC/USDT");
         assert_eq!(market_data.price, 50000.0);
         
         let all_market_data = cache.get_all_market_data();
         assert_eq!(all_market_data.len(), 3);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_cache_candle_intervals() {
+        let cache = MarketDataCache::new(10).with_candles(&["1m", "1h"], 100);
+        cache.update(tick_at(0, 100.0, 1.0));
+        cache.update(tick_at(60, 101.0, 1.0));
+
+        assert_eq!(cache.get_candles("binance", "BTCUSDT", "1m", 10).unwrap().len(), 1);
+        assert_eq!(cache.get_current_candle("binance", "BTCUSDT", "1h").unwrap().volume, 2.0);
+        assert!(cache.get_current_candle("binance", "BTCUSDT", "5m").is_none());
+        assert!(cache.get_candles("binance", "ETHUSDT", "1m", 10).is_none());
+    }
+
+    #[test]
+    fn test_cache_separates_exchanges() {
+        let okx_tick = |seconds: i64, price: f64, volume: f64| TickData {
+            exchange: "okx".to_string(),
+            ..tick_at(seconds, price, volume)
+        };
+        let cache = MarketDataCache::new(10).with_candles(&["1m"], 10);
+        cache.update(tick_at(0, 100.0, 1.0));
+        cache.update(okx_tick(1, 200.0, 2.0));
+        cache.update(tick_at(2, 101.0, 1.0));
+        cache.update(okx_tick(3, 199.0, 3.0));
+
+        // 两个交易所的同名交易对各自汇总 K 线
+        let binance = cache.get_current_candle("binance", "BTCUSDT", "1m").unwrap();
+        assert_eq!((binance.open, binance.high, binance.low, binance.close, binance.volume), (100.0, 101.0, 100.0, 101.0, 2.0));
+        let okx = cache.get_current_candle("okx", "BTCUSDT", "1m").unwrap();
+        assert_eq!((okx.open, okx.high, okx.low, okx.close, okx.volume), (200.0, 200.0, 199.0, 199.0, 5.0));
+        assert_eq!(cache.get_history("okx", "BTCUSDT", 10).unwrap().len(), 2);
+        assert!(cache.get_market_data("bybit", "BTCUSDT").is_none());
+
+        let kline = KlineUpdate {
+            symbol: "BTCUSDT".to_string(),
+            interval: "1m".to_string(),
+            candle: okx.clone(),
+            is_closed: false,
+        };
+        cache.update_kline("okx", kline);
+        assert!(cache.get_kline("okx", "BTCUSDT", "1m").is_some());
+        assert!(cache.get_kline("binance", "BTCUSDT", "1m").is_none());
+
+        assert_eq!(cache.len(), 2);
+        let mut symbols = cache.get_symbols();
+        symbols.sort();
+        assert_eq!(symbols, vec![
+            ("binance".to_string(), "BTCUSDT".to_string()),
+            ("okx".to_string(), "BTCUSDT".to_string()),
+        ]);
+
+        // 淘汰和清除也按交易所区分
+        let cache = MarketDataCache::new(2);
+        cache.update(tick_at(0, 100.0, 1.0));
+        cache.update(okx_tick(1, 200.0, 1.0));
+        cache.update(TickData { exchange: "bybit".to_string(), ..tick_at(2, 300.0, 1.0) });
+        assert!(cache.get_market_data("binance", "BTCUSDT").is_none());
+        assert!(cache.get_market_data("okx", "BTCUSDT").is_some());
+        cache.clear_symbol("okx", "BTCUSDT");
+        assert!(cache.get_market_data("okx", "BTCUSDT").is_none());
+        assert!(cache.get_market_data("bybit", "BTCUSDT").is_some());
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_cache_eviction_policy() {
+        let cache = MarketDataCache::new(2);
+        cache.update(create_test_tick("BTC/USDT", 50000.0, 1.0));
+        cache.update(create_test_tick("ETH/USDT", 3000.0, 1.0));
+        // BTC 重新更新后，最久没有更新的是 ETH
+        cache.update(create_test_tick("BTC/USDT", 50001.0, 1.0));
+        cache.update(create_test_tick("BNB/USDT", 400.0, 1.0));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get_market_data("binance", "ETH/USDT").is_none());
+        assert_eq!(cache.get_history("binance", "BTC/USDT", 10).unwrap().len(), 2);
+        assert!(cache.get_market_data("binance", "BNB/USDT").is_some());
+
+        let cache = MarketDataCache::new(1).with_eviction(EvictionPolicy::RejectNew);
+        cache.update(create_test_tick("BTC/USDT", 50000.0, 1.0));
+        cache.update(create_test_tick("ETH/USDT", 3000.0, 1.0));
+        assert_eq!(cache.get_symbols(), vec![("binance".to_string(), "BTC/USDT".to_string())]);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        cache.update(create_test_tick("ETH/USDT", 3000.0, 1.0));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_updates_and_reads() {
+        let cache = Arc::new(MarketDataCache::new(8).with_shards(4));
+        std::thread::scope(|scope| {
+            for writer in 0..4 {
+                let cache = cache.clone();
+                scope.spawn(move || {
+                    for i in 0..500 {
+                        cache.update(create_test_tick(&format!("SYM{}/USDT", (writer + i) % 12), 100.0, 1.0));
+                    }
+                });
+            }
+            for _ in 0..2 {
+                let cache = cache.clone();
+                scope.spawn(move || {
+                    for i in 0..500 {
+                        assert_history(&cache, &format!("SYM{}/USDT", i % 12));
+                    }
+                });
+            }
+        });
+
+        // 12 个交易对竞争 8 个位置，容量始终不超过上限
+        assert_eq!(cache.len(), 8);
+        assert_eq!(cache.get_symbols().len(), 8);
+    }
+
+    fn assert_history(cache: &MarketDataCache, symbol: &str) {
+        if let Some(history) = cache.get_history("binance", symbol, 10) {
+            assert!(!history.is_empty() && history.len() <= 10);
+        }
+    }
+}
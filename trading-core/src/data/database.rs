@@ -1,15 +1,46 @@
+use sqlx::migrate::{MigrateError, Migrator};
 use sqlx::postgres::{PgPool, PgPoolOptions};
-use tracing::info;
+use thiserror::Error;
+use tracing::{info, warn};
 use crate::config::Database as DbConfig;
 
+// 编译时嵌入 trading-core/migrations 下的迁移脚本
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+#[derive(Error, Debug)]
+pub enum DatabaseError {
+    #[error("Database error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+    #[error("Migration error: {0}")]
+    Migration(#[from] MigrateError),
+    #[error("Database schema is at version {current:?}, expected {expected}; run `db migrate`")]
+    Outdated { current: Option<i64>, expected: i64 },
+    #[error("Database schema version {current} is newer than this build supports ({expected})")]
+    TooNew { current: i64, expected: i64 },
+    #[error("Migration {0} did not complete; fix the schema and run `db rollback` or `db migrate`")]
+    Dirty(i64),
+}
+
+#[derive(Debug, Clone)]
+pub struct SchemaStatus {
+    // 已成功执行的最高版本
+    pub current: Option<i64>,
+    // 当前程序内嵌的最高版本
+    pub expected: i64,
+    // 已嵌入但尚未执行的版本
+    pub pending: Vec<i64>,
+    // 执行失败、处于中间状态的版本
+    pub dirty: Option<i64>,
+}
+
 pub struct Database {
     pub pool: PgPool,
 }
 
 impl Database {
-    pub async fn new(config: &DbConfig) -> Result<Self, sqlx::Error> {
+    pub async fn new(config: &DbConfig) -> Result<Self, DatabaseError> {
         info!("Initializing database connection pool...");
-        
+
         let pool = PgPoolOptions::new()
             .max_connections(config.max_connections)
             .min_connections(config.min_connections)
@@ -18,7 +49,11 @@ impl Database {
             .await?;
 
         info!("Database connection pool initialized successfully");
-        Ok(Self { pool })
+        let database = Self { pool };
+        if config.auto_migrate {
+            database.migrate().await?;
+        }
+        Ok(database)
     }
 
     pub async fn check_connection(&self) -> Result<(), sqlx::Error> {
@@ -26,4 +61,130 @@ impl Database {
         info!("Database connection test successful");
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// 执行所有未执行的迁移
+    pub async fn migrate(&self) -> Result<(), DatabaseError> {
+        MIGRATOR.run(&self.pool).await?;
+        info!("Database schema is up to date (version {})", latest_version());
+        Ok(())
+    }
+
+    /// 依次回滚版本高于 target 的迁移，target 为 0 时回滚全部
+    pub async fn rollback(&self, target: i64) -> Result<(), DatabaseError> {
+        warn!("Rolling back database schema to version {}", target);
+        MIGRATOR.undo(&self.pool, target).await?;
+        Ok(())
+    }
+
+    pub async fn schema_status(&self) -> Result<SchemaStatus, DatabaseError> {
+        use sqlx::migrate::Migrate;
+
+        let mut conn = self.pool.acquire().await?;
+        conn.ensure_migrations_table().await?;
+        let dirty = conn.dirty_version().await?;
+        let applied = conn.list_applied_migrations().await?;
+
+        let current = applied.iter().map(|m| m.version).max();
+        let pending = MIGRATOR
+            .iter()
+            .filter(|m| m.migration_type.is_up_migration())
+            .map(|m| m.version)
+            .filter(|version| applied.iter().all(|a| a.version != *version))
+            .collect();
+
+        Ok(SchemaStatus {
+            current,
+            expected: latest_version(),
+            pending,
+            dirty,
+        })
+    }
+
+    /// 启动时检查数据库结构版本与程序是否一致
+    pub async fn check_schema_version(&self) -> Result<(), DatabaseError> {
+        let status = self.schema_status().await?;
+        if let Some(version) = status.dirty {
+            return Err(DatabaseError::Dirty(version));
+        }
+        match status.current {
+            Some(current) if current > status.expected => Err(DatabaseError::TooNew {
+                current,
+                expected: status.expected,
+            }),
+            _ if !status.pending.is_empty() => Err(DatabaseError::Outdated {
+                current: status.current,
+                expected: status.expected,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn latest_version() -> i64 {
+    MIGRATOR.iter().map(|m| m.version).max().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dotenv::dotenv;
+    use sqlx::{Connection, Executor, PgConnection};
+
+    #[tokio::test]
+    async fn test_migrate_up_and_down() {
+        dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL")
+            .expect("DATABASE_URL must be set for tests");
+
+        // 在独立的临时库中执行，避免影响其他测试
+        let name = format!("rust_trade_migrate_{}", uuid::Uuid::new_v4().simple());
+        let mut admin = PgConnection::connect(&database_url).await.unwrap();
+        admin.execute(format!("CREATE DATABASE {}", name).as_str()).await.unwrap();
+
+        let (base, _) = database_url.rsplit_once('/').unwrap();
+        let config = DbConfig {
+            url: format!("{}/{}", base, name),
+            max_connections: 1,
+            min_connections: 0,
+            max_lifetime: 60,
+            auto_migrate: false,
+        };
+        let database = Database::new(&config).await.unwrap();
+
+        assert!(matches!(
+            database.check_schema_version().await,
+            Err(DatabaseError::Outdated { current: None, .. })
+        ));
+
+        database.migrate().await.unwrap();
+        database.check_schema_version().await.unwrap();
+        let status = database.schema_status().await.unwrap();
+        assert_eq!(status.current, Some(status.expected));
+        assert!(status.pending.is_empty());
+
+        let tables: Vec<String> = sqlx::query_scalar(
+            "SELECT tablename::text FROM pg_tables WHERE schemaname = 'public' ORDER BY tablename",
+        )
+        .fetch_all(&database.pool)
+        .await
+        .unwrap();
+        assert!(tables.contains(&"tick_data_default".to_string()));
+        assert!(tables.contains(&"candles".to_string()));
+
+        // 回滚到分区之前，再整体重新执行
+        database.rollback(1).await.unwrap();
+        let status = database.schema_status().await.unwrap();
+        assert_eq!(status.current, Some(1));
+        assert_eq!(status.pending.len() as i64, status.expected - 1);
+
+        database.rollback(0).await.unwrap();
+        database.migrate().await.unwrap();
+        database.check_schema_version().await.unwrap();
+
+        database.pool.close().await;
+        admin
+            .execute(format!("DROP DATABASE {}", name).as_str())
+            .await
+            .unwrap();
+    }
+}
@@ -3,7 +3,19 @@ use sqlx::{postgres::types::PgInterval, PgPool};
 use thiserror::Error;
 use tracing::{debug, error, info};
 
-use super::types::{MarketDataPoint, MarketDataManager};
+use super::spool::SpoolError;
+use super::types::{MarketDataPoint, MarketDataManager, TickData, TickGap, DEFAULT_EXCHANGE};
+use std::collections::HashMap;
+
+// 预汇总的 K 线周期及其来源周期，按顺序逐级汇总，所有周期最终都来自 1m
+pub const CANDLE_ROLLUPS: [(&str, &str); 6] = [
+    ("5m", "1m"),
+    ("15m", "5m"),
+    ("1h", "15m"),
+    ("4h", "1h"),
+    ("1d", "4h"),
+    ("1w", "1d"),
+];
 
 #[derive(Error, Debug)]
 pub enum MarketDataError {
@@ -13,9 +25,12 @@ pub enum MarketDataError {
     InvalidDataFormat(String),
     #[error("Data fetch error: {0}")]
     FetchError(String),
+    #[error("Spool error: {0}")]
+    SpoolError(#[from] SpoolError),
 }
 
 impl MarketDataPoint {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         timestamp: DateTime<Utc>,
         symbol: String,
@@ -41,33 +56,45 @@ impl MarketDataPoint {
 
 impl MarketDataManager {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            exchange: DEFAULT_EXCHANGE.to_string(),
+        }
     }
-    
+
+    /// 查询限定在该交易所的数据，例如 "binance"、"okx"
+    pub fn with_exchange(mut self, exchange: impl Into<String>) -> Self {
+        self.exchange = exchange.into();
+        self
+    }
+
+    pub fn exchange(&self) -> &str {
+        &self.exchange
+    }
+
     pub fn get_pool(&self) -> PgPool {
         self.pool.clone()
     }
 
-    // 存储市场数据现在实际上是存储tick数据
-    pub async fn store_market_data(
-        &self,
-        data: &MarketDataPoint,
-    ) -> Result<(), MarketDataError> {
-        debug!("Storing tick data for symbol: {}", data.symbol);
-        
-        sqlx::query!(
+    // 存储交易所推送的真实成交；(exchange, symbol, trade_id, timestamp) 已存在时跳过，返回是否实际写入
+    pub async fn store_tick_data(&self, tick: &TickData) -> Result<bool, MarketDataError> {
+        debug!("Storing {} trade {} for symbol: {}", tick.exchange, tick.trade_id, tick.symbol);
+
+        let result = sqlx::query!(
             r#"
             INSERT INTO tick_data 
-            (timestamp, symbol, price, volume, side, trade_id, is_maker)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            (exchange, timestamp, symbol, price, volume, side, trade_id, is_maker)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (exchange, symbol, trade_id, timestamp) DO NOTHING
             "#,
-            data.timestamp,
-            data.symbol,
-            data.price,
-            data.volume,
-            "BUY",  // 默认使用BUY，因为我们没有方向信息
-            format!("auto_{}", Utc::now().timestamp_nanos_opt().unwrap()),
-            false
+            tick.exchange,
+            tick.timestamp,
+            tick.symbol,
+            tick.price,
+            tick.volume,
+            tick.side.to_uppercase(),
+            tick.trade_id,
+            tick.is_maker
         )
         .execute(&self.pool)
         .await
@@ -76,8 +103,375 @@ impl MarketDataManager {
             MarketDataError::DatabaseError(e)
         })?;
 
-        debug!("Tick data stored successfully");
-        Ok(())
+        Ok(result.rows_affected() > 0)
+    }
+
+    // 批量写入成交，单条多行 INSERT，一批中可以包含多个交易所；返回实际写入的行数，其余为重复成交
+    pub async fn store_tick_batch(&self, ticks: &[TickData]) -> Result<u64, MarketDataError> {
+        if ticks.is_empty() {
+            return Ok(0);
+        }
+        debug!("Storing batch of {} ticks", ticks.len());
+
+        let mut exchanges = Vec::with_capacity(ticks.len());
+        let mut timestamps = Vec::with_capacity(ticks.len());
+        let mut symbols = Vec::with_capacity(ticks.len());
+        let mut prices = Vec::with_capacity(ticks.len());
+        let mut volumes = Vec::with_capacity(ticks.len());
+        let mut sides = Vec::with_capacity(ticks.len());
+        let mut trade_ids = Vec::with_capacity(ticks.len());
+        let mut makers = Vec::with_capacity(ticks.len());
+        for tick in ticks {
+            exchanges.push(tick.exchange.clone());
+            timestamps.push(tick.timestamp);
+            symbols.push(tick.symbol.clone());
+            prices.push(tick.price);
+            volumes.push(tick.volume);
+            sides.push(tick.side.to_uppercase());
+            trade_ids.push(tick.trade_id.clone());
+            makers.push(tick.is_maker);
+        }
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO tick_data 
+            (exchange, timestamp, symbol, price, volume, side, trade_id, is_maker)
+            SELECT * FROM UNNEST(
+                $1::varchar[], $2::timestamptz[], $3::varchar[], $4::float8[], $5::float8[],
+                $6::text[], $7::varchar[], $8::bool[]
+            )
+            ON CONFLICT (exchange, symbol, trade_id, timestamp) DO NOTHING
+            "#,
+            &exchanges,
+            &timestamps,
+            &symbols,
+            &prices,
+            &volumes,
+            &sides,
+            &trade_ids,
+            &makers
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to store tick batch: {}", e);
+            MarketDataError::DatabaseError(e)
+        })?;
+
+        Ok(result.rows_affected())
+    }
+
+    // 删除重复成交（保留最早写入的一条），并确保唯一索引存在。
+    // tick_data 按时间分区，唯一索引必须包含 timestamp，因此去重键为 (exchange, symbol, trade_id, timestamp)；
+    // 同一 trade_id 时间戳不同的成交按两笔保留
+    pub async fn remove_duplicate_ticks(&self) -> Result<u64, MarketDataError> {
+        info!("Removing duplicate tick data");
+
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM tick_data a
+            USING tick_data b
+            WHERE a.exchange = b.exchange
+            AND a.symbol = b.symbol
+            AND a.trade_id = b.trade_id
+            AND a.timestamp = b.timestamp
+            AND a.id > b.id
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to remove duplicate ticks: {}", e);
+            MarketDataError::DatabaseError(e)
+        })?;
+
+        sqlx::query(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_tick_data_exchange_symbol_trade_id ON tick_data(exchange, symbol, trade_id, timestamp)"
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to create unique tick index: {}", e);
+            MarketDataError::DatabaseError(e)
+        })?;
+
+        let removed = result.rows_affected();
+        info!("Removed {} duplicate tick data records", removed);
+        Ok(removed)
+    }
+
+    // 批量写入交易所 K 线，同一根 K 线再次写入时覆盖（最后一根可能在写入时还未收盘），
+    // 也覆盖此前由成交聚合或汇总得到的同一根 K 线
+    pub async fn store_candles(
+        &self,
+        symbol: &str,
+        timeframe: &str,
+        candles: &[MarketDataPoint],
+    ) -> Result<u64, MarketDataError> {
+        if candles.is_empty() {
+            return Ok(0);
+        }
+        debug!(
+            "Storing {} {} {} candles for symbol: {}",
+            candles.len(), self.exchange, timeframe, symbol
+        );
+
+        let mut open_times = Vec::with_capacity(candles.len());
+        let mut opens = Vec::with_capacity(candles.len());
+        let mut highs = Vec::with_capacity(candles.len());
+        let mut lows = Vec::with_capacity(candles.len());
+        let mut closes = Vec::with_capacity(candles.len());
+        let mut volumes = Vec::with_capacity(candles.len());
+        for candle in candles {
+            open_times.push(candle.timestamp);
+            opens.push(candle.open);
+            highs.push(candle.high);
+            lows.push(candle.low);
+            closes.push(candle.close);
+            volumes.push(candle.volume);
+        }
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO candles
+            (exchange, symbol, timeframe, open_time, open, high, low, close, volume, source)
+            SELECT $1::varchar, $2::varchar, $3::varchar, *, 'exchange' FROM UNNEST(
+                $4::timestamptz[], $5::float8[], $6::float8[], $7::float8[],
+                $8::float8[], $9::float8[]
+            )
+            ON CONFLICT (exchange, symbol, timeframe, open_time) DO UPDATE SET
+                open = EXCLUDED.open,
+                high = EXCLUDED.high,
+                low = EXCLUDED.low,
+                close = EXCLUDED.close,
+                volume = EXCLUDED.volume,
+                source = EXCLUDED.source,
+                updated_at = NOW()
+            "#,
+            self.exchange,
+            symbol,
+            timeframe,
+            &open_times,
+            &opens,
+            &highs,
+            &lows,
+            &closes,
+            &volumes
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to store candles: {}", e);
+            MarketDataError::DatabaseError(e)
+        })?;
+
+        Ok(result.rows_affected())
+    }
+
+    // 断点续传的起点：[start_time, end_time) 内从 start_time 开始连续存储的交易所 K 线中最后一根的开盘时间。
+    // 只看交易所 K 线，由成交聚合的 K 线不算已回补；第一根 K 线不在起点或中间有缺口时从缺口处继续
+    pub async fn get_backfilled_until(
+        &self,
+        symbol: &str,
+        timeframe: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        step: chrono::Duration,
+    ) -> Result<Option<DateTime<Utc>>, MarketDataError> {
+        let interval = PgInterval::try_from(step)
+            .map_err(|e| MarketDataError::InvalidDataFormat(format!("Invalid interval {}: {}", timeframe, e)))?;
+        let row = sqlx::query!(
+            r#"
+            WITH stored AS (
+                SELECT open_time, LEAD(open_time) OVER (ORDER BY open_time) AS next_open_time
+                FROM candles
+                WHERE exchange = $1
+                AND symbol = $2
+                AND timeframe = $3
+                AND source = 'exchange'
+                AND open_time >= $4
+                AND open_time < $5
+            )
+            SELECT
+                (SELECT MIN(open_time) FROM stored) AS first_open_time,
+                (SELECT MIN(open_time) FROM stored
+                 WHERE next_open_time IS NULL OR next_open_time > open_time + $6::interval) AS last_open_time
+            "#,
+            self.exchange,
+            symbol,
+            timeframe,
+            start_time,
+            end_time,
+            interval
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch backfilled candle range: {}", e);
+            MarketDataError::DatabaseError(e)
+        })?;
+
+        match row.first_open_time {
+            Some(first) if first < start_time + step => Ok(row.last_open_time),
+            _ => Ok(None),
+        }
+    }
+
+    // 从 candles 表读取 K 线，price 为收盘价
+    pub async fn get_candles(
+        &self,
+        symbol: &str,
+        timeframe: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<MarketDataPoint>, MarketDataError> {
+        self.query_candles(symbol, timeframe, Some(start_time), Some(end_time)).await
+    }
+
+    async fn query_candles(
+        &self,
+        symbol: &str,
+        timeframe: &str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+    ) -> Result<Vec<MarketDataPoint>, MarketDataError> {
+        debug!("Fetching {} candles for symbol: {}", timeframe, symbol);
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT open_time, symbol, open, high, low, close, volume
+            FROM candles
+            WHERE exchange = $1
+            AND symbol = $2
+            AND timeframe = $3
+            AND ($4::timestamptz IS NULL OR open_time >= $4)
+            AND ($5::timestamptz IS NULL OR open_time <= $5)
+            ORDER BY open_time ASC
+            "#,
+            self.exchange,
+            symbol,
+            timeframe,
+            start_time,
+            end_time
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch candles: {}", e);
+            MarketDataError::DatabaseError(e)
+        })?;
+
+        info!("Fetched {} candles", rows.len());
+
+        Ok(rows
+            .into_iter()
+            .map(|row| MarketDataPoint {
+                timestamp: row.open_time,
+                symbol: row.symbol,
+                price: row.close,
+                volume: row.volume,
+                high: row.high,
+                low: row.low,
+                open: row.open,
+                close: row.close,
+            })
+            .collect())
+    }
+
+    // 获取交易对最近一笔带交易所成交 ID 的成交，用于重连后确定补数据的起点
+    pub async fn get_last_trade(&self, symbol: &str) -> Result<Option<TickData>, MarketDataError> {
+        debug!("Fetching last exchange trade for symbol: {}", symbol);
+
+        let row = sqlx::query!(
+            r#"
+            SELECT exchange, timestamp, symbol, price, volume, side, trade_id, is_maker
+            FROM tick_data
+            WHERE exchange = $1
+            AND symbol = $2
+            AND trade_id ~ '^[0-9]+$'
+            ORDER BY timestamp DESC, id DESC
+            LIMIT 1
+            "#,
+            self.exchange,
+            symbol
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch last trade: {}", e);
+            MarketDataError::DatabaseError(e)
+        })?;
+
+        Ok(row.map(|row| TickData {
+            exchange: row.exchange,
+            timestamp: row.timestamp,
+            symbol: row.symbol,
+            price: row.price,
+            volume: row.volume,
+            side: row.side.trim().to_string(),
+            trade_id: row.trade_id,
+            is_maker: row.is_maker,
+        }))
+    }
+
+    // 查找相邻两笔成交间隔超过 min_gap_secs 秒的时间空洞
+    pub async fn find_tick_gaps(
+        &self,
+        symbol: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        min_gap_secs: f64,
+    ) -> Result<Vec<TickGap>, MarketDataError> {
+        debug!(
+            "Finding gaps longer than {}s for symbol: {} from {} to {}",
+            min_gap_secs, symbol, start_time, end_time
+        );
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                prev_timestamp as "start!",
+                timestamp as "end!",
+                CASE WHEN trade_id ~ '^[0-9]+$' AND prev_trade_id ~ '^[0-9]+$'
+                    THEN trade_id::BIGINT - prev_trade_id::BIGINT - 1
+                END as missing_trades
+            FROM (
+                SELECT
+                    timestamp,
+                    trade_id,
+                    LAG(timestamp) OVER (ORDER BY timestamp, id) as prev_timestamp,
+                    LAG(trade_id) OVER (ORDER BY timestamp, id) as prev_trade_id
+                FROM tick_data
+                WHERE exchange = $1
+                AND symbol = $2
+                AND timestamp BETWEEN $3 AND $4
+            ) t
+            WHERE prev_timestamp IS NOT NULL
+            AND timestamp - prev_timestamp > INTERVAL '1 second' * $5
+            ORDER BY prev_timestamp
+            "#,
+            self.exchange,
+            symbol,
+            start_time,
+            end_time,
+            min_gap_secs
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to find tick gaps: {}", e);
+            MarketDataError::DatabaseError(e)
+        })?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| TickGap {
+                symbol: symbol.to_string(),
+                start: row.start,
+                end: row.end,
+                missing_trades: row.missing_trades,
+            })
+            .collect())
     }
 
     // 获取市场数据现在需要聚合tick数据
@@ -102,11 +496,13 @@ impl MarketDataManager {
                 price as "open!",
                 price as "close!"
             FROM tick_data
-            WHERE symbol = $1 
-            AND timestamp >= $2 
-            AND timestamp <= $3
+            WHERE exchange = $1
+            AND symbol = $2 
+            AND timestamp >= $3 
+            AND timestamp <= $4
             ORDER BY timestamp ASC
             "#,
+            self.exchange,
             symbol,
             start_time,
             end_time
@@ -143,10 +539,12 @@ impl MarketDataManager {
             r#"
             SELECT price as "price!"
             FROM tick_data
-            WHERE symbol = $1
+            WHERE exchange = $1
+            AND symbol = $2
             ORDER BY timestamp DESC
             LIMIT 1
             "#,
+            self.exchange,
             symbol
         )
         .fetch_one(&self.pool)
@@ -173,9 +571,11 @@ impl MarketDataManager {
                 0.0
             ) as "vwap!"
             FROM tick_data
-            WHERE symbol = $1 
-            AND timestamp >= NOW() - INTERVAL '1 minute' * $2
+            WHERE exchange = $1
+            AND symbol = $2 
+            AND timestamp >= NOW() - INTERVAL '1 minute' * $3
             "#,
+            self.exchange,
             symbol,
             window_minutes
         )
@@ -189,6 +589,8 @@ impl MarketDataManager {
         Ok(row.vwap)
     }
     
+    // 整表 DELETE 会导致表膨胀并长时间锁表；分区表请使用 PartitionManager 的保留策略整区删除
+    #[deprecated(note = "use PartitionManager::apply_retention, which drops whole partitions")]
     pub async fn cleanup_old_data(
         &self,
         days_to_keep: f64,
@@ -219,146 +621,374 @@ impl MarketDataManager {
         Ok(deleted_count)
     }
 
-    fn get_postgres_interval(interval: &str) -> Result<(&'static str, PgInterval), MarketDataError> {
+    fn get_postgres_interval(interval: &str) -> Result<PgInterval, MarketDataError> {
         match interval.to_lowercase().as_str() {
-            "1m" => Ok(("minute", PgInterval {
+            "1m" => Ok(PgInterval {
                 months: 0,
                 days: 0,
                 microseconds: 60i64 * 1_000_000i64, // 1 minute
-            })),
-            "5m" => Ok(("minute", PgInterval {
+            }),
+            "5m" => Ok(PgInterval {
                 months: 0,
                 days: 0,
                 microseconds: 5i64 * 60i64 * 1_000_000i64, // 5 minutes
-            })),
-            "15m" => Ok(("minute", PgInterval {
+            }),
+            "15m" => Ok(PgInterval {
                 months: 0,
                 days: 0,
                 microseconds: 15i64 * 60i64 * 1_000_000i64, // 15 minutes
-            })),
-            "1h" => Ok(("hour", PgInterval {
+            }),
+            "1h" => Ok(PgInterval {
                 months: 0,
                 days: 0,
                 microseconds: 60i64 * 60i64 * 1_000_000i64, // 1 hour
-            })),
-            "4h" => Ok(("hour", PgInterval {
+            }),
+            "4h" => Ok(PgInterval {
                 months: 0,
                 days: 0,
                 microseconds: 4i64 * 60i64 * 60i64 * 1_000_000i64, // 4 hours
-            })),
-            "1d" => Ok(("day", PgInterval {
+            }),
+            "1d" => Ok(PgInterval {
                 months: 0,
                 days: 1,
                 microseconds: 0,
-            })),
-            "1w" => Ok(("week", PgInterval {
+            }),
+            "1w" => Ok(PgInterval {
                 months: 0,
                 days: 7,
                 microseconds: 0,
-            })),
+            }),
             _ => Err(MarketDataError::InvalidDataFormat(
                 format!("Unsupported interval: {}", interval)
             )),
         }
     }
-    pub async fn get_candlestick_data(
+
+    // 用 [start_time, end_time] 内的成交重算所覆盖的 1m K 线，再逐级汇总到更大周期。
+    // 按 tick_data 重新聚合而不是在旧 K 线上累加，重复写入的成交不会被重复计入，
+    // 被数据质量检查标记的成交不计入，全部被标记的分钟删除 K 线。交易所 K 线不会被覆盖或删除
+    pub async fn update_candles_from_ticks(
         &self,
         symbol: &str,
-        interval: &str,
-        start_time: Option<chrono::NaiveDateTime>,
-        end_time: Option<chrono::NaiveDateTime>,
-    ) -> Result<Vec<MarketDataPoint>, MarketDataError> {
-        let (trunc_unit, step_interval) = Self::get_postgres_interval(interval)?;
-        
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<u64, MarketDataError> {
         debug!(
-            "Fetching candlestick data for symbol: {}, truncate by: {}, step: {:?}",
-            symbol, trunc_unit, step_interval
+            "Updating {} 1m candles for symbol: {} from {} to {}",
+            self.exchange, symbol, start_time, end_time
         );
-    
-        let rows = sqlx::query!(
+
+        let result = sqlx::query!(
             r#"
-            WITH RECURSIVE time_series AS (
-                SELECT 
-                    CASE 
-                        WHEN $2::timestamp IS NOT NULL THEN date_trunc($4, $2::timestamp)
-                        ELSE date_trunc($4, MIN(timestamp))
-                    END as series_time
-                FROM tick_data
-                WHERE symbol = $1
-                AND ($2::timestamp IS NULL OR timestamp >= $2)
-                AND ($3::timestamp IS NULL OR timestamp <= $3)
-                
-                UNION ALL
-                
-                SELECT series_time + $5
-                FROM time_series
-                WHERE series_time + $5 <= (
-                    CASE 
-                        WHEN $3::timestamp IS NOT NULL THEN date_trunc($4, $3::timestamp)
-                        ELSE date_trunc($4, (SELECT MAX(timestamp) FROM tick_data WHERE symbol = $1))
-                    END
-                )
-            ),
-            interval_data AS (
-                SELECT 
-                    series_time as slot_time,
-                    first_value(td.price) OVER w as open,
-                    max(td.price) OVER w as high,
-                    min(td.price) OVER w as low,
-                    last_value(td.price) OVER w as close,
-                    sum(td.volume) OVER w as volume
-                FROM time_series ts
-                LEFT JOIN tick_data td ON 
-                    td.symbol = $1 
-                    AND td.timestamp >= ts.series_time 
-                    AND td.timestamp < ts.series_time + $5
-                WINDOW w AS (
-                    PARTITION BY ts.series_time 
-                    ORDER BY td.timestamp
-                    ROWS BETWEEN UNBOUNDED PRECEDING AND UNBOUNDED FOLLOWING
+            INSERT INTO candles
+            (exchange, symbol, timeframe, open_time, open, high, low, close, volume, source)
+            SELECT
+                exchange,
+                symbol,
+                '1m',
+                date_trunc('minute', timestamp),
+                (array_agg(price ORDER BY timestamp, id))[1],
+                MAX(price),
+                MIN(price),
+                (array_agg(price ORDER BY timestamp DESC, id DESC))[1],
+                SUM(volume),
+                'ticks'
+            FROM tick_data t
+            WHERE exchange = $1
+            AND symbol = $2
+            AND timestamp >= date_trunc('minute', $3::timestamptz)
+            AND timestamp < date_trunc('minute', $4::timestamptz) + INTERVAL '1 minute'
+            -- 排除被标记为问题数据的成交，见 IssueKind::excludes_row
+            AND NOT EXISTS (
+                SELECT 1 FROM tick_flags f
+                WHERE f.exchange = t.exchange
+                AND f.symbol = t.symbol
+                AND f.trade_id = t.trade_id
+                AND f.timestamp = t.timestamp
+                AND f.kind <> 'stale_feed'
+            )
+            GROUP BY exchange, symbol, date_trunc('minute', timestamp)
+            ON CONFLICT (exchange, symbol, timeframe, open_time) DO UPDATE SET
+                open = EXCLUDED.open,
+                high = EXCLUDED.high,
+                low = EXCLUDED.low,
+                close = EXCLUDED.close,
+                volume = EXCLUDED.volume,
+                updated_at = NOW()
+            WHERE candles.source <> 'exchange'
+            "#,
+            self.exchange,
+            symbol,
+            start_time,
+            end_time
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to update 1m candles: {}", e);
+            MarketDataError::DatabaseError(e)
+        })?;
+
+        // 一分钟内的成交全部被标记时上面的聚合没有结果，删除由这些成交聚合出的旧 K 线
+        let removed = sqlx::query!(
+            r#"
+            DELETE FROM candles c
+            WHERE c.exchange = $1
+            AND c.symbol = $2
+            AND c.timeframe = '1m'
+            AND c.source = 'ticks'
+            AND c.open_time >= date_trunc('minute', $3::timestamptz)
+            AND c.open_time < date_trunc('minute', $4::timestamptz) + INTERVAL '1 minute'
+            AND NOT EXISTS (
+                SELECT 1 FROM tick_data t
+                WHERE t.exchange = c.exchange
+                AND t.symbol = c.symbol
+                AND t.timestamp >= c.open_time
+                AND t.timestamp < c.open_time + INTERVAL '1 minute'
+                AND NOT EXISTS (
+                    SELECT 1 FROM tick_flags f
+                    WHERE f.exchange = t.exchange
+                    AND f.symbol = t.symbol
+                    AND f.trade_id = t.trade_id
+                    AND f.timestamp = t.timestamp
+                    AND f.kind <> 'stale_feed'
                 )
             )
-            SELECT DISTINCT
-                slot_time as "timestamp!",
-                $1 as "symbol!",
-                COALESCE(close, 0) as "price!",
-                COALESCE(volume, 0) as "volume!",
-                COALESCE(high, 0) as "high!",
-                COALESCE(low, 0) as "low!",
-                COALESCE(open, 0) as "open!",
-                COALESCE(close, 0) as "close!"
-            FROM interval_data
-            WHERE volume > 0
-            ORDER BY slot_time ASC
             "#,
+            self.exchange,
             symbol,
             start_time,
-            end_time,
-            trunc_unit,
-            step_interval
+            end_time
         )
-        .fetch_all(&self.pool)
+        .execute(&self.pool)
         .await
         .map_err(|e| {
-            error!("Failed to fetch candlestick data: {}", e);
+            error!("Failed to remove empty 1m candles: {}", e);
             MarketDataError::DatabaseError(e)
         })?;
-    
-        info!("Fetched {} candlestick data points", rows.len());
-    
-        Ok(rows
-            .into_iter()
-            .map(|row| MarketDataPoint {
-                timestamp: row.timestamp,
-                symbol: row.symbol,
-                price: row.price,
-                volume: row.volume,
-                high: row.high,
-                low: row.low,
-                open: row.open,
-                close: row.close,
-            })
-            .collect())
+
+        self.rollup_candles(symbol, start_time, end_time).await?;
+        Ok(result.rows_affected() + removed.rows_affected())
+    }
+
+    // 按交易所和交易对更新一批成交涉及的 K 线
+    pub async fn update_candles_for_ticks(&self, ticks: &[TickData]) -> Result<(), MarketDataError> {
+        type TimeRange = (DateTime<Utc>, DateTime<Utc>);
+        let mut ranges: HashMap<(&str, &str), TimeRange> = HashMap::new();
+        for tick in ticks {
+            let range = ranges
+                .entry((tick.exchange.as_str(), tick.symbol.as_str()))
+                .or_insert((tick.timestamp, tick.timestamp));
+            range.0 = range.0.min(tick.timestamp);
+            range.1 = range.1.max(tick.timestamp);
+        }
+
+        for ((exchange, symbol), (start_time, end_time)) in ranges {
+            self.clone()
+                .with_exchange(exchange)
+                .update_candles_from_ticks(symbol, start_time, end_time)
+                .await?;
+        }
+        Ok(())
+    }
+
+    // 升级前写入的成交没有对应的 K 线，K 线查询只读 candles 表，需要先由成交生成一次
+    pub async fn candles_missing(&self) -> Result<bool, MarketDataError> {
+        let missing = sqlx::query_scalar!(
+            r#"
+            SELECT NOT EXISTS (SELECT 1 FROM candles) AND EXISTS (SELECT 1 FROM tick_data) AS "missing!"
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(MarketDataError::DatabaseError)?;
+        Ok(missing)
+    }
+
+    // 由 tick_data 中全部交易所和交易对的成交生成 K 线，返回写入或删除的 1m K 线数量
+    pub async fn materialize_candles(&self) -> Result<u64, MarketDataError> {
+        let ranges = sqlx::query!(
+            r#"
+            SELECT exchange, symbol, MIN(timestamp) AS "start!", MAX(timestamp) AS "end!"
+            FROM tick_data
+            GROUP BY exchange, symbol
+            ORDER BY exchange, symbol
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(MarketDataError::DatabaseError)?;
+
+        let mut updated = 0;
+        for range in ranges {
+            info!("Building {} {} candles from trades since {}", range.exchange, range.symbol, range.start);
+            updated += self
+                .clone()
+                .with_exchange(&range.exchange)
+                .rebuild_candles(&range.symbol, range.start, range.end)
+                .await?;
+        }
+        Ok(updated)
+    }
+
+    // 按天分段重算 [start_time, end_time] 内由成交聚合的 K 线，避免单条语句扫描整段历史
+    pub async fn rebuild_candles(
+        &self,
+        symbol: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<u64, MarketDataError> {
+        let mut updated = 0;
+        let mut cursor = start_time;
+        loop {
+            let next = (cursor + chrono::Duration::days(1)).min(end_time);
+            updated += self.update_candles_from_ticks(symbol, cursor, next).await?;
+            if next >= end_time {
+                return Ok(updated);
+            }
+            cursor = next;
+        }
+    }
+
+    // 由 1m K 线逐级汇总出 CANDLE_ROLLUPS 中的各周期，只重算 [start_time, end_time] 覆盖的 K 线。
+    // 只写入已结束的周期：1m K 线已经推进到周期结束之后才算完整。上次调用时尚未结束的周期
+    // 在这里补上，所以起点向前延伸到上一根源 K 线所在的周期。交易所 K 线不会被覆盖
+    pub async fn rollup_candles(
+        &self,
+        symbol: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<u64, MarketDataError> {
+        let mut updated = 0;
+        for (timeframe, source) in CANDLE_ROLLUPS {
+            let step = Self::get_postgres_interval(timeframe)?;
+
+            // 以 2000-01-03（周一）为原点分桶，周线从周一开始
+            let result = sqlx::query!(
+                r#"
+                WITH bounds AS (
+                    SELECT
+                        date_bin(
+                            $5::interval,
+                            COALESCE(
+                                (SELECT MAX(open_time) FROM candles
+                                 WHERE exchange = $1 AND symbol = $2 AND timeframe = $4
+                                 AND open_time < date_bin($5::interval, $6::timestamptz, TIMESTAMPTZ '2000-01-03 00:00:00+00')),
+                                $6::timestamptz
+                            ),
+                            TIMESTAMPTZ '2000-01-03 00:00:00+00'
+                        ) AS first_bucket,
+                        (SELECT MAX(open_time) FROM candles
+                         WHERE exchange = $1 AND symbol = $2 AND timeframe = '1m') AS complete_before
+                )
+                INSERT INTO candles
+                (exchange, symbol, timeframe, open_time, open, high, low, close, volume, source)
+                SELECT
+                    exchange,
+                    symbol,
+                    $3::varchar,
+                    bucket,
+                    (array_agg(open ORDER BY open_time))[1],
+                    MAX(high),
+                    MIN(low),
+                    (array_agg(close ORDER BY open_time DESC))[1],
+                    SUM(volume),
+                    'rollup'
+                FROM (
+                    SELECT
+                        c.*,
+                        date_bin($5::interval, c.open_time, TIMESTAMPTZ '2000-01-03 00:00:00+00') as bucket
+                    FROM candles c, bounds b
+                    WHERE c.exchange = $1
+                    AND c.symbol = $2
+                    AND c.timeframe = $4
+                    AND c.open_time >= b.first_bucket
+                    AND c.open_time < date_bin($5::interval, $7::timestamptz, TIMESTAMPTZ '2000-01-03 00:00:00+00') + $5::interval
+                ) parts, bounds
+                WHERE parts.bucket + $5::interval <= bounds.complete_before
+                GROUP BY exchange, symbol, bucket
+                ON CONFLICT (exchange, symbol, timeframe, open_time) DO UPDATE SET
+                    open = EXCLUDED.open,
+                    high = EXCLUDED.high,
+                    low = EXCLUDED.low,
+                    close = EXCLUDED.close,
+                    volume = EXCLUDED.volume,
+                    updated_at = NOW()
+                WHERE candles.source <> 'exchange'
+                "#,
+                self.exchange,
+                symbol,
+                timeframe,
+                source,
+                step,
+                start_time,
+                end_time
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                error!("Failed to roll up {} candles: {}", timeframe, e);
+                MarketDataError::DatabaseError(e)
+            })?;
+            updated += result.rows_affected();
+
+            // 下级 K 线被删除后，没有下级 K 线的汇总 K 线也要删除
+            let removed = sqlx::query!(
+                r#"
+                DELETE FROM candles c
+                WHERE c.exchange = $1
+                AND c.symbol = $2
+                AND c.timeframe = $3
+                AND c.source = 'rollup'
+                AND c.open_time >= date_bin($5::interval, $6::timestamptz, TIMESTAMPTZ '2000-01-03 00:00:00+00')
+                AND c.open_time < date_bin($5::interval, $7::timestamptz, TIMESTAMPTZ '2000-01-03 00:00:00+00') + $5::interval
+                AND NOT EXISTS (
+                    SELECT 1 FROM candles s
+                    WHERE s.exchange = c.exchange
+                    AND s.symbol = c.symbol
+                    AND s.timeframe = $4
+                    AND s.open_time >= c.open_time
+                    AND s.open_time < c.open_time + $5::interval
+                )
+                "#,
+                self.exchange,
+                symbol,
+                timeframe,
+                source,
+                step,
+                start_time,
+                end_time
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                error!("Failed to remove empty {} candles: {}", timeframe, e);
+                MarketDataError::DatabaseError(e)
+            })?;
+            updated += removed.rows_affected();
+        }
+
+        Ok(updated)
+    }
+
+    // 读取预先汇总好的 K 线，按时间范围扫描 candles 表
+    pub async fn get_candlestick_data(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: Option<chrono::NaiveDateTime>,
+        end_time: Option<chrono::NaiveDateTime>,
+    ) -> Result<Vec<MarketDataPoint>, MarketDataError> {
+        let timeframe = interval.to_lowercase();
+        Self::get_postgres_interval(&timeframe)?;
+
+        self.query_candles(
+            symbol,
+            &timeframe,
+            start_time.map(|time| time.and_utc()),
+            end_time.map(|time| time.and_utc()),
+        )
+        .await
     }
 }
 
@@ -383,49 +1013,394 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_market_data_operations() {
+    async fn test_store_tick_data() {
         let pool = setup_test_db().await;
         let manager = MarketDataManager::new(pool);
 
-        let timestamp = Utc::now();
-        let test_data = MarketDataPoint::new(
-            timestamp,
-            "BTC/USDT".to_string(), 
-            50000.0,
-            1.5,
-            51000.0,
-            49000.0,
-            49500.0,
-            50000.0,
-        );
+        let tick = TickData {
+            exchange: "binance".to_string(),
+            timestamp: Utc::now(),
+            symbol: "TEST/TICK".to_string(),
+            price: 50000.0,
+            volume: 0.25,
+            side: "sell".to_string(),
+            trade_id: "123456789".to_string(),
+            is_maker: true,
+        };
 
-        // 清理旧数据
-        sqlx::query!("DELETE FROM tick_data WHERE symbol = $1", test_data.symbol)
+        sqlx::query!("DELETE FROM tick_data WHERE symbol = $1", tick.symbol)
             .execute(&manager.pool)
             .await
             .expect("Failed to clean up old test data");
 
-        // 存储数据
-        manager.store_market_data(&test_data)
+        let inserted = manager.store_tick_data(&tick)
             .await
-            .expect("Failed to store market data");
+            .expect("Failed to store tick data");
+        assert!(inserted);
 
-        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        // 重复写入同一成交会被忽略
+        let inserted = manager.store_tick_data(&tick)
+            .await
+            .expect("Failed to store duplicate tick data");
+        assert!(!inserted);
 
-        // 验证数据
-        let start_time = timestamp - Duration::hours(1);
-        let end_time = timestamp + Duration::hours(1);
-        
-        let retrieved_data = manager
-            .get_market_data(&test_data.symbol, start_time, end_time)
+        let row = sqlx::query!(
+            "SELECT side, trade_id, is_maker FROM tick_data WHERE symbol = $1",
+            tick.symbol
+        )
+        .fetch_one(&manager.pool)
+        .await
+        .expect("Failed to fetch stored tick");
+
+        assert_eq!(row.side, "SELL");
+        assert_eq!(row.trade_id, tick.trade_id);
+        assert!(row.is_maker);
+    }
+
+    #[tokio::test]
+    async fn test_dedup_keeps_same_trade_id_at_different_timestamps() {
+        let pool = setup_test_db().await;
+        let manager = MarketDataManager::new(pool);
+
+        let tick = TickData {
+            exchange: "binance".to_string(),
+            timestamp: Utc::now(),
+            symbol: "TEST/DEDUP".to_string(),
+            price: 50000.0,
+            volume: 0.25,
+            side: "BUY".to_string(),
+            trade_id: "42".to_string(),
+            is_maker: false,
+        };
+        let later = TickData {
+            timestamp: tick.timestamp + Duration::seconds(1),
+            ..tick.clone()
+        };
+
+        sqlx::query!("DELETE FROM tick_data WHERE symbol = $1", tick.symbol)
+            .execute(&manager.pool)
             .await
-            .expect("Failed to retrieve market data");
+            .expect("Failed to clean up old test data");
 
-        assert!(!retrieved_data.is_empty(), "Retrieved data should not be empty");
-        
-        let first_item = &retrieved_data[0];
-        assert_eq!(first_item.symbol, test_data.symbol, "Symbol mismatch");
-        assert_eq!(first_item.price, test_data.price, "Price mismatch");
-        assert_eq!(first_item.volume, test_data.volume, "Volume mismatch");
+        // 唯一键包含 timestamp，同一 trade_id 时间戳不同时两笔都写入，去重后也都保留
+        assert!(manager.store_tick_data(&tick).await.unwrap());
+        assert!(manager.store_tick_data(&later).await.unwrap());
+        assert!(!manager.store_tick_data(&later).await.unwrap());
+        manager.remove_duplicate_ticks().await.unwrap();
+
+        let count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM tick_data WHERE symbol = $1 AND trade_id = $2",
+            tick.symbol,
+            tick.trade_id
+        )
+        .fetch_one(&manager.pool)
+        .await
+        .unwrap();
+        assert_eq!(count, Some(2));
+
+        sqlx::query!("DELETE FROM tick_data WHERE symbol = $1", tick.symbol)
+            .execute(&manager.pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_store_tick_batch_skips_duplicates() {
+        let pool = setup_test_db().await;
+        let manager = MarketDataManager::new(pool);
+
+        let symbol = "TEST/BATCH".to_string();
+        let ticks: Vec<TickData> = (0..3)
+            .map(|i| TickData {
+                exchange: "binance".to_string(),
+                timestamp: Utc::now(),
+                symbol: symbol.clone(),
+                price: 100.0 + i as f64,
+                volume: 1.0,
+                side: "BUY".to_string(),
+                trade_id: format!("batch_{}", i),
+                is_maker: false,
+            })
+            .collect();
+
+        sqlx::query!("DELETE FROM tick_data WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .expect("Failed to clean up old test data");
+
+        let stored = manager.store_tick_batch(&ticks[..2])
+            .await
+            .expect("Failed to store tick batch");
+        assert_eq!(stored, 2);
+
+        // 与上一批重叠的成交只写入新的那条
+        let stored = manager.store_tick_batch(&ticks)
+            .await
+            .expect("Failed to store overlapping tick batch");
+        assert_eq!(stored, 1);
+    }
+
+    #[tokio::test]
+    async fn test_find_tick_gaps_and_last_trade() {
+        let pool = setup_test_db().await;
+        let manager = MarketDataManager::new(pool);
+
+        let symbol = "TEST/GAPS".to_string();
+        let base = Utc::now() - Duration::minutes(30);
+        // 第 2 和第 3 笔之间间隔 10 分钟，且缺了 5 笔成交
+        let ticks: Vec<TickData> = [(0, 100), (5, 101), (605, 107), (610, 108)]
+            .iter()
+            .map(|(offset_secs, trade_id)| TickData {
+                exchange: "binance".to_string(),
+                timestamp: base + Duration::seconds(*offset_secs),
+                symbol: symbol.clone(),
+                price: 100.0,
+                volume: 1.0,
+                side: "SELL".to_string(),
+                trade_id: trade_id.to_string(),
+                is_maker: true,
+            })
+            .collect();
+
+        sqlx::query!("DELETE FROM tick_data WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .expect("Failed to clean up old test data");
+        manager.store_tick_batch(&ticks).await.expect("Failed to store ticks");
+
+        let gaps = manager
+            .find_tick_gaps(&symbol, base - Duration::hours(1), Utc::now(), 60.0)
+            .await
+            .expect("Failed to find gaps");
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].start.timestamp_millis(), ticks[1].timestamp.timestamp_millis());
+        assert_eq!(gaps[0].end.timestamp_millis(), ticks[2].timestamp.timestamp_millis());
+        assert_eq!(gaps[0].missing_trades, Some(5));
+
+        let last = manager.get_last_trade(&symbol)
+            .await
+            .expect("Failed to fetch last trade")
+            .expect("Last trade should exist");
+        assert_eq!(last.trade_id, "108");
+        assert_eq!(last.side, "SELL");
+
+        sqlx::query!("DELETE FROM tick_data WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .expect("Failed to clean up test data");
+    }
+
+    #[tokio::test]
+    async fn test_candles_roll_up_from_ticks() {
+        let pool = setup_test_db().await;
+        let manager = MarketDataManager::new(pool);
+
+        let symbol = "TEST/CANDLES".to_string();
+        let base = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        // (秒偏移, 价格, 数量)：前两笔在 00:00，第三笔在 00:04，最后一笔在下一个 5 分钟
+        let trades = [(10, 100.0, 1.0), (50, 105.0, 2.0), (250, 95.0, 1.0), (310, 110.0, 0.5)];
+        let ticks: Vec<TickData> = trades
+            .iter()
+            .enumerate()
+            .map(|(i, (offset_secs, price, volume))| TickData {
+                exchange: "binance".to_string(),
+                timestamp: base + Duration::seconds(*offset_secs),
+                symbol: symbol.clone(),
+                price: *price,
+                volume: *volume,
+                side: "BUY".to_string(),
+                trade_id: format!("candle_{}", i),
+                is_maker: false,
+            })
+            .collect();
+
+        sqlx::query!("DELETE FROM tick_data WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .expect("Failed to clean up old test data");
+        sqlx::query!("DELETE FROM candles WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .expect("Failed to clean up old test candles");
+
+        manager.store_tick_batch(&ticks).await.expect("Failed to store ticks");
+        manager.update_candles_for_ticks(&ticks).await.expect("Failed to update candles");
+        // 重复更新结果不变
+        manager.update_candles_for_ticks(&ticks).await.expect("Failed to update candles");
+
+        let end = base + Duration::hours(1);
+        let one_minute = manager.get_candles(&symbol, "1m", base, end).await.unwrap();
+        assert_eq!(one_minute.len(), 3);
+        assert_eq!(
+            (one_minute[0].open, one_minute[0].high, one_minute[0].low, one_minute[0].close, one_minute[0].volume),
+            (100.0, 105.0, 100.0, 105.0, 3.0)
+        );
+
+        // 只汇总已结束的周期：00:05 的 5m 和当天的日线都还没结束
+        let five_minute = manager.get_candles(&symbol, "5m", base, end).await.unwrap();
+        assert_eq!(five_minute.len(), 1);
+        assert_eq!(
+            (five_minute[0].open, five_minute[0].high, five_minute[0].low, five_minute[0].close, five_minute[0].volume),
+            (100.0, 105.0, 95.0, 95.0, 4.0)
+        );
+        let daily = manager
+            .get_candlestick_data(&symbol, "1d", Some(base.naive_utc()), None)
+            .await
+            .unwrap();
+        assert!(daily.is_empty());
+
+        // 第二天的成交让之前未结束的周期补齐
+        let next_day = TickData {
+            timestamp: base + Duration::days(1) + Duration::seconds(30),
+            price: 120.0,
+            volume: 1.0,
+            trade_id: "candle_next_day".to_string(),
+            ..ticks[0].clone()
+        };
+        manager.store_tick_batch(std::slice::from_ref(&next_day)).await.expect("Failed to store tick");
+        manager
+            .update_candles_for_ticks(std::slice::from_ref(&next_day))
+            .await
+            .expect("Failed to update candles");
+
+        let five_minute = manager.get_candles(&symbol, "5m", base, end).await.unwrap();
+        assert_eq!(five_minute.len(), 2);
+        assert_eq!(five_minute[1].timestamp, base + Duration::minutes(5));
+
+        let daily = manager
+            .get_candlestick_data(&symbol, "1d", Some(base.naive_utc()), None)
+            .await
+            .unwrap();
+        assert_eq!(daily.len(), 1);
+        assert_eq!((daily[0].open, daily[0].high, daily[0].low, daily[0].close), (100.0, 110.0, 95.0, 110.0));
+        assert_eq!(daily[0].volume, 4.5);
+
+        sqlx::query!("DELETE FROM tick_data WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .expect("Failed to clean up test data");
+        sqlx::query!("DELETE FROM candles WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .expect("Failed to clean up test candles");
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_candles_from_stored_ticks() {
+        let pool = setup_test_db().await;
+        let manager = MarketDataManager::new(pool);
+        let symbol = "TEST/REBUILD";
+        for table in ["tick_data", "candles"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE symbol = $1", table))
+                .bind(symbol)
+                .execute(&manager.pool)
+                .await
+                .unwrap();
+        }
+
+        // 升级前写入的成交：跨两天，每 10 分钟一笔，只写 tick_data
+        let start = DateTime::parse_from_rfc3339("2024-04-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let ticks: Vec<TickData> = (0..300)
+            .map(|i| TickData {
+                exchange: "binance".to_string(),
+                timestamp: start + Duration::minutes(10 * i),
+                symbol: symbol.to_string(),
+                price: 100.0 + i as f64,
+                volume: 1.0,
+                side: "BUY".to_string(),
+                trade_id: i.to_string(),
+                is_maker: false,
+            })
+            .collect();
+        manager.store_tick_batch(&ticks).await.unwrap();
+        let end = ticks.last().unwrap().timestamp;
+        assert!(manager.get_candles(symbol, "1m", start, end).await.unwrap().is_empty());
+
+        manager.rebuild_candles(symbol, start, end).await.unwrap();
+        let minutes = manager.get_candles(symbol, "1m", start, end + Duration::minutes(1)).await.unwrap();
+        assert_eq!(minutes.len(), 300);
+        let hours = manager.get_candles(symbol, "1h", start, end).await.unwrap();
+        assert_eq!(hours.len(), 49);
+        assert_eq!((hours[0].open, hours[0].close, hours[0].volume), (100.0, 105.0, 6.0));
+
+        for table in ["tick_data", "candles"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE symbol = $1", table))
+                .bind(symbol)
+                .execute(&manager.pool)
+                .await
+                .unwrap();
+        }
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_tick_candles_keep_exchange_klines() {
+        let pool = setup_test_db().await;
+        let manager = MarketDataManager::new(pool);
+
+        let symbol = "TEST/KLINESRC".to_string();
+        let base = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        sqlx::query!("DELETE FROM tick_data WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .expect("Failed to clean up old test data");
+        sqlx::query!("DELETE FROM candles WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .expect("Failed to clean up old test candles");
+
+        let kline = |timestamp| MarketDataPoint {
+            timestamp,
+            symbol: symbol.clone(),
+            price: 101.0,
+            volume: 50.0,
+            high: 102.0,
+            low: 99.0,
+            open: 100.0,
+            close: 101.0,
+        };
+        manager.store_candles(&symbol, "1m", &[kline(base)]).await.unwrap();
+        manager.store_candles(&symbol, "5m", &[kline(base)]).await.unwrap();
+
+        // 同一分钟内只收到部分成交，下一个 5 分钟的成交让 00:00 的 5m 周期结束
+        let ticks: Vec<TickData> = [(10, 150.0), (400, 151.0)]
+            .iter()
+            .enumerate()
+            .map(|(i, (offset_secs, price))| TickData {
+                exchange: "binance".to_string(),
+                timestamp: base + Duration::seconds(*offset_secs),
+                symbol: symbol.clone(),
+                price: *price,
+                volume: 1.0,
+                side: "BUY".to_string(),
+                trade_id: format!("kline_src_{}", i),
+                is_maker: false,
+            })
+            .collect();
+        manager.store_tick_batch(&ticks).await.expect("Failed to store ticks");
+        manager.update_candles_for_ticks(&ticks).await.expect("Failed to update candles");
+
+        let end = base + Duration::hours(1);
+        for timeframe in ["1m", "5m"] {
+            let candles = manager.get_candles(&symbol, timeframe, base, end).await.unwrap();
+            let first = &candles[0];
+            assert_eq!(
+                (first.open, first.high, first.low, first.close, first.volume),
+                (100.0, 102.0, 99.0, 101.0, 50.0),
+                "{} exchange kline was overwritten",
+                timeframe
+            );
+        }
+        let one_minute = manager.get_candles(&symbol, "1m", base, end).await.unwrap();
+        assert_eq!(one_minute.len(), 2);
+        assert_eq!(one_minute[1].open, 151.0);
+
+        sqlx::query!("DELETE FROM tick_data WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .expect("Failed to clean up test data");
+        sqlx::query!("DELETE FROM candles WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .expect("Failed to clean up test candles");
+    }
+}
@@ -1,4 +1,10 @@
 pub mod types;
 pub mod cache;
 pub mod database;
-pub mod market_data;
\ No newline at end of file
+pub mod market_data;
+pub mod batch_writer;
+pub mod spool;
+pub mod backfill;
+pub mod partition;
+pub mod transfer;
+pub mod quality;
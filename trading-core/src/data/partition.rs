@@ -0,0 +1,544 @@
+// trading-core/src/data/partition.rs
+// tick_data 按时间范围分区：按天或按月预先创建分区，过期数据整区删除。
+// 落在已有分区之外的数据进入默认分区，创建对应分区时再从默认分区迁出。
+
+use super::market_data::MarketDataError;
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tracing::{debug, error, info, warn};
+
+const PARENT_TABLE: &str = "tick_data";
+const DEFAULT_PARTITION: &str = "tick_data_default";
+const PARTITION_PREFIX: &str = "tick_data_p";
+// 逐行删除时每条语句最多删除的行数，避免长事务和大量锁
+const DELETE_BATCH_ROWS: i64 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartitionInterval {
+    Day,
+    Month,
+}
+
+impl PartitionInterval {
+    // 包含 date 的分区的起始日期
+    fn period_start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            PartitionInterval::Day => date,
+            PartitionInterval::Month => date.with_day(1).expect("day 1 always exists"),
+        }
+    }
+
+    fn next_period(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            PartitionInterval::Day => start + Duration::days(1),
+            PartitionInterval::Month => start + Months::new(1),
+        }
+    }
+
+    fn partition_name(self, start: NaiveDate) -> String {
+        match self {
+            PartitionInterval::Day => format!("{}{}", PARTITION_PREFIX, start.format("%Y%m%d")),
+            PartitionInterval::Month => format!("{}{}", PARTITION_PREFIX, start.format("%Y%m")),
+        }
+    }
+}
+
+// 保留策略：未单独配置的交易对使用 default_days；None 表示永久保留
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub default_days: Option<u32>,
+    #[serde(default)]
+    pub per_symbol: HashMap<String, u32>,
+}
+
+impl RetentionPolicy {
+    // 所有交易对中最长的保留天数；早于它的分区可以整区删除
+    fn longest_days(&self) -> Option<u32> {
+        let default_days = self.default_days?;
+        Some(self.per_symbol.values().copied().fold(default_days, u32::max))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PartitionConfig {
+    pub interval: PartitionInterval,
+    // 提前创建的未来分区数
+    pub premake: u32,
+    pub retention: RetentionPolicy,
+}
+
+impl Default for PartitionConfig {
+    fn default() -> Self {
+        Self {
+            interval: PartitionInterval::Day,
+            premake: 7,
+            retention: RetentionPolicy::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PartitionInfo {
+    pub name: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RetentionReport {
+    pub dropped_partitions: Vec<String>,
+    // 保留期短于最长保留期的交易对，在未删除分区中分批逐行删除的数量
+    pub deleted_rows: u64,
+}
+
+pub struct PartitionManager {
+    pool: PgPool,
+    config: PartitionConfig,
+}
+
+impl PartitionManager {
+    pub fn new(pool: PgPool, config: PartitionConfig) -> Self {
+        Self { pool, config }
+    }
+
+    pub fn config(&self) -> &PartitionConfig {
+        &self.config
+    }
+
+    /// 创建从当前周期开始的 premake 个未来分区，并执行保留策略
+    pub async fn maintain(&self, now: DateTime<Utc>) -> Result<RetentionReport, MarketDataError> {
+        let end = now + self.premake_span(now);
+        let created = self.ensure_partitions(now, end).await?;
+        if !created.is_empty() {
+            info!("Created tick_data partitions: {:?}", created);
+        }
+        self.apply_retention(now).await
+    }
+
+    /// 确保 [start, end] 覆盖的每个周期都有分区，返回新建的分区名。
+    /// 已有分区（包括按其他周期创建的）与目标周期重叠时跳过该周期。
+    pub async fn ensure_partitions(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<String>, MarketDataError> {
+        let existing = self.list_partitions().await?;
+        let interval = self.config.interval;
+        let mut created = Vec::new();
+
+        let mut period = interval.period_start(start.date_naive());
+        while period <= end.date_naive() {
+            let next = interval.next_period(period);
+            let (from, to) = (Self::midnight(period), Self::midnight(next));
+            let overlaps = existing.iter().any(|p| p.start < to && from < p.end);
+            if !overlaps {
+                let name = interval.partition_name(period);
+                self.create_partition(&name, from, to).await?;
+                created.push(name);
+            }
+            period = next;
+        }
+
+        Ok(created)
+    }
+
+    /// 按时间顺序列出 tick_data 的分区（不含默认分区）
+    pub async fn list_partitions(&self) -> Result<Vec<PartitionInfo>, MarketDataError> {
+        let names: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT child.relname::text
+            FROM pg_inherits
+            JOIN pg_class parent ON pg_inherits.inhparent = parent.oid
+            JOIN pg_class child ON pg_inherits.inhrelid = child.oid
+            WHERE parent.relname = $1
+            "#,
+        )
+        .bind(PARENT_TABLE)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to list tick_data partitions: {}", e);
+            MarketDataError::DatabaseError(e)
+        })?;
+
+        let mut partitions: Vec<PartitionInfo> = names
+            .iter()
+            .filter(|name| name.as_str() != DEFAULT_PARTITION)
+            .filter_map(|name| {
+                let info = Self::parse_partition_name(name);
+                if info.is_none() {
+                    warn!("Ignoring unmanaged tick_data partition {}", name);
+                }
+                info
+            })
+            .collect();
+        partitions.sort_by_key(|p| p.start);
+        Ok(partitions)
+    }
+
+    /// 删除所有交易对都已过保留期的分区；保留期较短的交易对在剩余分区中逐行删除。
+    /// 单独配置的保留期长于 default_days 时，分区要等该交易对也过期才能整区删除，
+    /// 其余交易对的过期数据在此之前只能逐分区分批删除，开销远大于整区删除
+    pub async fn apply_retention(&self, now: DateTime<Utc>) -> Result<RetentionReport, MarketDataError> {
+        let policy = &self.config.retention;
+        let mut report = RetentionReport::default();
+        let longest_days = policy.longest_days();
+        let cutoff = |days: u32| now - Duration::days(days as i64);
+        if let (Some(longest), Some(default_days)) = (longest_days, policy.default_days) {
+            if longest > default_days {
+                warn!(
+                    "Per-symbol retention of {} days keeps partitions past the default {} days; other symbols are deleted row by row",
+                    longest, default_days
+                );
+            }
+        }
+
+        if let Some(longest_days) = longest_days {
+            let drop_before = cutoff(longest_days);
+            for partition in self.list_partitions().await? {
+                if partition.end <= drop_before {
+                    self.drop_partition(&partition.name).await?;
+                    report.dropped_partitions.push(partition.name);
+                }
+            }
+            // 默认分区中过期的零散数据
+            report.deleted_rows += self.delete_rows(DEFAULT_PARTITION, drop_before, None, &[]).await?;
+        }
+
+        // 使用默认保留期的交易对
+        if let Some(default_days) = policy.default_days.filter(|days| Some(*days) < longest_days) {
+            let keep: Vec<String> = policy.per_symbol.keys().cloned().collect();
+            let before = cutoff(default_days);
+            for table in self.tables_before(before).await? {
+                report.deleted_rows += self.delete_rows(&table, before, None, &keep).await?;
+            }
+        }
+        for (symbol, days) in &policy.per_symbol {
+            if longest_days.is_none_or(|longest| *days < longest) {
+                let before = cutoff(*days);
+                for table in self.tables_before(before).await? {
+                    report.deleted_rows += self.delete_rows(&table, before, Some(symbol), &[]).await?;
+                }
+            }
+        }
+
+        if !report.dropped_partitions.is_empty() || report.deleted_rows > 0 {
+            info!(
+                "Retention dropped {} partitions and deleted {} rows",
+                report.dropped_partitions.len(), report.deleted_rows
+            );
+        }
+        Ok(report)
+    }
+
+    // 新建分区表，把默认分区中属于该范围的数据迁入后再挂载
+    async fn create_partition(
+        &self,
+        name: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<(), MarketDataError> {
+        debug!("Creating partition {} for [{}, {})", name, from, to);
+        let (from, to) = (from.to_rfc3339(), to.to_rfc3339());
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(&format!(
+            "CREATE TABLE {} (LIKE {} INCLUDING DEFAULTS INCLUDING CONSTRAINTS)",
+            name, PARENT_TABLE
+        ))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(&format!(
+            r#"
+            WITH moved AS (
+                DELETE FROM {} WHERE timestamp >= '{}' AND timestamp < '{}' RETURNING *
+            )
+            INSERT INTO {} SELECT * FROM moved
+            "#,
+            DEFAULT_PARTITION, from, to, name
+        ))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(&format!(
+            "ALTER TABLE {} ATTACH PARTITION {} FOR VALUES FROM ('{}') TO ('{}')",
+            PARENT_TABLE, name, from, to
+        ))
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await.map_err(|e| {
+            error!("Failed to create partition {}: {}", name, e);
+            MarketDataError::DatabaseError(e)
+        })
+    }
+
+    async fn drop_partition(&self, name: &str) -> Result<(), MarketDataError> {
+        info!("Dropping expired partition {}", name);
+        sqlx::query(&format!("DROP TABLE IF EXISTS {}", name))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                error!("Failed to drop partition {}: {}", name, e);
+                MarketDataError::DatabaseError(e)
+            })?;
+        Ok(())
+    }
+
+    // symbol 为 None 时只清理默认分区
+    // 可能含有早于 cutoff 的数据的分区，包括默认分区
+    async fn tables_before(&self, cutoff: DateTime<Utc>) -> Result<Vec<String>, MarketDataError> {
+        let mut tables: Vec<String> = self
+            .list_partitions()
+            .await?
+            .into_iter()
+            .filter(|partition| partition.start < cutoff)
+            .map(|partition| partition.name)
+            .collect();
+        tables.push(DEFAULT_PARTITION.to_string());
+        Ok(tables)
+    }
+
+    // 在单个分区中分批删除早于 cutoff 的成交：指定 symbol 时只删除该交易对，否则删除 keep_symbols 以外的交易对
+    async fn delete_rows(
+        &self,
+        table: &str,
+        cutoff: DateTime<Utc>,
+        symbol: Option<&str>,
+        keep_symbols: &[String],
+    ) -> Result<u64, MarketDataError> {
+        let mut deleted = 0;
+        loop {
+            let result = sqlx::query(&format!(
+                r#"
+                DELETE FROM {table} WHERE ctid IN (
+                    SELECT ctid FROM {table}
+                    WHERE timestamp < $1
+                    AND ($2::varchar IS NULL OR symbol = $2)
+                    AND NOT (symbol = ANY($3))
+                    LIMIT $4
+                )
+                "#,
+                table = table
+            ))
+            .bind(cutoff)
+            .bind(symbol)
+            .bind(keep_symbols)
+            .bind(DELETE_BATCH_ROWS)
+            .execute(&self.pool)
+            .await
+            .map_err(MarketDataError::DatabaseError)?;
+            deleted += result.rows_affected();
+            if result.rows_affected() < DELETE_BATCH_ROWS as u64 {
+                debug!("Deleted {} expired rows from {}", deleted, table);
+                return Ok(deleted);
+            }
+        }
+    }
+
+    fn premake_span(&self, now: DateTime<Utc>) -> Duration {
+        let interval = self.config.interval;
+        let mut end = interval.period_start(now.date_naive());
+        for _ in 0..self.config.premake {
+            end = interval.next_period(end);
+        }
+        Self::midnight(end) - now
+    }
+
+    fn midnight(date: NaiveDate) -> DateTime<Utc> {
+        date.and_hms_opt(0, 0, 0).expect("midnight is valid").and_utc()
+    }
+
+    fn parse_partition_name(name: &str) -> Option<PartitionInfo> {
+        let suffix = name.strip_prefix(PARTITION_PREFIX)?;
+        let (interval, start) = match suffix.len() {
+            8 => (PartitionInterval::Day, NaiveDate::parse_from_str(suffix, "%Y%m%d").ok()?),
+            6 => (
+                PartitionInterval::Month,
+                NaiveDate::parse_from_str(&format!("{}01", suffix), "%Y%m%d").ok()?,
+            ),
+            _ => return None,
+        };
+        Some(PartitionInfo {
+            name: name.to_string(),
+            start: Self::midnight(start),
+            end: Self::midnight(interval.next_period(start)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use dotenv::dotenv;
+    use sqlx::postgres::PgPoolOptions;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_partition_periods_and_names() {
+        let day = PartitionInterval::Day;
+        assert_eq!(day.next_period(date(2024, 2, 28)), date(2024, 2, 29));
+        assert_eq!(day.partition_name(date(2024, 2, 28)), "tick_data_p20240228");
+
+        let month = PartitionInterval::Month;
+        assert_eq!(month.period_start(date(2024, 12, 15)), date(2024, 12, 1));
+        assert_eq!(month.next_period(date(2024, 12, 1)), date(2025, 1, 1));
+        assert_eq!(month.partition_name(date(2024, 12, 1)), "tick_data_p202412");
+
+        let info = PartitionManager::parse_partition_name("tick_data_p202412").unwrap();
+        assert_eq!(info.start.date_naive(), date(2024, 12, 1));
+        assert_eq!(info.end.date_naive(), date(2025, 1, 1));
+        assert!(PartitionManager::parse_partition_name("tick_data_default").is_none());
+    }
+
+    #[test]
+    fn test_longest_retention() {
+        let mut policy = RetentionPolicy::default();
+        assert_eq!(policy.longest_days(), None);
+
+        policy.default_days = Some(30);
+        policy.per_symbol.insert("BTCUSDT".to_string(), 365);
+        policy.per_symbol.insert("DOGEUSDT".to_string(), 7);
+        assert_eq!(policy.longest_days(), Some(365));
+    }
+
+    #[tokio::test]
+    async fn test_partitions_created_and_dropped() {
+        dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL")
+            .expect("DATABASE_URL must be set for tests");
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&database_url)
+            .await
+            .expect("Failed to create test database pool");
+
+        // 使用远早于其他测试数据的日期，避免影响并发运行的测试
+        let symbol = "TEST/PARTITION";
+        let tick_time = Utc.with_ymd_and_hms(2001, 1, 1, 12, 0, 0).unwrap();
+        sqlx::query("DROP TABLE IF EXISTS tick_data_p20010101, tick_data_p20010102")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM tick_data WHERE symbol = $1")
+            .bind(symbol)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // 分区创建前写入的数据进入默认分区
+        sqlx::query(
+            "INSERT INTO tick_data (exchange, timestamp, symbol, price, volume, side, trade_id) VALUES ('binance', $1, $2, 1, 1, 'BUY', '1')",
+        )
+        .bind(tick_time)
+        .bind(symbol)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let manager = PartitionManager::new(
+            pool.clone(),
+            PartitionConfig {
+                interval: PartitionInterval::Day,
+                premake: 1,
+                retention: RetentionPolicy {
+                    default_days: Some(30),
+                    per_symbol: HashMap::new(),
+                },
+            },
+        );
+        let created = manager.ensure_partitions(tick_time, tick_time + Duration::days(1)).await.unwrap();
+        assert_eq!(created, vec!["tick_data_p20010101", "tick_data_p20010102"]);
+        // 再次执行不会重复创建
+        assert!(manager.ensure_partitions(tick_time, tick_time).await.unwrap().is_empty());
+
+        let in_partition: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tick_data_p20010101 WHERE symbol = $1")
+            .bind(symbol)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(in_partition, 1);
+
+        // 以 2001-02-03 为当前时间，两个分区都已过 30 天保留期
+        let now = Utc.with_ymd_and_hms(2001, 2, 3, 0, 0, 0).unwrap();
+        let report = manager.apply_retention(now).await.unwrap();
+        assert!(report.dropped_partitions.contains(&"tick_data_p20010101".to_string()));
+        assert!(report.dropped_partitions.contains(&"tick_data_p20010102".to_string()));
+
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tick_data WHERE symbol = $1")
+            .bind(symbol)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, 0);
+    }
+
+    #[tokio::test]
+    async fn test_retention_deletes_rows_in_kept_partitions() {
+        dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL")
+            .expect("DATABASE_URL must be set for tests");
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&database_url)
+            .await
+            .expect("Failed to create test database pool");
+
+        let (short, long) = ("TEST/RETAIN-SHORT", "TEST/RETAIN-LONG");
+        let tick_time = Utc.with_ymd_and_hms(2001, 3, 1, 12, 0, 0).unwrap();
+        sqlx::query("DROP TABLE IF EXISTS tick_data_p20010301")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM tick_data WHERE symbol = ANY($1)")
+            .bind(vec![short, long])
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let manager = PartitionManager::new(
+            pool.clone(),
+            PartitionConfig {
+                interval: PartitionInterval::Day,
+                premake: 1,
+                // 默认保留期足够长，不会删除并发运行的其他测试的数据
+                retention: RetentionPolicy {
+                    default_days: Some(3650),
+                    per_symbol: HashMap::from([(short.to_string(), 30)]),
+                },
+            },
+        );
+        manager.ensure_partitions(tick_time, tick_time).await.unwrap();
+        for symbol in [short, long] {
+            sqlx::query(
+                "INSERT INTO tick_data (exchange, timestamp, symbol, price, volume, side, trade_id) VALUES ('binance', $1, $2, 1, 1, 'BUY', '1')",
+            )
+            .bind(tick_time)
+            .bind(symbol)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        // 分区因其他交易对的保留期保留，保留期较短的交易对在分区中逐行删除
+        let now = Utc.with_ymd_and_hms(2001, 4, 15, 0, 0, 0).unwrap();
+        let report = manager.apply_retention(now).await.unwrap();
+        assert!(!report.dropped_partitions.contains(&"tick_data_p20010301".to_string()));
+        let remaining: Vec<String> = sqlx::query_scalar("SELECT symbol FROM tick_data_p20010301 WHERE symbol = ANY($1)")
+            .bind(vec![short, long])
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, vec![long.to_string()]);
+
+        sqlx::query("DROP TABLE tick_data_p20010301")
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
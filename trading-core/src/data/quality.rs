@@ -0,0 +1,466 @@
+// trading-core/src/data/quality.rs
+// 成交数据质量检查：非正价格/数量、未来时间戳、时间倒退、偏离滚动中位数过大的异常成交以及行情中断。
+// 问题成交仍然写入 tick_data，同时在 tick_flags 中记录原因，K 线聚合和回测可以据此排除。
+
+use super::market_data::MarketDataError;
+use super::types::{MarketDataManager, MarketDataPoint, TickData};
+use chrono::{DateTime, Duration, Utc};
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use tracing::{debug, error, info};
+
+// MAD 换算为正态分布标准差的系数
+const MAD_SCALE: f64 = 1.4826;
+// 价格长时间不变时 MAD 为 0，标准差至少取中位数的万分之一
+const MIN_SIGMA_RATIO: f64 = 1e-4;
+const FLAG_BATCH_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    NonPositivePrice,
+    NonPositiveVolume,
+    FutureTimestamp,
+    OutOfOrder,
+    PriceSpike,
+    StaleFeed,
+}
+
+impl IssueKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IssueKind::NonPositivePrice => "non_positive_price",
+            IssueKind::NonPositiveVolume => "non_positive_volume",
+            IssueKind::FutureTimestamp => "future_timestamp",
+            IssueKind::OutOfOrder => "out_of_order",
+            IssueKind::PriceSpike => "price_spike",
+            IssueKind::StaleFeed => "stale_feed",
+        }
+    }
+
+    /// 行情中断只说明这笔成交之前缺数据，成交本身可用；其余问题的成交在聚合和回测中排除
+    pub fn excludes_row(&self) -> bool {
+        !matches!(self, IssueKind::StaleFeed)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct QualityConfig {
+    // 成交时间晚于接收时间超过该秒数视为未来时间戳
+    pub max_future_skew_secs: i64,
+    // 同一交易对两笔成交间隔超过该秒数视为行情中断
+    pub stale_after_secs: i64,
+    // 滚动中位数窗口大小，窗口内样本少于 spike_min_samples 时不做异常检测
+    pub spike_window: usize,
+    pub spike_min_samples: usize,
+    // 偏离中位数超过多少个标准差（由 MAD 估计）视为异常成交
+    pub spike_sigma: f64,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self {
+            max_future_skew_secs: 5,
+            stale_after_secs: 60,
+            spike_window: 100,
+            spike_min_samples: 20,
+            spike_sigma: 10.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TickFlag {
+    pub exchange: String,
+    pub symbol: String,
+    pub trade_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub kind: IssueKind,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub checked: u64,
+    pub flagged: BTreeMap<IssueKind, u64>,
+}
+
+#[derive(Default)]
+struct SymbolState {
+    last_timestamp: Option<DateTime<Utc>>,
+    prices: VecDeque<f64>,
+}
+
+/// 按成交到达顺序逐笔检查，按交易所和交易对维护上一笔时间和最近价格窗口
+pub struct TickValidator {
+    config: QualityConfig,
+    symbols: HashMap<(String, String), SymbolState>,
+}
+
+impl TickValidator {
+    pub fn new(config: QualityConfig) -> Self {
+        Self {
+            config,
+            symbols: HashMap::new(),
+        }
+    }
+
+    /// 检查一笔成交，received_at 为接收（或写入数据库）的时间
+    pub fn check(&mut self, tick: &TickData, received_at: DateTime<Utc>) -> Vec<TickFlag> {
+        let mut issues = Vec::new();
+        let state = self
+            .symbols
+            .entry((tick.exchange.clone(), tick.symbol.clone()))
+            .or_default();
+
+        let price_valid = tick.price.is_finite() && tick.price > 0.0;
+        if !price_valid {
+            issues.push((IssueKind::NonPositivePrice, format!("price {}", tick.price)));
+        }
+        if !(tick.volume.is_finite() && tick.volume > 0.0) {
+            issues.push((IssueKind::NonPositiveVolume, format!("volume {}", tick.volume)));
+        }
+
+        let skew = tick.timestamp - received_at;
+        if skew > Duration::seconds(self.config.max_future_skew_secs) {
+            issues.push((
+                IssueKind::FutureTimestamp,
+                format!("{}ms ahead of receive time {}", skew.num_milliseconds(), received_at),
+            ));
+        }
+
+        if let Some(last) = state.last_timestamp {
+            if tick.timestamp < last {
+                issues.push((
+                    IssueKind::OutOfOrder,
+                    format!("{}ms before previous trade at {}", (last - tick.timestamp).num_milliseconds(), last),
+                ));
+            } else if tick.timestamp - last > Duration::seconds(self.config.stale_after_secs) {
+                issues.push((
+                    IssueKind::StaleFeed,
+                    format!("no trades for {}s before this one", (tick.timestamp - last).num_seconds()),
+                ));
+            }
+        }
+        state.last_timestamp = state.last_timestamp.max(Some(tick.timestamp));
+
+        if price_valid {
+            if state.prices.len() >= self.config.spike_min_samples {
+                let (median, sigma) = median_and_sigma(&state.prices);
+                let deviation = (tick.price - median).abs() / sigma;
+                if deviation > self.config.spike_sigma {
+                    issues.push((
+                        IssueKind::PriceSpike,
+                        format!("price {} is {:.1} sigma from rolling median {}", tick.price, deviation, median),
+                    ));
+                }
+            }
+            // 异常价格也进入窗口，中位数和 MAD 不受个别异常值影响，行情真实跳变时窗口能跟上
+            state.prices.push_back(tick.price);
+            if state.prices.len() > self.config.spike_window {
+                state.prices.pop_front();
+            }
+        }
+
+        issues
+            .into_iter()
+            .map(|(kind, reason)| TickFlag {
+                exchange: tick.exchange.clone(),
+                symbol: tick.symbol.clone(),
+                trade_id: tick.trade_id.clone(),
+                timestamp: tick.timestamp,
+                kind,
+                reason,
+            })
+            .collect()
+    }
+}
+
+fn median_of(values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+fn median_and_sigma(prices: &VecDeque<f64>) -> (f64, f64) {
+    let mut values: Vec<f64> = prices.iter().copied().collect();
+    let median = median_of(&mut values);
+    let mut deviations: Vec<f64> = values.iter().map(|v| (v - median).abs()).collect();
+    let mad = median_of(&mut deviations);
+    (median, (MAD_SCALE * mad).max(median * MIN_SIGMA_RATIO))
+}
+
+impl MarketDataManager {
+    /// 记录问题成交，重复检查时覆盖原因
+    pub async fn store_tick_flags(&self, flags: &[TickFlag]) -> Result<u64, MarketDataError> {
+        let mut stored = 0;
+        for chunk in flags.chunks(FLAG_BATCH_SIZE) {
+            let exchanges: Vec<&str> = chunk.iter().map(|f| f.exchange.as_str()).collect();
+            let symbols: Vec<&str> = chunk.iter().map(|f| f.symbol.as_str()).collect();
+            let trade_ids: Vec<&str> = chunk.iter().map(|f| f.trade_id.as_str()).collect();
+            let timestamps: Vec<DateTime<Utc>> = chunk.iter().map(|f| f.timestamp).collect();
+            let kinds: Vec<&str> = chunk.iter().map(|f| f.kind.as_str()).collect();
+            let reasons: Vec<&str> = chunk.iter().map(|f| f.reason.as_str()).collect();
+
+            let result = sqlx::query!(
+                r#"
+                INSERT INTO tick_flags (exchange, symbol, trade_id, timestamp, kind, reason)
+                SELECT * FROM UNNEST(
+                    $1::varchar[], $2::varchar[], $3::varchar[], $4::timestamptz[], $5::varchar[], $6::text[]
+                )
+                ON CONFLICT (exchange, symbol, trade_id, timestamp, kind) DO UPDATE SET reason = EXCLUDED.reason
+                "#,
+                &exchanges as &[&str],
+                &symbols as &[&str],
+                &trade_ids as &[&str],
+                &timestamps,
+                &kinds as &[&str],
+                &reasons as &[&str]
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                error!("Failed to store tick flags: {}", e);
+                MarketDataError::DatabaseError(e)
+            })?;
+            stored += result.rows_affected();
+        }
+        Ok(stored)
+    }
+
+    /// 按成交 ID 顺序（非数字 ID 按写入顺序）检查已存储的成交，record 为 true 时记录问题成交
+    pub async fn validate_stored_ticks(
+        &self,
+        symbol: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        config: &QualityConfig,
+        record: bool,
+    ) -> Result<ValidationReport, MarketDataError> {
+        let mut validator = TickValidator::new(config.clone());
+        let mut report = ValidationReport::default();
+        let mut flags = Vec::new();
+
+        let mut rows = sqlx::query!(
+            r#"
+            SELECT exchange, timestamp, symbol, price, volume, side, trade_id, is_maker, created_at
+            FROM tick_data
+            WHERE exchange = $1
+            AND symbol = $2
+            AND timestamp >= $3
+            AND timestamp < $4
+            ORDER BY CASE WHEN trade_id ~ '^[0-9]{1,18}$' THEN trade_id::bigint END, id
+            "#,
+            self.exchange,
+            symbol,
+            start_time,
+            end_time
+        )
+        .fetch(&self.pool);
+
+        while let Some(row) = rows.try_next().await? {
+            let tick = TickData {
+                exchange: row.exchange,
+                timestamp: row.timestamp,
+                symbol: row.symbol,
+                price: row.price,
+                volume: row.volume,
+                side: row.side.trim().to_string(),
+                trade_id: row.trade_id,
+                is_maker: row.is_maker,
+            };
+            report.checked += 1;
+            for flag in validator.check(&tick, row.created_at) {
+                *report.flagged.entry(flag.kind).or_default() += 1;
+                flags.push(flag);
+            }
+        }
+        drop(rows);
+
+        if record && !flags.is_empty() {
+            self.store_tick_flags(&flags).await?;
+            // 新标记的成交不再计入 K 线
+            self.update_candles_from_ticks(symbol, start_time, end_time).await?;
+        }
+        info!(
+            "Validated {} {} ticks for {}, flagged {} issues",
+            report.checked, self.exchange, symbol, flags.len()
+        );
+        Ok(report)
+    }
+
+    /// 与 get_market_data 相同，但排除被标记为问题数据的成交
+    pub async fn get_clean_market_data(
+        &self,
+        symbol: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<MarketDataPoint>, MarketDataError> {
+        debug!("Fetching unflagged market data for symbol: {}", symbol);
+        let rows = sqlx::query!(
+            r#"
+            SELECT timestamp, symbol, price, volume
+            FROM tick_data t
+            WHERE exchange = $1
+            AND symbol = $2
+            AND timestamp >= $3
+            AND timestamp <= $4
+            AND NOT EXISTS (
+                SELECT 1 FROM tick_flags f
+                WHERE f.exchange = t.exchange
+                AND f.symbol = t.symbol
+                AND f.trade_id = t.trade_id
+                AND f.timestamp = t.timestamp
+                AND f.kind <> 'stale_feed'
+            )
+            ORDER BY timestamp ASC
+            "#,
+            self.exchange,
+            symbol,
+            start_time,
+            end_time
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| MarketDataPoint {
+                timestamp: row.timestamp,
+                symbol: row.symbol,
+                price: row.price,
+                volume: row.volume,
+                high: row.price,
+                low: row.price,
+                open: row.price,
+                close: row.price,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(trade_id: u64, seconds: i64, price: f64) -> TickData {
+        TickData {
+            exchange: "binance".to_string(),
+            timestamp: DateTime::from_timestamp(1_699_999_980 + seconds, 0).unwrap(),
+            symbol: "BTCUSDT".to_string(),
+            price,
+            volume: 0.1,
+            side: "BUY".to_string(),
+            trade_id: trade_id.to_string(),
+            is_maker: false,
+        }
+    }
+
+    fn kinds(flags: &[TickFlag]) -> Vec<IssueKind> {
+        flags.iter().map(|f| f.kind).collect()
+    }
+
+    #[test]
+    fn test_validator_flags_issues() {
+        let mut validator = TickValidator::new(QualityConfig::default());
+        let now = DateTime::from_timestamp(1_700_001_000, 0).unwrap();
+
+        // 正常波动不产生标记
+        for i in 0..30 {
+            let price = 50000.0 + (i % 5) as f64 * 10.0;
+            assert!(validator.check(&tick(i, i as i64, price), now).is_empty());
+        }
+
+        assert_eq!(kinds(&validator.check(&tick(30, 30, 65000.0), now)), vec![IssueKind::PriceSpike]);
+        // 单个异常值不影响之后的正常价格
+        assert!(validator.check(&tick(31, 31, 50020.0), now).is_empty());
+
+        let mut bad = tick(32, 32, 0.0);
+        bad.volume = -1.0;
+        assert_eq!(
+            kinds(&validator.check(&bad, now)),
+            vec![IssueKind::NonPositivePrice, IssueKind::NonPositiveVolume]
+        );
+        assert_eq!(kinds(&validator.check(&tick(33, 20, 50010.0), now)), vec![IssueKind::OutOfOrder]);
+        assert_eq!(kinds(&validator.check(&tick(34, 200, 50010.0), now)), vec![IssueKind::StaleFeed]);
+        assert_eq!(
+            kinds(&validator.check(&tick(35, 2000, 50010.0), now)),
+            vec![IssueKind::FutureTimestamp, IssueKind::StaleFeed]
+        );
+        // 不同交易所的同一交易对分别维护状态
+        let other_venue = TickData { exchange: "okx".to_string(), ..tick(1, 25, 50010.0) };
+        assert!(validator.check(&other_venue, now).is_empty());
+        assert!(IssueKind::PriceSpike.excludes_row());
+        assert!(!IssueKind::StaleFeed.excludes_row());
+    }
+
+    #[tokio::test]
+    async fn test_flagged_ticks_excluded() {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL")
+            .expect("DATABASE_URL must be set for tests");
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&database_url)
+            .await
+            .expect("Failed to create test database pool");
+        let manager = MarketDataManager::new(pool);
+        let symbol = "TEST/QUALITY";
+        for table in ["tick_data", "tick_flags", "candles"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE symbol = $1", table))
+                .bind(symbol)
+                .execute(&manager.pool)
+                .await
+                .unwrap();
+        }
+
+        // 一分钟内 30 笔正常成交和一笔异常成交
+        let mut ticks: Vec<TickData> = (0..31)
+            .map(|i| TickData {
+                symbol: symbol.to_string(),
+                ..tick(i, i as i64, 50000.0 + (i % 3) as f64)
+            })
+            .collect();
+        ticks[25].price = 90000.0;
+        // 下一分钟只有一笔价格为 0 的成交
+        ticks.push(TickData {
+            symbol: symbol.to_string(),
+            ..tick(31, 90, 0.0)
+        });
+        manager.store_tick_batch(&ticks).await.unwrap();
+        manager.update_candles_for_ticks(&ticks).await.unwrap();
+        let start = ticks[0].timestamp;
+        let end = start + Duration::minutes(2);
+        let candles = manager.get_candles(symbol, "1m", start, end).await.unwrap();
+        assert_eq!((candles.len(), candles[0].high), (2, 90000.0));
+
+        let report = manager
+            .validate_stored_ticks(symbol, start, end, &QualityConfig::default(), true)
+            .await
+            .unwrap();
+        assert_eq!(report.checked, 32);
+        assert_eq!(report.flagged.get(&IssueKind::PriceSpike), Some(&1));
+        assert_eq!(report.flagged.get(&IssueKind::NonPositivePrice), Some(&1));
+
+        // 标记后 K 线重新聚合，回测数据不含异常成交；成交全部被标记的分钟不再有 K 线
+        let candles = manager.get_candles(symbol, "1m", start, end).await.unwrap();
+        assert_eq!((candles.len(), candles[0].high), (1, 50002.0));
+        let clean = manager.get_clean_market_data(symbol, start, end).await.unwrap();
+        assert_eq!(clean.len(), 30);
+        assert!(clean.iter().all(|point| point.price < 60000.0));
+
+        for table in ["tick_data", "tick_flags", "candles"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE symbol = $1", table))
+                .bind(symbol)
+                .execute(&manager.pool)
+                .await
+                .unwrap();
+        }
+    }
+}
@@ -0,0 +1,250 @@
+// trading-core/src/data/spool.rs
+// 数据库不可用时的本地落盘：按段追加写入 JSON Lines 文件，数据库恢复后按顺序回放
+
+use super::types::TickData;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tracing::{info, warn};
+
+const SEGMENT_PREFIX: &str = "spool-";
+const SEGMENT_SUFFIX: &str = ".jsonl";
+pub const DEFAULT_SEGMENT_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum SpoolError {
+    #[error("Spool IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Spool encode error: {0}")]
+    Encode(#[from] serde_json::Error),
+}
+
+struct ActiveSegment {
+    sequence: u64,
+    file: File,
+    bytes: u64,
+}
+
+pub struct TickSpool {
+    dir: PathBuf,
+    max_segment_bytes: u64,
+    // 每个段的序号 -> 记录数
+    segments: BTreeMap<u64, u64>,
+    active: Option<ActiveSegment>,
+    next_sequence: u64,
+}
+
+impl TickSpool {
+    /// 打开（或创建）落盘目录，统计上次进程遗留的待回放数据
+    pub fn open(dir: impl AsRef<Path>, max_segment_bytes: u64) -> Result<Self, SpoolError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut segments = BTreeMap::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if let Some(sequence) = Self::parse_sequence(&path) {
+                segments.insert(sequence, Self::count_records(&path)?);
+            }
+        }
+
+        let next_sequence = segments.keys().next_back().map_or(0, |last| last + 1);
+        let spool = Self {
+            dir,
+            max_segment_bytes,
+            segments,
+            active: None,
+            next_sequence,
+        };
+
+        if spool.depth() > 0 {
+            info!(
+                "Found {} spooled ticks in {} segments at {}",
+                spool.depth(), spool.segments.len(), spool.dir.display()
+            );
+        }
+        Ok(spool)
+    }
+
+    /// 待回放的记录数
+    pub fn depth(&self) -> u64 {
+        self.segments.values().sum()
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// 追加写入并刷盘，当前段超过大小上限时切换到新段
+    pub fn append(&mut self, ticks: &[TickData]) -> Result<(), SpoolError> {
+        if ticks.is_empty() {
+            return Ok(());
+        }
+
+        let mut buffer = Vec::new();
+        for tick in ticks {
+            serde_json::to_writer(&mut buffer, tick)?;
+            buffer.push(b'\n');
+        }
+
+        let active = self.active_segment()?;
+        active.file.write_all(&buffer)?;
+        active.file.sync_data()?;
+        active.bytes += buffer.len() as u64;
+        let sequence = active.sequence;
+        let full = active.bytes >= self.max_segment_bytes;
+
+        *self.segments.entry(sequence).or_insert(0) += ticks.len() as u64;
+        if full {
+            self.active = None;
+        }
+        Ok(())
+    }
+
+    /// 关闭当前段，返回所有待回放段（按写入顺序）。之后的追加写入进入新段。
+    pub fn seal(&mut self) -> Vec<PathBuf> {
+        self.active = None;
+        self.segments.keys().map(|sequence| self.segment_path(*sequence)).collect()
+    }
+
+    /// 读取一个段中的全部记录；进程崩溃导致的末尾半行会被跳过
+    pub fn read_segment(path: &Path) -> Result<Vec<TickData>, SpoolError> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut ticks = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<TickData>(&line) {
+                Ok(tick) => ticks.push(tick),
+                Err(e) => warn!("Skipping corrupt spool record in {}: {}", path.display(), e),
+            }
+        }
+        Ok(ticks)
+    }
+
+    /// 段回放成功后删除
+    pub fn remove_segment(&mut self, path: &Path) -> Result<(), SpoolError> {
+        if let Some(sequence) = Self::parse_sequence(path) {
+            if self.active.as_ref().is_some_and(|active| active.sequence == sequence) {
+                self.active = None;
+            }
+            self.segments.remove(&sequence);
+        }
+        fs::remove_file(path)?;
+        Ok(())
+    }
+
+    fn active_segment(&mut self) -> Result<&mut ActiveSegment, SpoolError> {
+        if self.active.is_none() {
+            let sequence = self.next_sequence;
+            self.next_sequence += 1;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.segment_path(sequence))?;
+            self.active = Some(ActiveSegment { sequence, file, bytes: 0 });
+        }
+        Ok(self.active.as_mut().expect("active segment was just opened"))
+    }
+
+    fn segment_path(&self, sequence: u64) -> PathBuf {
+        self.dir.join(format!("{}{:020}{}", SEGMENT_PREFIX, sequence, SEGMENT_SUFFIX))
+    }
+
+    fn parse_sequence(path: &Path) -> Option<u64> {
+        path.file_name()?
+            .to_str()?
+            .strip_prefix(SEGMENT_PREFIX)?
+            .strip_suffix(SEGMENT_SUFFIX)?
+            .parse()
+            .ok()
+    }
+
+    fn count_records(path: &Path) -> Result<u64, SpoolError> {
+        Ok(Self::read_segment(path)?.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn create_test_tick(trade_id: u64) -> TickData {
+        TickData {
+            exchange: "binance".to_string(),
+            timestamp: Utc::now(),
+            symbol: "BTCUSDT".to_string(),
+            price: 50000.0,
+            volume: 1.0,
+            side: "BUY".to_string(),
+            trade_id: trade_id.to_string(),
+            is_maker: false,
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rust-trade-spool-{}-{}", name, uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_spool_rotates_and_replays_in_order() {
+        let dir = temp_dir("rotate");
+        // 段大小很小，每次追加都会切换新段
+        let mut spool = TickSpool::open(&dir, 1).unwrap();
+        spool.append(&[create_test_tick(1), create_test_tick(2)]).unwrap();
+        spool.append(&[create_test_tick(3)]).unwrap();
+
+        assert_eq!(spool.depth(), 3);
+        assert_eq!(spool.segment_count(), 2);
+
+        let segments = spool.seal();
+        let replayed: Vec<String> = segments
+            .iter()
+            .flat_map(|path| TickSpool::read_segment(path).unwrap())
+            .map(|tick| tick.trade_id)
+            .collect();
+        assert_eq!(replayed, vec!["1", "2", "3"]);
+
+        for path in &segments {
+            spool.remove_segment(path).unwrap();
+        }
+        assert_eq!(spool.depth(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_spool_recovers_after_restart() {
+        let dir = temp_dir("restart");
+        {
+            let mut spool = TickSpool::open(&dir, DEFAULT_SEGMENT_BYTES).unwrap();
+            spool.append(&[create_test_tick(1), create_test_tick(2)]).unwrap();
+        }
+
+        // 模拟崩溃时写了一半的记录
+        let path = dir.join(format!("{}{:020}{}", SEGMENT_PREFIX, 0, SEGMENT_SUFFIX));
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"timestamp\":").unwrap();
+
+        let mut spool = TickSpool::open(&dir, DEFAULT_SEGMENT_BYTES).unwrap();
+        assert_eq!(spool.segment_count(), 1);
+        assert_eq!(spool.depth(), 2);
+        spool.append(&[create_test_tick(3)]).unwrap();
+        // 重启后的写入进入新段，不会接在半行后面
+        assert_eq!(spool.segment_count(), 2);
+
+        let ticks: Vec<TickData> = spool
+            .seal()
+            .iter()
+            .flat_map(|path| TickSpool::read_segment(path).unwrap())
+            .collect();
+        assert_eq!(ticks.len(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
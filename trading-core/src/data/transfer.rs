@@ -0,0 +1,891 @@
+// trading-core/src/data/transfer.rs
+// 成交与 K 线的 CSV / Parquet 导入导出。导出按数据库游标流式写入，
+// 导入时先按列名识别数据类型并校验表结构，再逐行校验后分批写入。
+// 导出和读取都限定在 MarketDataManager 所选的交易所；没有 exchange 列的旧文件按该交易所导入。
+
+use super::backfill::interval_duration;
+use super::market_data::MarketDataError;
+use super::quality::{QualityConfig, TickValidator};
+use super::types::{MarketDataManager, MarketDataPoint, TickData, DEFAULT_EXCHANGE};
+use arrow_array::{
+    Array, ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray,
+    TimestampMicrosecondArray, TimestampMillisecondArray, TimestampNanosecondArray,
+    TimestampSecondArray,
+};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef, TimeUnit};
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use parquet::arrow::arrow_reader::{ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder};
+use parquet::arrow::ArrowWriter;
+use parquet::errors::ParquetError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+// Parquet 每个 RecordBatch 的行数，也是导入时每次写库的行数
+const BATCH_ROWS: usize = 8192;
+
+// 早期导出的文件没有该列
+const EXCHANGE_COLUMN: &str = "exchange";
+
+#[derive(Error, Debug)]
+pub enum TransferError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("Parquet error: {0}")]
+    Parquet(#[from] ParquetError),
+    #[error("Arrow error: {0}")]
+    Arrow(#[from] ArrowError),
+    #[error("Data error: {0}")]
+    Data(#[from] MarketDataError),
+    #[error("Schema mismatch: {0}")]
+    Schema(String),
+    #[error("Invalid row {row}: {reason}")]
+    InvalidRow { row: u64, reason: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Csv,
+    Parquet,
+}
+
+impl FileFormat {
+    /// 按扩展名推断文件格式
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+impl FromStr for FileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "parquet" | "pq" => Ok(Self::Parquet),
+            other => Err(format!("unsupported file format: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataKind {
+    Ticks,
+    Candles,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportFilter {
+    // 未指定时导出所有交易对
+    pub symbol: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportReport {
+    pub kind: DataKind,
+    pub rows: u64,
+    // 实际写入的行数；重复成交不会重复写入，K 线按主键覆盖
+    pub stored: u64,
+}
+
+fn default_exchange() -> String {
+    DEFAULT_EXCHANGE.to_string()
+}
+
+// 导入导出文件中的一行 K 线
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CandleRecord {
+    #[serde(default = "default_exchange")]
+    exchange: String,
+    open_time: DateTime<Utc>,
+    symbol: String,
+    interval: String,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+}
+
+// 文件中一行记录与 Arrow 列之间的转换及校验
+trait Record: Serialize + DeserializeOwned + Sized {
+    const COLUMNS: &'static [&'static str];
+
+    fn schema() -> Schema;
+    fn to_batch(rows: &[Self]) -> Result<RecordBatch, ArrowError>;
+    fn from_batch(batch: &RecordBatch) -> Result<Vec<Self>, TransferError>;
+    fn validate(&self) -> Result<(), String>;
+    fn set_exchange(&mut self, exchange: &str);
+}
+
+fn timestamp_field(name: &str) -> Field {
+    Field::new(name, DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())), false)
+}
+
+fn timestamp_array(values: impl Iterator<Item = DateTime<Utc>>) -> ArrayRef {
+    Arc::new(
+        TimestampMicrosecondArray::from_iter_values(values.map(|t| t.timestamp_micros()))
+            .with_timezone("UTC"),
+    )
+}
+
+fn column<'a, A: Array + 'static>(
+    batch: &'a RecordBatch,
+    name: &str,
+) -> Result<&'a A, TransferError> {
+    let array = batch
+        .column_by_name(name)
+        .ok_or_else(|| TransferError::Schema(format!("missing column {}", name)))?;
+    if array.null_count() > 0 {
+        return Err(TransferError::Schema(format!("column {} contains nulls", name)));
+    }
+    array.as_any().downcast_ref::<A>().ok_or_else(|| {
+        TransferError::Schema(format!("column {} has unexpected type {}", name, array.data_type()))
+    })
+}
+
+// 接受任意精度的时间戳列，pandas 默认写出纳秒精度
+fn timestamp_column(batch: &RecordBatch, name: &str) -> Result<Vec<DateTime<Utc>>, TransferError> {
+    let data_type = batch
+        .column_by_name(name)
+        .map(|array| array.data_type().clone())
+        .ok_or_else(|| TransferError::Schema(format!("missing column {}", name)))?;
+    let micros: Vec<i64> = match data_type {
+        DataType::Timestamp(TimeUnit::Second, _) => column::<TimestampSecondArray>(batch, name)?
+            .values().iter().map(|v| v * 1_000_000).collect(),
+        DataType::Timestamp(TimeUnit::Millisecond, _) => column::<TimestampMillisecondArray>(batch, name)?
+            .values().iter().map(|v| v * 1_000).collect(),
+        DataType::Timestamp(TimeUnit::Microsecond, _) => column::<TimestampMicrosecondArray>(batch, name)?
+            .values().to_vec(),
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => column::<TimestampNanosecondArray>(batch, name)?
+            .values().iter().map(|v| v / 1_000).collect(),
+        other => {
+            return Err(TransferError::Schema(format!(
+                "column {} has unexpected type {}", name, other
+            )))
+        }
+    };
+    micros
+        .into_iter()
+        .map(|v| {
+            DateTime::from_timestamp_micros(v)
+                .ok_or_else(|| TransferError::Schema(format!("column {} has out of range timestamps", name)))
+        })
+        .collect()
+}
+
+fn check_price(name: &str, value: f64) -> Result<(), String> {
+    if value.is_finite() && value > 0.0 {
+        Ok(())
+    } else {
+        Err(format!("{} must be positive, got {}", name, value))
+    }
+}
+
+// 没有 exchange 列时先填默认值，导入时再替换为目标交易所
+fn exchange_values(batch: &RecordBatch) -> Result<Vec<String>, TransferError> {
+    if batch.column_by_name(EXCHANGE_COLUMN).is_none() {
+        return Ok(vec![default_exchange(); batch.num_rows()]);
+    }
+    let exchanges = column::<StringArray>(batch, EXCHANGE_COLUMN)?;
+    Ok((0..batch.num_rows()).map(|i| exchanges.value(i).to_string()).collect())
+}
+
+fn check_exchange(exchange: &str) -> Result<(), String> {
+    if exchange.is_empty() || exchange.len() > 20 {
+        return Err(format!("invalid exchange {:?}", exchange));
+    }
+    Ok(())
+}
+
+fn check_symbol(symbol: &str) -> Result<(), String> {
+    if symbol.is_empty() || symbol.len() > 20 {
+        return Err(format!("invalid symbol {:?}", symbol));
+    }
+    Ok(())
+}
+
+impl Record for TickData {
+    const COLUMNS: &'static [&'static str] =
+        &["exchange", "timestamp", "symbol", "price", "volume", "side", "trade_id", "is_maker"];
+
+    fn schema() -> Schema {
+        Schema::new(vec![
+            Field::new(EXCHANGE_COLUMN, DataType::Utf8, false),
+            timestamp_field("timestamp"),
+            Field::new("symbol", DataType::Utf8, false),
+            Field::new("price", DataType::Float64, false),
+            Field::new("volume", DataType::Float64, false),
+            Field::new("side", DataType::Utf8, false),
+            Field::new("trade_id", DataType::Utf8, false),
+            Field::new("is_maker", DataType::Boolean, false),
+        ])
+    }
+
+    fn to_batch(rows: &[Self]) -> Result<RecordBatch, ArrowError> {
+        RecordBatch::try_new(
+            Arc::new(Self::schema()),
+            vec![
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.exchange))),
+                timestamp_array(rows.iter().map(|r| r.timestamp)),
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.symbol))),
+                Arc::new(Float64Array::from_iter_values(rows.iter().map(|r| r.price))),
+                Arc::new(Float64Array::from_iter_values(rows.iter().map(|r| r.volume))),
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.side))),
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.trade_id))),
+                Arc::new(BooleanArray::from_iter(rows.iter().map(|r| Some(r.is_maker)))),
+            ],
+        )
+    }
+
+    fn from_batch(batch: &RecordBatch) -> Result<Vec<Self>, TransferError> {
+        let exchanges = exchange_values(batch)?;
+        let timestamps = timestamp_column(batch, "timestamp")?;
+        let symbols = column::<StringArray>(batch, "symbol")?;
+        let prices = column::<Float64Array>(batch, "price")?;
+        let volumes = column::<Float64Array>(batch, "volume")?;
+        let sides = column::<StringArray>(batch, "side")?;
+        let trade_ids = column::<StringArray>(batch, "trade_id")?;
+        let makers = column::<BooleanArray>(batch, "is_maker")?;
+
+        Ok(exchanges
+            .into_iter()
+            .enumerate()
+            .map(|(i, exchange)| TickData {
+                exchange,
+                timestamp: timestamps[i],
+                symbol: symbols.value(i).to_string(),
+                price: prices.value(i),
+                volume: volumes.value(i),
+                side: sides.value(i).to_string(),
+                trade_id: trade_ids.value(i).to_string(),
+                is_maker: makers.value(i),
+            })
+            .collect())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        check_exchange(&self.exchange)?;
+        check_symbol(&self.symbol)?;
+        check_price("price", self.price)?;
+        check_price("volume", self.volume)?;
+        if !matches!(self.side.to_uppercase().as_str(), "BUY" | "SELL") {
+            return Err(format!("side must be BUY or SELL, got {:?}", self.side));
+        }
+        if self.trade_id.is_empty() || self.trade_id.len() > 50 {
+            return Err(format!("invalid trade_id {:?}", self.trade_id));
+        }
+        Ok(())
+    }
+
+    fn set_exchange(&mut self, exchange: &str) {
+        self.exchange = exchange.to_string();
+    }
+}
+
+impl Record for CandleRecord {
+    const COLUMNS: &'static [&'static str] =
+        &["exchange", "open_time", "symbol", "interval", "open", "high", "low", "close", "volume"];
+
+    fn schema() -> Schema {
+        Schema::new(vec![
+            Field::new(EXCHANGE_COLUMN, DataType::Utf8, false),
+            timestamp_field("open_time"),
+            Field::new("symbol", DataType::Utf8, false),
+            Field::new("interval", DataType::Utf8, false),
+            Field::new("open", DataType::Float64, false),
+            Field::new("high", DataType::Float64, false),
+            Field::new("low", DataType::Float64, false),
+            Field::new("close", DataType::Float64, false),
+            Field::new("volume", DataType::Float64, false),
+        ])
+    }
+
+    fn to_batch(rows: &[Self]) -> Result<RecordBatch, ArrowError> {
+        let floats = |f: fn(&Self) -> f64| -> ArrayRef {
+            Arc::new(Float64Array::from_iter_values(rows.iter().map(f)))
+        };
+        RecordBatch::try_new(
+            Arc::new(Self::schema()),
+            vec![
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.exchange))),
+                timestamp_array(rows.iter().map(|r| r.open_time)),
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.symbol))),
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.interval))),
+                floats(|r| r.open),
+                floats(|r| r.high),
+                floats(|r| r.low),
+                floats(|r| r.close),
+                floats(|r| r.volume),
+            ],
+        )
+    }
+
+    fn from_batch(batch: &RecordBatch) -> Result<Vec<Self>, TransferError> {
+        let exchanges = exchange_values(batch)?;
+        let open_times = timestamp_column(batch, "open_time")?;
+        let symbols = column::<StringArray>(batch, "symbol")?;
+        let intervals = column::<StringArray>(batch, "interval")?;
+        let opens = column::<Float64Array>(batch, "open")?;
+        let highs = column::<Float64Array>(batch, "high")?;
+        let lows = column::<Float64Array>(batch, "low")?;
+        let closes = column::<Float64Array>(batch, "close")?;
+        let volumes = column::<Float64Array>(batch, "volume")?;
+
+        Ok(exchanges
+            .into_iter()
+            .enumerate()
+            .map(|(i, exchange)| CandleRecord {
+                exchange,
+                open_time: open_times[i],
+                symbol: symbols.value(i).to_string(),
+                interval: intervals.value(i).to_string(),
+                open: opens.value(i),
+                high: highs.value(i),
+                low: lows.value(i),
+                close: closes.value(i),
+                volume: volumes.value(i),
+            })
+            .collect())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        check_exchange(&self.exchange)?;
+        check_symbol(&self.symbol)?;
+        if self.interval.len() > 4 || interval_duration(&self.interval).is_none() {
+            return Err(format!("unsupported interval {:?}", self.interval));
+        }
+        for (name, value) in [("open", self.open), ("high", self.high), ("low", self.low), ("close", self.close)] {
+            check_price(name, value)?;
+        }
+        if self.high < self.open.max(self.close).max(self.low) || self.low > self.open.min(self.close) {
+            return Err(format!(
+                "inconsistent OHLC: open {} high {} low {} close {}",
+                self.open, self.high, self.low, self.close
+            ));
+        }
+        if !self.volume.is_finite() || self.volume < 0.0 {
+            return Err(format!("volume must not be negative, got {}", self.volume));
+        }
+        Ok(())
+    }
+
+    fn set_exchange(&mut self, exchange: &str) {
+        self.exchange = exchange.to_string();
+    }
+}
+
+enum RecordWriter<R> {
+    Csv { writer: csv::Writer<File>, rows: u64 },
+    Parquet { writer: ArrowWriter<File>, buffer: Vec<R> },
+}
+
+impl<R: Record> RecordWriter<R> {
+    fn create(path: &Path, format: FileFormat) -> Result<Self, TransferError> {
+        let file = File::create(path)?;
+        Ok(match format {
+            FileFormat::Csv => Self::Csv { writer: csv::Writer::from_writer(file), rows: 0 },
+            FileFormat::Parquet => Self::Parquet {
+                writer: ArrowWriter::try_new(file, Arc::new(R::schema()), None)?,
+                buffer: Vec::with_capacity(BATCH_ROWS),
+            },
+        })
+    }
+
+    fn write(&mut self, row: R) -> Result<(), TransferError> {
+        match self {
+            Self::Csv { writer, rows } => {
+                writer.serialize(row)?;
+                *rows += 1;
+            }
+            Self::Parquet { writer, buffer } => {
+                buffer.push(row);
+                if buffer.len() >= BATCH_ROWS {
+                    writer.write(&R::to_batch(buffer)?)?;
+                    buffer.clear();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), TransferError> {
+        match self {
+            Self::Csv { mut writer, rows } => {
+                // 表头随第一行写出，没有数据时单独写出
+                if rows == 0 {
+                    writer.write_record(R::COLUMNS)?;
+                }
+                writer.flush()?;
+            }
+            Self::Parquet { mut writer, buffer } => {
+                if !buffer.is_empty() {
+                    writer.write(&R::to_batch(&buffer)?)?;
+                }
+                writer.close()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+enum RecordSource {
+    Csv(csv::Reader<File>),
+    Parquet(ParquetRecordBatchReader),
+}
+
+struct RecordReader {
+    source: RecordSource,
+    // 文件没有 exchange 列时，所有行都属于该交易所
+    exchange: Option<String>,
+}
+
+impl RecordReader {
+    // 打开文件并按列名识别数据类型，default_exchange 用于没有 exchange 列的文件
+    fn open(
+        path: &Path,
+        format: FileFormat,
+        default_exchange: &str,
+    ) -> Result<(Self, DataKind), TransferError> {
+        let (source, columns, schema) = match format {
+            FileFormat::Csv => {
+                let mut reader = csv::Reader::from_path(path)?;
+                let columns: Vec<String> = reader.headers()?.iter().map(str::to_string).collect();
+                (RecordSource::Csv(reader), columns, None)
+            }
+            FileFormat::Parquet => {
+                let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?;
+                let schema = builder.schema().clone();
+                let columns = schema.fields().iter().map(|f| f.name().clone()).collect();
+                (RecordSource::Parquet(builder.with_batch_size(BATCH_ROWS).build()?), columns, Some(schema))
+            }
+        };
+
+        let kind = detect_kind(&columns)?;
+        if let Some(schema) = schema {
+            match kind {
+                DataKind::Ticks => check_schema(&schema, &TickData::schema())?,
+                DataKind::Candles => check_schema(&schema, &CandleRecord::schema())?,
+            }
+        }
+        let exchange = (!columns.iter().any(|c| c == EXCHANGE_COLUMN))
+            .then(|| default_exchange.to_string());
+        Ok((Self { source, exchange }, kind))
+    }
+
+    fn next_chunk<R: Record>(&mut self) -> Result<Option<Vec<R>>, TransferError> {
+        let mut rows = match &mut self.source {
+            RecordSource::Csv(reader) => {
+                let rows = reader
+                    .deserialize()
+                    .take(BATCH_ROWS)
+                    .collect::<Result<Vec<R>, _>>()?;
+                if rows.is_empty() {
+                    return Ok(None);
+                }
+                rows
+            }
+            RecordSource::Parquet(reader) => match reader.next() {
+                Some(batch) => R::from_batch(&batch?)?,
+                None => return Ok(None),
+            },
+        };
+        if let Some(exchange) = &self.exchange {
+            rows.iter_mut().for_each(|row| row.set_exchange(exchange));
+        }
+        Ok(Some(rows))
+    }
+}
+
+// exchange 列可以没有
+fn detect_kind(columns: &[String]) -> Result<DataKind, TransferError> {
+    let found: BTreeSet<&str> = columns
+        .iter()
+        .map(String::as_str)
+        .filter(|c| *c != EXCHANGE_COLUMN)
+        .collect();
+    for (kind, expected) in [
+        (DataKind::Ticks, TickData::COLUMNS),
+        (DataKind::Candles, CandleRecord::COLUMNS),
+    ] {
+        if found == expected.iter().copied().filter(|c| *c != EXCHANGE_COLUMN).collect() {
+            return Ok(kind);
+        }
+    }
+    Err(TransferError::Schema(format!(
+        "columns {:?} match neither ticks {:?} nor candles {:?}",
+        columns, TickData::COLUMNS, CandleRecord::COLUMNS
+    )))
+}
+
+// 时间戳列允许任意精度，其余列类型必须一致
+fn check_schema(actual: &SchemaRef, expected: &Schema) -> Result<(), TransferError> {
+    for field in expected.fields() {
+        if field.name() == EXCHANGE_COLUMN && actual.column_with_name(EXCHANGE_COLUMN).is_none() {
+            continue;
+        }
+        let actual_type = actual.field_with_name(field.name())?.data_type();
+        let matches = match (field.data_type(), actual_type) {
+            (DataType::Timestamp(..), DataType::Timestamp(..)) => true,
+            (expected, actual) => expected == actual,
+        };
+        if !matches {
+            return Err(TransferError::Schema(format!(
+                "column {} should be {}, found {}",
+                field.name(), field.data_type(), actual_type
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn validate_rows<R: Record>(rows: &[R], offset: u64) -> Result<(), TransferError> {
+    for (i, row) in rows.iter().enumerate() {
+        row.validate().map_err(|reason| TransferError::InvalidRow {
+            row: offset + i as u64 + 1,
+            reason,
+        })?;
+    }
+    Ok(())
+}
+
+impl MarketDataManager {
+    /// 按时间顺序导出成交，返回导出的行数
+    pub async fn export_ticks(
+        &self,
+        path: &Path,
+        format: FileFormat,
+        filter: &ExportFilter,
+    ) -> Result<u64, TransferError> {
+        let mut writer = RecordWriter::<TickData>::create(path, format)?;
+        let mut rows = sqlx::query!(
+            r#"
+            SELECT exchange, timestamp, symbol, price, volume, side, trade_id, is_maker
+            FROM tick_data
+            WHERE exchange = $1
+            AND ($2::varchar IS NULL OR symbol = $2)
+            AND timestamp >= $3
+            AND timestamp < $4
+            ORDER BY timestamp, id
+            "#,
+            self.exchange,
+            filter.symbol,
+            filter.start,
+            filter.end
+        )
+        .fetch(&self.pool);
+
+        let mut count = 0;
+        while let Some(row) = rows.try_next().await.map_err(MarketDataError::DatabaseError)? {
+            writer.write(TickData {
+                exchange: row.exchange,
+                timestamp: row.timestamp,
+                symbol: row.symbol,
+                price: row.price,
+                volume: row.volume,
+                side: row.side.trim().to_string(),
+                trade_id: row.trade_id,
+                is_maker: row.is_maker,
+            })?;
+            count += 1;
+        }
+        writer.finish()?;
+
+        info!("Exported {} ticks to {}", count, path.display());
+        Ok(count)
+    }
+
+    /// 按时间顺序导出某一周期的 K 线，返回导出的行数
+    pub async fn export_candles(
+        &self,
+        path: &Path,
+        format: FileFormat,
+        interval: &str,
+        filter: &ExportFilter,
+    ) -> Result<u64, TransferError> {
+        let mut writer = RecordWriter::<CandleRecord>::create(path, format)?;
+        let mut rows = sqlx::query!(
+            r#"
+            SELECT exchange, symbol, timeframe, open_time, open, high, low, close, volume
+            FROM candles
+            WHERE exchange = $1
+            AND ($2::varchar IS NULL OR symbol = $2)
+            AND timeframe = $3
+            AND open_time >= $4
+            AND open_time < $5
+            ORDER BY open_time, symbol
+            "#,
+            self.exchange,
+            filter.symbol,
+            interval,
+            filter.start,
+            filter.end
+        )
+        .fetch(&self.pool);
+
+        let mut count = 0;
+        while let Some(row) = rows.try_next().await.map_err(MarketDataError::DatabaseError)? {
+            writer.write(CandleRecord {
+                exchange: row.exchange,
+                open_time: row.open_time,
+                symbol: row.symbol,
+                interval: row.timeframe,
+                open: row.open,
+                high: row.high,
+                low: row.low,
+                close: row.close,
+                volume: row.volume,
+            })?;
+            count += 1;
+        }
+        writer.finish()?;
+
+        info!("Exported {} {} candles to {}", count, interval, path.display());
+        Ok(count)
+    }
+
+    /// 导入成交或 K 线文件，类型由列名决定。遇到不合法的行时停止导入，
+    /// 之前的批次已经写入；修正后重新导入不会产生重复数据。
+    /// 文件中的 exchange 列优先，没有该列时导入到当前交易所。
+    /// 传入 quality 时逐笔检查导入的成交，问题成交在生成 K 线前记录到 tick_flags。
+    pub async fn import_file(
+        &self,
+        path: &Path,
+        format: FileFormat,
+        quality: Option<&QualityConfig>,
+    ) -> Result<ImportReport, TransferError> {
+        let mut validator = quality.cloned().map(TickValidator::new);
+        let (mut reader, kind) = RecordReader::open(path, format, &self.exchange)?;
+        let mut report = ImportReport { kind, rows: 0, stored: 0 };
+        info!("Importing {:?} from {}", kind, path.display());
+
+        match kind {
+            DataKind::Ticks => {
+                while let Some(ticks) = reader.next_chunk::<TickData>()? {
+                    validate_rows(&ticks, report.rows)?;
+                    report.stored += self.store_tick_batch(&ticks).await?;
+                    if let Some(validator) = &mut validator {
+                        let now = Utc::now();
+                        let flags: Vec<_> = ticks.iter().flat_map(|tick| validator.check(tick, now)).collect();
+                        if !flags.is_empty() {
+                            warn!("Flagged {} data quality issues in {} imported ticks", flags.len(), ticks.len());
+                            self.store_tick_flags(&flags).await?;
+                        }
+                    }
+                    self.update_candles_for_ticks(&ticks).await?;
+                    report.rows += ticks.len() as u64;
+                    debug!("Imported {} ticks", report.rows);
+                }
+            }
+            DataKind::Candles => {
+                while let Some(candles) = reader.next_chunk::<CandleRecord>()? {
+                    validate_rows(&candles, report.rows)?;
+                    let mut groups: HashMap<(&str, &str, &str), Vec<MarketDataPoint>> = HashMap::new();
+                    for candle in &candles {
+                        groups
+                            .entry((&candle.exchange, &candle.symbol, &candle.interval))
+                            .or_default()
+                            .push(MarketDataPoint {
+                                timestamp: candle.open_time,
+                                symbol: candle.symbol.clone(),
+                                price: candle.close,
+                                volume: candle.volume,
+                                high: candle.high,
+                                low: candle.low,
+                                open: candle.open,
+                                close: candle.close,
+                            });
+                    }
+                    for ((exchange, symbol, interval), points) in groups {
+                        report.stored += self
+                            .clone()
+                            .with_exchange(exchange)
+                            .store_candles(symbol, interval, &points)
+                            .await?;
+                    }
+                    report.rows += candles.len() as u64;
+                    debug!("Imported {} candles", report.rows);
+                }
+            }
+        }
+
+        info!("Imported {} rows ({} stored) from {}", report.rows, report.stored, path.display());
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use dotenv::dotenv;
+    use sqlx::postgres::PgPoolOptions;
+
+    async fn setup_test_db() -> MarketDataManager {
+        dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL")
+            .expect("DATABASE_URL must be set for tests");
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&database_url)
+            .await
+            .expect("Failed to create test database pool");
+        MarketDataManager::new(pool)
+    }
+
+    async fn cleanup(manager: &MarketDataManager, symbol: &str) {
+        sqlx::query!("DELETE FROM tick_data WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM candles WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM tick_flags WHERE symbol = $1", symbol)
+            .execute(&manager.pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() {
+        let manager = setup_test_db().await;
+        let symbol = "TEST/TRANSFER";
+        cleanup(&manager, symbol).await;
+
+        let start = DateTime::parse_from_rfc3339("2024-03-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let ticks: Vec<TickData> = (0..20)
+            .map(|i| TickData {
+                exchange: "binance".to_string(),
+                timestamp: start + Duration::seconds(i * 15),
+                symbol: symbol.to_string(),
+                price: 100.0 + i as f64,
+                volume: 0.5,
+                side: if i % 2 == 0 { "BUY" } else { "SELL" }.to_string(),
+                trade_id: i.to_string(),
+                is_maker: i % 3 == 0,
+            })
+            .collect();
+        manager.store_tick_batch(&ticks).await.unwrap();
+        manager.update_candles_for_ticks(&ticks).await.unwrap();
+
+        let filter = ExportFilter {
+            symbol: Some(symbol.to_string()),
+            start,
+            end: start + Duration::hours(1),
+        };
+        let dir = std::env::temp_dir().join(format!("rust-trade-transfer-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for format in [FileFormat::Csv, FileFormat::Parquet] {
+            let ticks_path = dir.join(format!("ticks.{:?}", format).to_lowercase());
+            let candles_path = dir.join(format!("candles.{:?}", format).to_lowercase());
+            assert_eq!(manager.export_ticks(&ticks_path, format, &filter).await.unwrap(), 20);
+            assert_eq!(
+                manager.export_candles(&candles_path, format, "1m", &filter).await.unwrap(),
+                5
+            );
+
+            cleanup(&manager, symbol).await;
+            let report = manager.import_file(&candles_path, format, None).await.unwrap();
+            assert_eq!((report.kind, report.rows, report.stored), (DataKind::Candles, 5, 5));
+            let report = manager.import_file(&ticks_path, format, None).await.unwrap();
+            assert_eq!((report.kind, report.rows, report.stored), (DataKind::Ticks, 20, 20));
+
+            // 重复导入不会产生重复成交
+            let report = manager.import_file(&ticks_path, format, None).await.unwrap();
+            assert_eq!(report.stored, 0);
+
+            let imported = manager.get_market_data(symbol, start, start + Duration::hours(1)).await.unwrap();
+            assert_eq!(imported.len(), 20);
+            assert_eq!(imported.last().unwrap().price, 119.0);
+            let candles = manager.get_candles(symbol, "1m", start, start + Duration::hours(1)).await.unwrap();
+            assert_eq!(candles.len(), 5);
+            assert_eq!((candles[0].open, candles[0].close, candles[0].high), (100.0, 103.0, 103.0));
+        }
+
+        cleanup(&manager, symbol).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_import_file_without_exchange_column() {
+        let manager = setup_test_db().await.with_exchange("okx");
+        let symbol = "TEST/LEGACY";
+        cleanup(&manager, symbol).await;
+        let dir = std::env::temp_dir().join(format!("rust-trade-transfer-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("legacy.csv");
+        std::fs::write(
+            &path,
+            "timestamp,symbol,price,volume,side,trade_id,is_maker\n\
+             2024-03-01T00:00:00Z,TEST/LEGACY,100,1,BUY,1,false\n\
+             2024-03-01T00:00:30Z,TEST/LEGACY,100,1,BUY,2,false\n\
+             2024-03-01T00:00:10Z,TEST/LEGACY,100,1,BUY,3,false\n",
+        )
+        .unwrap();
+        let quality = QualityConfig::default();
+        let report = manager.import_file(&path, FileFormat::Csv, Some(&quality)).await.unwrap();
+        assert_eq!((report.kind, report.stored), (DataKind::Ticks, 3));
+
+        // 导入的成交同样逐笔检查
+        let flagged: Vec<String> = sqlx::query_scalar!("SELECT trade_id FROM tick_flags WHERE symbol = $1", symbol)
+            .fetch_all(&manager.pool)
+            .await
+            .unwrap();
+        assert_eq!(flagged, vec!["3".to_string()]);
+
+        // 旧文件导入到当前交易所，其他交易所查不到
+        let start = DateTime::parse_from_rfc3339("2024-03-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let end = start + Duration::minutes(1);
+        assert_eq!(manager.get_market_data(symbol, start, end).await.unwrap().len(), 3);
+        let binance = manager.clone().with_exchange("binance");
+        assert!(binance.get_market_data(symbol, start, end).await.unwrap().is_empty());
+
+        cleanup(&manager, symbol).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_invalid_files() {
+        let manager = setup_test_db().await;
+        let dir = std::env::temp_dir().join(format!("rust-trade-transfer-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("unknown.csv");
+        std::fs::write(&path, "time,symbol,price\n2024-03-01T00:00:00Z,BTCUSDT,1\n").unwrap();
+        assert!(matches!(
+            manager.import_file(&path, FileFormat::Csv, None).await,
+            Err(TransferError::Schema(_))
+        ));
+
+        let path = dir.join("invalid.csv");
+        std::fs::write(
+            &path,
+            "timestamp,symbol,price,volume,side,trade_id,is_maker\n\
+             2024-03-01T00:00:00Z,TEST/INVALID,-1,1,BUY,1,false\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            manager.import_file(&path, FileFormat::Csv, None).await,
+            Err(TransferError::InvalidRow { row: 1, .. })
+        ));
+
+        assert_eq!(FileFormat::from_path(Path::new("a/b.PARQUET")), Some(FileFormat::Parquet));
+        assert_eq!(FileFormat::from_path(Path::new("a/b.json")), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,8 +2,18 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
+// 加入交易所维度之前的数据都来自 Binance
+pub const DEFAULT_EXCHANGE: &str = "binance";
+
+fn default_exchange() -> String {
+    DEFAULT_EXCHANGE.to_string()
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TickData {
+    // 旧版本写入的落盘文件没有该字段
+    #[serde(default = "default_exchange")]
+    pub exchange: String,
     pub timestamp: DateTime<Utc>,
     pub symbol: String,
     pub price: f64,
@@ -16,6 +26,8 @@ pub struct TickData {
 #[derive(Clone)]
 pub struct MarketDataManager {
     pub pool: PgPool,
+    // 读取和写入 K 线时使用的交易所，成交按各自的 exchange 字段写入
+    pub exchange: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -28,4 +40,12 @@ pub struct MarketDataPoint {
     pub low: f64,
     pub open: f64,
     pub close: f64,
-}
\ No newline at end of file
+}
+// 成交数据中的时间空洞；两端成交 ID 都是数字时，missing_trades 为缺失的成交笔数
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TickGap {
+    pub symbol: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub missing_trades: Option<i64>,
+}
@@ -1,22 +1,262 @@
+use super::rate_limit::RateLimiter;
+use super::stream::{Channel, MarketEvent, MarketStream, StreamProtocol, Topic};
+use super::symbols::SymbolInfo;
 use super::types::*;
-use crate::data::types::MarketDataPoint;
+use crate::data::types::{MarketDataPoint, TickData};
 use chrono::{DateTime, TimeZone, Utc};
-use reqwest::{Client, Url};
+use reqwest::{Client, StatusCode, Url};
 use rust_decimal::Decimal;
+use serde::de::{self, DeserializeOwned, IgnoredAny, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
 use std::time::Duration;
-use tokio_tungstenite::connect_async;
-use tracing::{debug, error, info};
-use futures_util::{SinkExt, StreamExt};  
-use tokio_tungstenite::tungstenite::Message;  
+use tracing::warn;
+
+// 成交数据流：@trade 为逐笔成交，@aggTrade 为同价同方向合并后的成交
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TradeStream {
+    #[default]
+    Trade,
+    AggTrade,
+}
+
+// 预置的 Binance 接入环境
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BinanceNetwork {
+    #[default]
+    Mainnet,
+    // 现货测试网，需使用测试网单独申请的 API key
+    Testnet,
+    // Binance.US
+    Us,
+}
+
+impl BinanceNetwork {
+    pub fn endpoints(self) -> ExchangeEndpoints {
+        let (rest, ws) = match self {
+            BinanceNetwork::Mainnet => ("https://api.binance.com", "wss://stream.binance.com:9443"),
+            BinanceNetwork::Testnet => ("https://testnet.binance.vision", "wss://stream.testnet.binance.vision"),
+            BinanceNetwork::Us => ("https://api.binance.us", "wss://stream.binance.us:9443"),
+        };
+        ExchangeEndpoints {
+            rest_url: Url::parse(rest).expect("preset URL is valid"),
+            ws_url: Url::parse(ws).expect("preset URL is valid"),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct BinanceSpot {
     client: Client,
-    base_url: Url,
-    //ws_url: Url,
+    endpoints: ExchangeEndpoints,
     api_key: Option<String>,
     //api_secret: Option<String>,
+    trade_stream: TradeStream,
+    // 克隆出的实例共享同一个限流器
+    rate_limiter: Arc<RateLimiter>,
+}
+
+pub const EXCHANGE_NAME: &str = "binance";
+
+// WebSocket 连接满 24 小时会被服务端断开
+const MAX_CONNECTION_AGE: Duration = Duration::from_secs(24 * 3600);
+
+// 被限流（429）或封禁（418）后的最大重试次数
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+
+// 按 Binance 文档估算各 REST 接口的请求权重
+fn request_weight(endpoint: &str, params: &[(&str, String)]) -> u32 {
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _)| *key == name)
+            .and_then(|(_, value)| value.parse::<u32>().ok())
+    };
+    match endpoint {
+        "/api/v3/depth" => match param("limit").unwrap_or(100) {
+            0..=100 => 5,
+            101..=500 => 25,
+            501..=1000 => 50,
+            _ => 250,
+        },
+        "/api/v3/ticker/24hr" => 2,
+        "/api/v3/trades" | "/api/v3/historicalTrades" => 25,
+        "/api/v3/exchangeInfo" => 20,
+        "/api/v3/aggTrades" | "/api/v3/klines" => 2,
+        _ => 1,
+    }
+}
+
+// Retry-After 以秒为单位；缺失时按重试次数指数退避
+fn retry_after(headers: &reqwest::header::HeaderMap, attempt: u32) -> Duration {
+    headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .unwrap_or_else(|| Duration::from_secs(1 << attempt))
+}
+
+// 错误响应：{"code":-1121,"msg":"Invalid symbol."}
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    code: i64,
+    msg: String,
+}
+
+// 把错误响应映射为具体的 ExchangeError，无法解析时保留原始内容
+fn decode_error(status: StatusCode, body: &str) -> ExchangeError {
+    let Ok(error) = serde_json::from_str::<ErrorResponse>(body) else {
+        return ExchangeError::ApiError(format!("HTTP {}: {}", status, body));
+    };
+    match error.code {
+        -1121 => ExchangeError::InvalidSymbol(error.msg),
+        -1002 | -1022 | -2014 | -2015 => ExchangeError::AuthError(error.msg),
+        -1003 | -1015 => ExchangeError::RateLimitExceeded,
+        code => ExchangeError::ApiError(format!("Binance error {}: {}", code, error.msg)),
+    }
+}
+
+fn timestamp_millis(millis: i64) -> Result<DateTime<Utc>, ExchangeError> {
+    Utc.timestamp_millis_opt(millis)
+        .single()
+        .ok_or_else(|| ExchangeError::ApiError(format!("Invalid timestamp: {}", millis)))
+}
+
+fn to_f64(value: Decimal) -> Result<f64, ExchangeError> {
+    use rust_decimal::prelude::ToPrimitive;
+    value.to_f64()
+        .ok_or_else(|| ExchangeError::ApiError(format!("Invalid number: {}", value)))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TickerResponse {
+    last_price: Decimal,
+    bid_price: Decimal,
+    ask_price: Decimal,
+    volume: Decimal,
+}
+
+impl TickerResponse {
+    fn into_ticker(self, symbol: &str) -> Ticker {
+        Ticker {
+            symbol: symbol.to_string(),
+            timestamp: Utc::now(),
+            last_price: self.last_price,
+            bid_price: self.bid_price,
+            ask_price: self.ask_price,
+            volume_24h: self.volume,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DepthResponse {
+    last_update_id: u64,
+    // 每档为 [价格, 数量]
+    bids: Vec<(Decimal, Decimal)>,
+    asks: Vec<(Decimal, Decimal)>,
+}
+
+impl DepthResponse {
+    fn into_orderbook(self, symbol: &str) -> OrderBook {
+        let levels = |levels: Vec<(Decimal, Decimal)>| {
+            levels
+                .into_iter()
+                .map(|(price, quantity)| OrderBookLevel { price, quantity })
+                .collect()
+        };
+        OrderBook {
+            symbol: symbol.to_string(),
+            timestamp: Utc::now(),
+            last_update_id: Some(self.last_update_id),
+            bids: levels(self.bids),
+            asks: levels(self.asks),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TradeResponse {
+    price: Decimal,
+    qty: Decimal,
+    time: i64,
+    is_buyer_maker: bool,
+}
+
+impl TradeResponse {
+    fn into_trade(self, symbol: &str) -> Result<ExchangeTrade, ExchangeError> {
+        Ok(ExchangeTrade {
+            symbol: symbol.to_string(),
+            timestamp: timestamp_millis(self.time)?,
+            price: self.price,
+            quantity: self.qty,
+            is_buyer_maker: self.is_buyer_maker,
+        })
+    }
+}
+
+// K 线为数组：[开盘时间, 开, 高, 低, 收, 成交量, 收盘时间, ...]，后续字段不使用。
+// 按序列逐个读取并跳过其余元素，交易所在末尾新增字段时不影响解析
+#[derive(Debug)]
+struct KlineResponse {
+    open_time: i64,
+    open: Decimal,
+    high: Decimal,
+    low: Decimal,
+    close: Decimal,
+    volume: Decimal,
+}
+
+impl<'de> Deserialize<'de> for KlineResponse {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KlineVisitor;
+
+        impl<'de> Visitor<'de> for KlineVisitor {
+            type Value = KlineResponse;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a kline array with at least 6 elements")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<KlineResponse, A::Error> {
+                let missing = |index| de::Error::invalid_length(index, &self);
+                let kline = KlineResponse {
+                    open_time: seq.next_element()?.ok_or_else(|| missing(0))?,
+                    open: seq.next_element()?.ok_or_else(|| missing(1))?,
+                    high: seq.next_element()?.ok_or_else(|| missing(2))?,
+                    low: seq.next_element()?.ok_or_else(|| missing(3))?,
+                    close: seq.next_element()?.ok_or_else(|| missing(4))?,
+                    volume: seq.next_element()?.ok_or_else(|| missing(5))?,
+                };
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(kline)
+            }
+        }
+
+        deserializer.deserialize_seq(KlineVisitor)
+    }
+}
+
+impl KlineResponse {
+    fn into_market_data(self, symbol: &str) -> Result<MarketDataPoint, ExchangeError> {
+        Ok(MarketDataPoint {
+            timestamp: timestamp_millis(self.open_time)?,
+            symbol: symbol.to_string(),
+            price: to_f64(self.close)?,
+            volume: to_f64(self.volume)?,
+            high: to_f64(self.high)?,
+            low: to_f64(self.low)?,
+            open: to_f64(self.open)?,
+            close: to_f64(self.close)?,
+        })
+    }
 }
 
 impl BinanceSpot {
@@ -28,54 +268,98 @@ impl BinanceSpot {
             
         Self {
             client,
-            base_url: Url::parse("https://api.binance.com").unwrap(),
-            //ws_url: Url::parse("wss://stream.binance.com:9443").unwrap(),
+            endpoints: BinanceNetwork::default().endpoints(),
             api_key,
             //api_secret,
+            trade_stream: TradeStream::default(),
+            rate_limiter: Arc::new(RateLimiter::binance()),
         }
     }
+
+    /// 与其他实例共享限流器，例如同一 IP 下的采集器和回填任务
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    pub fn rate_limiter(&self) -> Arc<RateLimiter> {
+        self.rate_limiter.clone()
+    }
+
+    pub fn with_endpoints(mut self, endpoints: ExchangeEndpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    pub fn with_trade_stream(mut self, trade_stream: TradeStream) -> Self {
+        self.trade_stream = trade_stream;
+        self
+    }
     
-    async fn make_request(&self, endpoint: &str, params: Option<Vec<(&str, String)>>) 
-        -> Result<Value, ExchangeError> {
-        let mut url = self.base_url.join(endpoint)
+    async fn make_request<T: DeserializeOwned>(&self, endpoint: &str, params: Option<Vec<(&str, String)>>) 
+        -> Result<T, ExchangeError> {
+        let mut url = self.endpoints.rest_url.join(endpoint)
             .map_err(|e| ExchangeError::NetworkError(e.to_string()))?;
+        let params = params.unwrap_or_default();
+        let weight = request_weight(endpoint, &params);
         
-        if let Some(params) = params {
+        if !params.is_empty() {
             let mut query = url.query_pairs_mut();
-            for (key, value) in params {
-                query.append_pair(key, &value);
+            for (key, value) in &params {
+                query.append_pair(key, value);
             }
         }
         
-        let mut request = self.client.get(url);
-        if let Some(api_key) = &self.api_key {
-            request = request.header("X-MBX-APIKEY", api_key);
-        }
-        
-        let response = request
-            .send()
-            .await
-            .map_err(|e| ExchangeError::NetworkError(e.to_string()))?;
+        let mut attempt = 0;
+        loop {
+            self.rate_limiter.acquire(weight).await;
+
+            let mut request = self.client.get(url.clone());
+            if let Some(api_key) = &self.api_key {
+                request = request.header("X-MBX-APIKEY", api_key);
+            }
             
-        if !response.status().is_success() {
-            let error_text = response
+            let response = request
+                .send()
+                .await
+                .map_err(|e| ExchangeError::NetworkError(e.to_string()))?;
+
+            // 以交易所统计的已用权重为准
+            if let Some(used) = response
+                .headers()
+                .get("x-mbx-used-weight-1m")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u32>().ok())
+            {
+                self.rate_limiter.record_used_weight(used);
+            }
+
+            let status = response.status();
+            if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::IM_A_TEAPOT {
+                let wait = retry_after(response.headers(), attempt);
+                self.rate_limiter.back_off(wait);
+                if attempt >= MAX_RATE_LIMIT_RETRIES {
+                    return Err(ExchangeError::RateLimitExceeded);
+                }
+                attempt += 1;
+                warn!("{} returned {}, retry {}/{}", endpoint, status, attempt, MAX_RATE_LIMIT_RETRIES);
+                continue;
+            }
+                
+            let body = response
                 .text()
                 .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(ExchangeError::ApiError(error_text));
+                .map_err(|e| ExchangeError::NetworkError(e.to_string()))?;
+            if !status.is_success() {
+                return Err(decode_error(status, &body));
+            }
+            
+            return serde_json::from_str(&body)
+                .map_err(|e| ExchangeError::ApiError(format!("Unexpected response from {}: {}", endpoint, e)));
         }
-        
-        response.json::<Value>()
-            .await
-            .map_err(|e| ExchangeError::ApiError(e.to_string()))
     }
     
-    fn parse_decimal(value: &str) -> Result<Decimal, ExchangeError> {
-        value.parse()
-            .map_err(|_| ExchangeError::ApiError("Invalid decimal format".to_string()))
-    }
-
-    fn parse_ticker_message(&self, data: &serde_json::Value) -> Option<MarketDataPoint> {
+    fn parse_ticker_message(data: &Value) -> Option<MarketDataPoint> {
         // 提取必要的字段
         let symbol = data.get("s")?.as_str()?;
         let price = data.get("c")?.as_str()?;
@@ -96,22 +380,247 @@ impl BinanceSpot {
             close: price.parse().ok()?,
         })
     }
+
+    pub(crate) fn parse_trade_message(data: &Value) -> Option<TickData> {
+        // 逐笔成交使用 t 作为成交 ID，归集成交使用 a
+        let trade_id = match data.get("e")?.as_str()? {
+            "trade" => data.get("t")?.as_u64()?,
+            "aggTrade" => data.get("a")?.as_u64()?,
+            _ => return None,
+        };
+        // m 表示买方是 maker，此时主动方（taker）是卖方
+        let is_buyer_maker = data.get("m")?.as_bool()?;
+
+        Some(TickData {
+            exchange: EXCHANGE_NAME.to_string(),
+            timestamp: Utc.timestamp_millis_opt(data.get("T")?.as_i64()?).single()?,
+            symbol: data.get("s")?.as_str()?.to_string(),
+            price: data.get("p")?.as_str()?.parse().ok()?,
+            volume: data.get("q")?.as_str()?.parse().ok()?,
+            side: if is_buyer_maker { "SELL" } else { "BUY" }.to_string(),
+            trade_id: trade_id.to_string(),
+            is_maker: is_buyer_maker,
+        })
+    }
+
+    // 解析 REST 历史成交：/api/v3/historicalTrades 使用 id，/api/v3/aggTrades 使用 a
+    pub(crate) fn parse_rest_trade(symbol: &str, data: &Value) -> Option<TickData> {
+        let (trade_id, price, quantity, time, is_buyer_maker) = if data.get("a").is_some() {
+            (data.get("a")?, data.get("p")?, data.get("q")?, data.get("T")?, data.get("m")?)
+        } else {
+            (data.get("id")?, data.get("price")?, data.get("qty")?, data.get("time")?, data.get("isBuyerMaker")?)
+        };
+        let is_buyer_maker = is_buyer_maker.as_bool()?;
+
+        Some(TickData {
+            exchange: EXCHANGE_NAME.to_string(),
+            timestamp: Utc.timestamp_millis_opt(time.as_i64()?).single()?,
+            symbol: symbol.to_string(),
+            price: price.as_str()?.parse().ok()?,
+            volume: quantity.as_str()?.parse().ok()?,
+            side: if is_buyer_maker { "SELL" } else { "BUY" }.to_string(),
+            trade_id: trade_id.as_u64()?.to_string(),
+            is_maker: is_buyer_maker,
+        })
+    }
+
+    // 解析 exchangeInfo 中的一个交易对；缺少的过滤器按不限制处理
+    pub(crate) fn parse_symbol_info(data: &Value) -> Option<SymbolInfo> {
+        let mut info = SymbolInfo {
+            symbol: data.get("symbol")?.as_str()?.to_string(),
+            base_asset: data.get("baseAsset")?.as_str()?.to_string(),
+            quote_asset: data.get("quoteAsset")?.as_str()?.to_string(),
+            status: data.get("status")?.as_str()?.to_string(),
+            tick_size: Decimal::ZERO,
+            min_price: Decimal::ZERO,
+            max_price: Decimal::ZERO,
+            step_size: Decimal::ZERO,
+            min_qty: Decimal::ZERO,
+            max_qty: Decimal::ZERO,
+            market_step_size: Decimal::ZERO,
+            market_min_qty: Decimal::ZERO,
+            market_max_qty: Decimal::ZERO,
+            min_notional: Decimal::ZERO,
+        };
+        let mut has_market_lot_size = false;
+
+        let field = |filter: &Value, name: &str| -> Option<Decimal> {
+            filter.get(name)?.as_str()?.parse::<Decimal>().ok().map(|d| d.normalize())
+        };
+        for filter in data.get("filters")?.as_array()? {
+            match filter.get("filterType")?.as_str()? {
+                "PRICE_FILTER" => {
+                    info.tick_size = field(filter, "tickSize")?;
+                    info.min_price = field(filter, "minPrice")?;
+                    info.max_price = field(filter, "maxPrice")?;
+                }
+                "LOT_SIZE" => {
+                    info.step_size = field(filter, "stepSize")?;
+                    info.min_qty = field(filter, "minQty")?;
+                    info.max_qty = field(filter, "maxQty")?;
+                }
+                "MARKET_LOT_SIZE" => {
+                    info.market_step_size = field(filter, "stepSize")?;
+                    info.market_min_qty = field(filter, "minQty")?;
+                    info.market_max_qty = field(filter, "maxQty")?;
+                    has_market_lot_size = true;
+                }
+                // 旧的 MIN_NOTIONAL 已被 NOTIONAL 取代，两者都可能出现
+                "MIN_NOTIONAL" | "NOTIONAL" => {
+                    info.min_notional = field(filter, "minNotional")?;
+                }
+                _ => {}
+            }
+        }
+        // 没有单独的市价单规则时按 LOT_SIZE 检查
+        if !has_market_lot_size {
+            info.market_step_size = info.step_size;
+            info.market_min_qty = info.min_qty;
+            info.market_max_qty = info.max_qty;
+        }
+        Some(info)
+    }
+
+    pub(crate) fn parse_depth_message(data: &Value) -> Option<DepthUpdate> {
+        let parse_levels = |levels: &Value| -> Option<Vec<OrderBookLevel>> {
+            levels.as_array()?
+                .iter()
+                .map(|level| {
+                    Some(OrderBookLevel {
+                        price: level.get(0)?.as_str()?.parse().ok()?,
+                        quantity: level.get(1)?.as_str()?.parse().ok()?,
+                    })
+                })
+                .collect()
+        };
+
+        if data.get("e")?.as_str()? != "depthUpdate" {
+            return None;
+        }
+
+        Some(DepthUpdate {
+            symbol: data.get("s")?.as_str()?.to_string(),
+            event_time: Utc.timestamp_millis_opt(data.get("E")?.as_i64()?).single()?,
+            first_update_id: data.get("U")?.as_u64()?,
+            final_update_id: data.get("u")?.as_u64()?,
+            bids: parse_levels(data.get("b")?)?,
+            asks: parse_levels(data.get("a")?)?,
+        })
+    }
+
+    pub(crate) fn parse_kline_message(data: &Value) -> Option<KlineUpdate> {
+        if data.get("e")?.as_str()? != "kline" {
+            return None;
+        }
+        let kline = data.get("k")?;
+        let field = |name: &str| -> Option<f64> { kline.get(name)?.as_str()?.parse().ok() };
+        let symbol = data.get("s")?.as_str()?.to_string();
+        let close = field("c")?;
+
+        Some(KlineUpdate {
+            symbol: symbol.clone(),
+            interval: kline.get("i")?.as_str()?.to_string(),
+            candle: MarketDataPoint {
+                timestamp: Utc.timestamp_millis_opt(kline.get("t")?.as_i64()?).single()?,
+                symbol,
+                price: close,
+                volume: field("v")?,
+                high: field("h")?,
+                low: field("l")?,
+                open: field("o")?,
+                close,
+            },
+            is_closed: kline.get("x")?.as_bool()?,
+        })
+    }
+}
+
+// Binance 行情流：初始订阅写在 URL 中，之后通过 SUBSCRIBE/UNSUBSCRIBE 请求增减
+struct BinanceStream {
+    base_url: String,
+    trade_stream: TradeStream,
+    next_id: u64,
+}
+
+impl BinanceStream {
+    fn stream_name(&self, topic: &Topic) -> String {
+        let symbol = topic.symbol.to_lowercase();
+        match &topic.channel {
+            Channel::Ticker => format!("{}@ticker", symbol),
+            Channel::Trades => match self.trade_stream {
+                TradeStream::Trade => format!("{}@trade", symbol),
+                TradeStream::AggTrade => format!("{}@aggTrade", symbol),
+            },
+            Channel::Depth => format!("{}@depth@100ms", symbol),
+            Channel::Kline(interval) => format!("{}@kline_{}", symbol, interval),
+        }
+    }
+}
+
+impl StreamProtocol for BinanceStream {
+    fn connect_request(&mut self, topics: &[Topic]) -> Result<(String, Option<String>), ExchangeError> {
+        let names: Vec<String> = topics.iter().map(|topic| self.stream_name(topic)).collect();
+        let url = match names.len() {
+            // 不带订阅的原始连接，之后再发送 SUBSCRIBE
+            0 => format!("{}/ws", self.base_url),
+            // 单个流格式：wss://stream.binance.com:9443/ws/btcusdt@ticker
+            1 => format!("{}/ws/{}", self.base_url, names[0]),
+            // 多个流格式：wss://stream.binance.com:9443/stream?streams=btcusdt@ticker/ethusdt@ticker
+            _ => format!("{}/stream?streams={}", self.base_url, names.join("/")),
+        };
+        Ok((url, None))
+    }
+
+    fn subscription_message(&mut self, topics: &[Topic], subscribe: bool) -> Result<String, ExchangeError> {
+        self.next_id += 1;
+        let names: Vec<String> = topics.iter().map(|topic| self.stream_name(topic)).collect();
+        Ok(serde_json::json!({
+            "method": if subscribe { "SUBSCRIBE" } else { "UNSUBSCRIBE" },
+            "params": names,
+            "id": self.next_id,
+        })
+        .to_string())
+    }
+
+    fn parse(&self, text: &str) -> Result<Vec<MarketEvent>, ExchangeError> {
+        let Ok(mut data) = serde_json::from_str::<Value>(text) else {
+            warn!("Invalid Binance stream message: {}", text);
+            return Ok(Vec::new());
+        };
+        if let Some(error) = data.get("error") {
+            return Err(ExchangeError::ApiError(format!("Stream request failed: {}", error)));
+        }
+        // 订阅请求的应答：{"result":null,"id":1}
+        if data.get("id").is_some() {
+            return Ok(Vec::new());
+        }
+
+        // 多流格式的数据在 data 字段中，单流格式直接是数据本身
+        let payload = match data.get_mut("data") {
+            Some(stream_data) => stream_data.take(),
+            None => data,
+        };
+        let event = match payload.get("e").and_then(Value::as_str) {
+            Some("24hrTicker") => BinanceSpot::parse_ticker_message(&payload).map(MarketEvent::Ticker),
+            Some("trade") | Some("aggTrade") => BinanceSpot::parse_trade_message(&payload).map(MarketEvent::Trade),
+            Some("depthUpdate") => BinanceSpot::parse_depth_message(&payload).map(MarketEvent::Depth),
+            Some("kline") => BinanceSpot::parse_kline_message(&payload).map(MarketEvent::Kline),
+            _ => None,
+        };
+        Ok(event.into_iter().collect())
+    }
 }
 
 #[async_trait::async_trait]
 impl Exchange for BinanceSpot {
+    fn name(&self) -> &'static str {
+        EXCHANGE_NAME
+    }
+
     async fn get_ticker(&self, symbol: &str) -> Result<Ticker, ExchangeError> {
         let params = vec![("symbol", symbol.to_string())];
-        let data = self.make_request("/api/v3/ticker/24hr", Some(params)).await?;
-        
-        Ok(Ticker {
-            symbol: symbol.to_string(),
-            timestamp: Utc::now(),
-            last_price: Self::parse_decimal(data["lastPrice"].as_str().unwrap())?,
-            bid_price: Self::parse_decimal(data["bidPrice"].as_str().unwrap())?,
-            ask_price: Self::parse_decimal(data["askPrice"].as_str().unwrap())?,
-            volume_24h: Self::parse_decimal(data["volume"].as_str().unwrap())?,
-        })
+        let data: TickerResponse = self.make_request("/api/v3/ticker/24hr", Some(params)).await?;
+        Ok(data.into_ticker(symbol))
     }
     
     async fn get_orderbook(&self, symbol: &str, limit: u32) -> Result<OrderBook, ExchangeError> {
@@ -120,26 +629,8 @@ impl Exchange for BinanceSpot {
             ("limit", limit.to_string()),
         ];
         
-        let data = self.make_request("/api/v3/depth", Some(params)).await?;
-        
-        let parse_levels = |levels: &Value| -> Result<Vec<OrderBookLevel>, ExchangeError> {
-            levels.as_array()
-                .ok_or_else(|| ExchangeError::ApiError("Invalid orderbook data".to_string()))?
-                .iter()
-                .map(|level| {
-                    let price = Self::parse_decimal(level[0].as_str().unwrap())?;
-                    let quantity = Self::parse_decimal(level[1].as_str().unwrap())?;
-                    Ok(OrderBookLevel { price, quantity })
-                })
-                .collect()
-        };
-        
-        Ok(OrderBook {
-            symbol: symbol.to_string(),
-            timestamp: Utc::now(),
-            bids: parse_levels(&data["bids"])?,
-            asks: parse_levels(&data["asks"])?,
-        })
+        let data: DepthResponse = self.make_request("/api/v3/depth", Some(params)).await?;
+        Ok(data.into_orderbook(symbol))
     }
     
     async fn get_recent_trades(&self, symbol: &str, limit: u32) -> Result<Vec<ExchangeTrade>, ExchangeError> {
@@ -148,19 +639,51 @@ impl Exchange for BinanceSpot {
             ("limit", limit.to_string()),
         ];
         
-        let data = self.make_request("/api/v3/trades", Some(params)).await?;
-        
+        let data: Vec<TradeResponse> = self.make_request("/api/v3/trades", Some(params)).await?;
+        data.into_iter()
+            .map(|trade| trade.into_trade(symbol))
+            .collect()
+    }
+    
+    async fn get_historical_trades(
+        &self,
+        symbol: &str,
+        from_id: u64,
+        limit: u32,
+    ) -> Result<Vec<TickData>, ExchangeError> {
+        // 与实时订阅的成交流保持一致，否则成交 ID 对不上
+        let endpoint = match self.trade_stream {
+            TradeStream::Trade => "/api/v3/historicalTrades",
+            TradeStream::AggTrade => "/api/v3/aggTrades",
+        };
+        let params = vec![
+            ("symbol", symbol.to_string()),
+            ("fromId", from_id.to_string()),
+            ("limit", limit.to_string()),
+        ];
+
+        let data: Value = self.make_request(endpoint, Some(params)).await?;
+
         data.as_array()
-            .ok_or_else(|| ExchangeError::ApiError("Invalid trades data".to_string()))?
+            .ok_or_else(|| ExchangeError::ApiError("Invalid historical trades data".to_string()))?
             .iter()
             .map(|trade| {
-                Ok(ExchangeTrade {
-                    symbol: symbol.to_string(),
-                    timestamp: Utc.timestamp_millis_opt(trade["time"].as_i64().unwrap()).unwrap(),
-                    price: Self::parse_decimal(trade["price"].as_str().unwrap())?,
-                    quantity: Self::parse_decimal(trade["qty"].as_str().unwrap())?,
-                    is_buyer_maker: trade["isBuyerMaker"].as_bool().unwrap(),
-                })
+                Self::parse_rest_trade(symbol, trade)
+                    .ok_or_else(|| ExchangeError::ApiError(format!("Invalid trade: {}", trade)))
+            })
+            .collect()
+    }
+    
+    async fn get_symbols(&self) -> Result<Vec<SymbolInfo>, ExchangeError> {
+        let data: Value = self.make_request("/api/v3/exchangeInfo", None).await?;
+
+        data["symbols"]
+            .as_array()
+            .ok_or_else(|| ExchangeError::ApiError("Invalid exchange info".to_string()))?
+            .iter()
+            .map(|symbol| {
+                Self::parse_symbol_info(symbol)
+                    .ok_or_else(|| ExchangeError::ApiError(format!("Invalid symbol info: {}", symbol["symbol"])))
             })
             .collect()
     }
@@ -188,117 +711,253 @@ impl Exchange for BinanceSpot {
             params.push(("limit", limit.to_string()));
         }
         
-        let data = self.make_request("/api/v3/klines", Some(params)).await?;
-        
-        data.as_array()
-            .ok_or_else(|| ExchangeError::ApiError("Invalid kline data".to_string()))?
-            .iter()
-            .map(|kline| {
-                Ok(MarketDataPoint {
-                    timestamp: Utc.timestamp_millis_opt(kline[0].as_i64().unwrap()).unwrap(),
-                    symbol: symbol.to_string(),
-                    price: kline[4].as_str().unwrap().parse().unwrap(),
-                    volume: kline[5].as_str().unwrap().parse().unwrap(),
-                    high: kline[2].as_str().unwrap().parse().unwrap(),
-                    low: kline[3].as_str().unwrap().parse().unwrap(),
-                    open: kline[1].as_str().unwrap().parse().unwrap(),
-                    close: kline[4].as_str().unwrap().parse().unwrap(),
-                })
-            })
+        let data: Vec<KlineResponse> = self.make_request("/api/v3/klines", Some(params)).await?;
+        data.into_iter()
+            .map(|kline| kline.into_market_data(symbol))
             .collect()
     }
     
-    async fn subscribe_market_data(
-        &self,
-        symbols: &[String],
-        callback: Box<dyn Fn(MarketDataPoint) + Send + Sync>,
-    ) -> Result<(), ExchangeError> {
-        // 构建正确的 stream names
-        let stream_names: Vec<String> = symbols
+    fn max_connection_age(&self) -> Option<Duration> {
+        Some(MAX_CONNECTION_AGE)
+    }
+
+    fn stream(&self, topics: &[Topic]) -> Result<MarketStream, ExchangeError> {
+        let protocol = BinanceStream {
+            base_url: self.endpoints.ws_url.as_str().trim_end_matches('/').to_string(),
+            trade_stream: self.trade_stream,
+            next_id: 0,
+        };
+        MarketStream::spawn(protocol, topics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRADE: &str = include_str!("../../tests/fixtures/binance/trade.json");
+    const AGG_TRADE: &str = include_str!("../../tests/fixtures/binance/agg_trade.json");
+    const HISTORICAL_TRADES: &str = include_str!("../../tests/fixtures/binance/historical_trades.json");
+    const AGG_TRADES: &str = include_str!("../../tests/fixtures/binance/agg_trades.json");
+    const EXCHANGE_INFO: &str = include_str!("../../tests/fixtures/binance/exchange_info.json");
+    const TICKER: &str = include_str!("../../tests/fixtures/binance/ticker_24hr.json");
+    const KLINE: &str = include_str!("../../tests/fixtures/binance/kline.json");
+    const DEPTH: &str = include_str!("../../tests/fixtures/binance/depth_snapshot.json");
+    const KLINES: &str = include_str!("../../tests/fixtures/binance/klines.json");
+    const ERROR_INVALID_SYMBOL: &str = include_str!("../../tests/fixtures/binance/error_invalid_symbol.json");
+    const ERROR_INVALID_API_KEY: &str = include_str!("../../tests/fixtures/binance/error_invalid_api_key.json");
+    const ERROR_TOO_MANY_REQUESTS: &str = include_str!("../../tests/fixtures/binance/error_too_many_requests.json");
+
+    #[test]
+    fn test_parse_trade_message() {
+        let data: Value = serde_json::from_str(TRADE).unwrap();
+        let tick = BinanceSpot::parse_trade_message(&data["data"]).unwrap();
+
+        assert_eq!(tick.symbol, "BTCUSDT");
+        assert_eq!(tick.trade_id, "3412093912");
+        assert_eq!(tick.timestamp.timestamp_millis(), 1700000000123);
+        assert_eq!(tick.price, 37250.5);
+        assert_eq!(tick.volume, 0.012);
+        assert_eq!(tick.side, "SELL");
+        assert!(tick.is_maker);
+    }
+
+    #[test]
+    fn test_parse_agg_trade_message() {
+        let data: Value = serde_json::from_str(AGG_TRADE).unwrap();
+        let tick = BinanceSpot::parse_trade_message(&data).unwrap();
+
+        assert_eq!(tick.trade_id, "2871234567");
+        assert_eq!(tick.timestamp.timestamp_millis(), 1700000000456);
+        assert_eq!(tick.side, "BUY");
+        assert!(!tick.is_maker);
+    }
+
+    #[test]
+    fn test_parse_trade_message_ignores_other_events() {
+        let data = serde_json::json!({"e": "24hrTicker", "s": "BTCUSDT"});
+        assert!(BinanceSpot::parse_trade_message(&data).is_none());
+    }
+
+    #[test]
+    fn test_parse_kline_message() {
+        let data: Value = serde_json::from_str(KLINE).unwrap();
+        let kline = BinanceSpot::parse_kline_message(&data["data"]).unwrap();
+
+        assert_eq!((kline.symbol.as_str(), kline.interval.as_str()), ("BTCUSDT", "1m"));
+        assert_eq!(kline.candle.timestamp.timestamp_millis(), 1699999980000);
+        assert_eq!((kline.candle.open, kline.candle.close, kline.candle.volume), (37240.0, 37250.5, 12.345));
+        assert!(!kline.is_closed);
+    }
+
+    #[test]
+    fn test_stream_protocol() {
+        let mut protocol = BinanceStream {
+            base_url: "wss://stream.binance.com:9443".to_string(),
+            trade_stream: TradeStream::AggTrade,
+            next_id: 0,
+        };
+        let topics = [Topic::trades("BTCUSDT"), Topic::kline("ETHUSDT", "1h")];
+        assert_eq!(
+            protocol.connect_request(&topics).unwrap().0,
+            "wss://stream.binance.com:9443/stream?streams=btcusdt@aggTrade/ethusdt@kline_1h"
+        );
+        let request: Value = serde_json::from_str(
+            &protocol.subscription_message(&[Topic::depth("BTCUSDT")], false).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(request["method"], "UNSUBSCRIBE");
+        assert_eq!(request["params"][0], "btcusdt@depth@100ms");
+        assert_eq!(request["id"], 1);
+
+        assert!(matches!(protocol.parse(KLINE).unwrap()[..], [MarketEvent::Kline(_)]));
+        assert!(protocol.parse(r#"{"result":null,"id":1}"#).unwrap().is_empty());
+        assert!(protocol.parse(r#"{"error":{"code":2,"msg":"Invalid request"},"id":2}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_exchange_info() {
+        let data: Value = serde_json::from_str(EXCHANGE_INFO).unwrap();
+        let symbols: Vec<SymbolInfo> = data["symbols"].as_array().unwrap()
             .iter()
-            .map(|s| format!("{}@ticker", s.to_lowercase()))
+            .map(|symbol| BinanceSpot::parse_symbol_info(symbol).unwrap())
             .collect();
 
-        // 正确构建 WebSocket URL，避免重复的 'ws' 路径
-        let ws_url = if stream_names.len() == 1 {
-            // 单个交易对格式：wss://stream.binance.com:9443/ws/btcusdt@ticker
-            format!("wss://stream.binance.com:9443/ws/{}", stream_names[0])
-        } else {
-            // 多个交易对格式：wss://stream.binance.com:9443/stream?streams=btcusdt@ticker/ethusdt@ticker
-            format!("wss://stream.binance.com:9443/stream?streams={}", stream_names.join("/"))
-        };
+        let btc = &symbols[0];
+        assert_eq!((btc.base_asset.as_str(), btc.quote_asset.as_str()), ("BTC", "USDT"));
+        assert!(btc.is_trading());
+        assert_eq!(btc.tick_size.to_string(), "0.01");
+        assert_eq!(btc.step_size.to_string(), "0.00001");
+        assert_eq!(btc.max_qty.to_string(), "9000");
+        assert_eq!(btc.market_max_qty.to_string(), "83.52391474");
+        assert!(btc.market_step_size.is_zero());
+        assert_eq!(btc.min_notional.to_string(), "5");
 
-        info!("Connecting to Binance WebSocket: {}", ws_url);
+        let eth = &symbols[1];
+        assert!(!eth.is_trading());
+        assert_eq!(eth.min_notional.to_string(), "0.0001");
+    }
 
-        // 建立 WebSocket 连接
-        let (ws_stream, _response) = connect_async(&ws_url)
-            .await
-            .map_err(|e| ExchangeError::NetworkError(format!("WebSocket connection failed: {}", e)))?;
+    #[test]
+    fn test_parse_rest_responses() {
+        let ticker = serde_json::from_str::<TickerResponse>(TICKER).unwrap().into_ticker("BTCUSDT");
+        assert_eq!(ticker.last_price.to_string(), "37250.50000000");
+        assert_eq!(ticker.bid_price.to_string(), "37250.49000000");
+        assert_eq!(ticker.volume_24h.to_string(), "24569.83412000");
 
-        info!("WebSocket connection established successfully");
+        let book = serde_json::from_str::<DepthResponse>(DEPTH).unwrap().into_orderbook("BTCUSDT");
+        assert_eq!(book.last_update_id, Some(1027024));
+        assert_eq!(book.bids.len(), 3);
+        assert_eq!(book.asks[0].quantity.to_string(), "1.50000000");
 
-        let (mut write, mut read) = ws_stream.split();
+        // /api/v3/trades 与 historicalTrades 格式相同
+        let trades: Vec<ExchangeTrade> = serde_json::from_str::<Vec<TradeResponse>>(HISTORICAL_TRADES)
+            .unwrap()
+            .into_iter()
+            .map(|trade| trade.into_trade("BTCUSDT").unwrap())
+            .collect();
+        assert_eq!(trades[1].timestamp.timestamp_millis(), 1700000000350);
+        assert!(trades[0].is_buyer_maker);
 
-        // 对于多个交易对，发送订阅消息
-        if stream_names.len() > 1 {
-            let subscribe_msg = serde_json::json!({
-                "method": "SUBSCRIBE",
-                "params": stream_names,
-                "id": 1
-            });
+        let klines: Vec<MarketDataPoint> = serde_json::from_str::<Vec<KlineResponse>>(KLINES)
+            .unwrap()
+            .into_iter()
+            .map(|kline| kline.into_market_data("BTCUSDT").unwrap())
+            .collect();
+        assert_eq!(klines.len(), 2);
+        assert_eq!(klines[0].timestamp.timestamp(), 1699999980);
+        assert_eq!((klines[0].open, klines[0].close), (37245.1, 37250.5));
+        assert_eq!(klines[1].volume, 3.5);
+    }
 
-            write
-                .send(Message::Text(subscribe_msg.to_string()))
-                .await
-                .map_err(|e| ExchangeError::NetworkError(format!("Failed to send subscription: {}", e)))?;
+    #[test]
+    fn test_unexpected_response_is_an_error() {
+        // 错误响应或字段变化不应导致 panic
+        assert!(serde_json::from_str::<TickerResponse>(ERROR_INVALID_SYMBOL).is_err());
+        assert!(serde_json::from_str::<DepthResponse>(r#"{"lastUpdateId": 1, "bids": [["x", "1"]], "asks": []}"#).is_err());
+        assert!(serde_json::from_str::<Vec<KlineResponse>>(TICKER).is_err());
+        assert!(serde_json::from_str::<Vec<KlineResponse>>(r#"[[1699999980000, "1", "2", "0.5"]]"#).is_err());
 
-            info!("Subscription message sent: {}", subscribe_msg);
-        }
+        // 末尾新增的字段被忽略
+        let klines = serde_json::from_str::<Vec<KlineResponse>>(
+            r#"[[1699999980000, "1", "2", "0.5", "1.5", "10", 1700000039999, "15", 3, "5", "7", "0", "new"]]"#,
+        )
+        .unwrap();
+        assert_eq!((klines[0].open_time, klines[0].close), (1699999980000, Decimal::new(15, 1)));
+    }
 
-        // 处理接收到的消息
-        while let Some(msg_result) = read.next().await {
-            match msg_result {
-                Ok(msg) => {
-                    match msg {
-                        Message::Text(text) => {
-                            info!("Received market data: {}", text);
-                            debug!("Received message: {}", text);
-                            
-                            if let Ok(data) = serde_json::from_str::<serde_json::Value>(&text) {
-                                // 处理市场数据
-                                let ticker_data = if let Some(stream_data) = data.get("data") {
-                                    stream_data // 多流格式
-                                } else {
-                                    &data // 单流格式
-                                };
-
-                                if let Some(market_data) = self.parse_ticker_message(ticker_data) {
-                                    info!("Successfully parsed market data for {}: price={}", 
-                                            market_data.symbol, market_data.price);
-                                    callback(market_data);
-                                }
-                            }
-                        }
-                        Message::Ping(data) => {
-                            write
-                                .send(Message::Pong(data))
-                                .await
-                                .map_err(|e| ExchangeError::NetworkError(format!("Failed to send pong: {}", e)))?;
-                        }
-                        Message::Close(frame) => {
-                            error!("WebSocket closed by server: {:?}", frame);
-                            return Err(ExchangeError::NetworkError("Connection closed by server".into()));
-                        }
-                        _ => {}
-                    }
-                }
-                Err(e) => {
-                    error!("WebSocket error: {}", e);
-                    return Err(ExchangeError::NetworkError(e.to_string()));
-                }
-            }
-        }
+    #[test]
+    fn test_decode_error() {
+        assert!(matches!(
+            decode_error(StatusCode::BAD_REQUEST, ERROR_INVALID_SYMBOL),
+            ExchangeError::InvalidSymbol(msg) if msg == "Invalid symbol."
+        ));
+        assert!(matches!(
+            decode_error(StatusCode::UNAUTHORIZED, ERROR_INVALID_API_KEY),
+            ExchangeError::AuthError(_)
+        ));
+        assert!(matches!(
+            decode_error(StatusCode::BAD_REQUEST, ERROR_TOO_MANY_REQUESTS),
+            ExchangeError::RateLimitExceeded
+        ));
+        assert!(matches!(
+            decode_error(StatusCode::BAD_REQUEST, r#"{"code":-1100,"msg":"Illegal characters found in parameter 'symbol'"}"#),
+            ExchangeError::ApiError(msg) if msg.starts_with("Binance error -1100")
+        ));
+        assert!(matches!(
+            decode_error(StatusCode::BAD_GATEWAY, "<html>Bad Gateway</html>"),
+            ExchangeError::ApiError(msg) if msg.contains("502")
+        ));
+    }
+
+    #[test]
+    fn test_network_presets() {
+        let testnet: BinanceNetwork = serde_json::from_str(r#""testnet""#).unwrap();
+        assert_eq!(testnet.endpoints().rest_url.as_str(), "https://testnet.binance.vision/");
+        assert_eq!(BinanceNetwork::Us.endpoints().ws_url.host_str(), Some("stream.binance.us"));
+        assert_eq!(BinanceNetwork::default().endpoints(), BinanceNetwork::Mainnet.endpoints());
+
+        // 本地模拟服务器
+        let spot = BinanceSpot::new(None).with_endpoints(ExchangeEndpoints {
+            rest_url: Url::parse("http://127.0.0.1:8081").unwrap(),
+            ws_url: Url::parse("ws://127.0.0.1:8081").unwrap(),
+        });
+        assert_eq!(
+            spot.endpoints.rest_url.join("/api/v3/klines").unwrap().as_str(),
+            "http://127.0.0.1:8081/api/v3/klines"
+        );
+    }
 
-        Ok(())
+    #[test]
+    fn test_request_weight() {
+        let limit = |n: u32| vec![("symbol", "BTCUSDT".to_string()), ("limit", n.to_string())];
+        assert_eq!(request_weight("/api/v3/depth", &limit(100)), 5);
+        assert_eq!(request_weight("/api/v3/depth", &limit(1000)), 50);
+        assert_eq!(request_weight("/api/v3/depth", &limit(5000)), 250);
+        assert_eq!(request_weight("/api/v3/historicalTrades", &limit(1000)), 25);
+        assert_eq!(request_weight("/api/v3/exchangeInfo", &[]), 20);
+        assert_eq!(request_weight("/api/v3/klines", &limit(1000)), 2);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_rest_trades() {
+        let data: Value = serde_json::from_str(HISTORICAL_TRADES).unwrap();
+        let ticks: Vec<TickData> = data.as_array().unwrap()
+            .iter()
+            .map(|trade| BinanceSpot::parse_rest_trade("BTCUSDT", trade).unwrap())
+            .collect();
+
+        assert_eq!(ticks.len(), 2);
+        assert_eq!(ticks[0].trade_id, "3412093913");
+        assert_eq!(ticks[0].side, "SELL");
+        assert_eq!(ticks[1].timestamp.timestamp_millis(), 1700000000350);
+        assert_eq!(ticks[1].volume, 0.5);
+        assert_eq!(ticks[1].side, "BUY");
+
+        let data: Value = serde_json::from_str(AGG_TRADES).unwrap();
+        let tick = BinanceSpot::parse_rest_trade("BTCUSDT", &data[0]).unwrap();
+        assert_eq!(tick.trade_id, "2871234568");
+        assert_eq!(tick.price, 37251.0);
+        assert!(!tick.is_maker);
+    }
+}
@@ -0,0 +1,233 @@
+// trading-core/src/exchange/kline.rs
+// 交易所推送的实时 K 线：收盘的 K 线直接写入 candles 表，最新的 K 线（包括未收盘的）
+// 推送给 MarketDataCache 和订阅者，策略可以直接使用交易所计算的收盘价。
+
+use super::reconnect::{Backoff, ReconnectConfig};
+use super::stream::{ConnectionState, MarketEvent, Topic};
+use super::types::{Exchange, ExchangeError, KlineUpdate};
+use crate::data::cache::MarketDataCache;
+use crate::data::types::MarketDataManager;
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+use tracing::{error, info};
+
+const CHANNEL_BUFFER_SIZE: usize = 1000;
+
+/// 订阅多个交易对和周期的实时 K 线，断线后按退避重连，直到调用 stop
+pub struct LiveKlineManager {
+    exchange: Arc<dyn Exchange>,
+    manager: MarketDataManager,
+    topics: Vec<Topic>,
+    latest: Arc<RwLock<HashMap<(String, String), KlineUpdate>>>,
+    cache: Option<Arc<MarketDataCache>>,
+    reconnect: ReconnectConfig,
+    update_tx: broadcast::Sender<KlineUpdate>,
+    shutdown_tx: broadcast::Sender<()>,
+}
+
+impl LiveKlineManager {
+    pub fn new(
+        exchange: Arc<dyn Exchange>,
+        manager: MarketDataManager,
+        symbols: &[String],
+        intervals: &[String],
+    ) -> Self {
+        let (update_tx, _) = broadcast::channel(CHANNEL_BUFFER_SIZE);
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let topics = symbols
+            .iter()
+            .flat_map(|symbol| intervals.iter().map(move |interval| Topic::kline(symbol.to_uppercase(), interval.as_str())))
+            .collect();
+        Self {
+            // K 线按交易所写入
+            manager: manager.with_exchange(exchange.name()),
+            exchange,
+            topics,
+            latest: Arc::new(RwLock::new(HashMap::new())),
+            cache: None,
+            reconnect: ReconnectConfig::default(),
+            update_tx,
+            shutdown_tx,
+        }
+    }
+
+    pub fn with_cache(mut self, cache: Arc<MarketDataCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn with_reconnect(mut self, config: ReconnectConfig) -> Self {
+        self.reconnect = config;
+        self
+    }
+
+    /// 订阅 K 线更新，包括未收盘和刚收盘的 K 线
+    pub fn subscribe(&self) -> broadcast::Receiver<KlineUpdate> {
+        self.update_tx.subscribe()
+    }
+
+    /// 该交易对和周期最新的一根 K 线
+    pub fn latest(&self, symbol: &str, interval: &str) -> Option<KlineUpdate> {
+        self.latest
+            .read()
+            .ok()?
+            .get(&(symbol.to_uppercase(), interval.to_string()))
+            .cloned()
+    }
+
+    pub async fn run(&self) {
+        let name = self.exchange.name();
+        let mut shutdown_rx = self.shutdown_tx.subscribe();
+        let mut backoff = self.reconnect.backoff();
+        loop {
+            let result = tokio::select! {
+                result = self.run_connection(&mut backoff) => result,
+                _ = shutdown_rx.recv() => break,
+            };
+            let delay = backoff.next_delay();
+            match result {
+                Ok(()) => info!("{} kline stream closed, reconnecting in {:?}", name, delay),
+                Err(e) => error!("{} kline stream failed: {}, reconnecting in {:?}", name, e, delay),
+            }
+            tokio::select! {
+                _ = sleep(delay) => {}
+                _ = shutdown_rx.recv() => break,
+            }
+        }
+        info!("Stopped {} kline stream", name);
+    }
+
+    pub fn stop(&self) {
+        let _ = self.shutdown_tx.send(());
+    }
+
+    async fn run_connection(&self, backoff: &mut Backoff) -> Result<(), ExchangeError> {
+        let mut stream = self.exchange.stream(&self.topics)?;
+        while let Some(event) = stream.next().await {
+            match event {
+                MarketEvent::Kline(update) => {
+                    backoff.reset();
+                    self.on_update(update).await;
+                }
+                MarketEvent::State(ConnectionState::Failed(e)) => return Err(e),
+                MarketEvent::State(ConnectionState::Closed) => return Ok(()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    async fn on_update(&self, update: KlineUpdate) {
+        // 同一根 K 线收盘后可能再次推送，写入时覆盖
+        if update.is_closed {
+            if let Err(e) = self
+                .manager
+                .store_candles(&update.symbol, &update.interval, std::slice::from_ref(&update.candle))
+                .await
+            {
+                error!("Failed to store {} {} kline: {}", update.symbol, update.interval, e);
+            }
+        }
+
+        if let Ok(mut latest) = self.latest.write() {
+            latest.insert((update.symbol.clone(), update.interval.clone()), update.clone());
+        }
+        if let Some(cache) = &self.cache {
+            cache.update_kline(self.exchange.name(), update.clone());
+        }
+        // 没有订阅者时发送会失败，忽略即可
+        let _ = self.update_tx.send(update);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::binance::BinanceSpot;
+    use crate::tests::mock_exchange::{MockExchange, StreamEvent};
+    use dotenv::dotenv;
+    use sqlx::postgres::PgPoolOptions;
+    use std::time::Duration;
+
+    fn kline_message(open_time: i64, close: &str, is_closed: bool) -> StreamEvent {
+        StreamEvent::Text(
+            serde_json::json!({
+                "e": "kline", "E": open_time + 30_000, "s": "KLINEUSDT",
+                "k": {
+                    "t": open_time, "T": open_time + 59_999, "s": "KLINEUSDT", "i": "1m",
+                    "o": "100.0", "c": close, "h": "102.0", "l": "99.0", "v": "3.5", "x": is_closed
+                }
+            })
+            .to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_closed_klines_are_stored_and_live_klines_published() {
+        dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for tests");
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&database_url)
+            .await
+            .expect("Failed to create database pool");
+        let cleanup = || async {
+            sqlx::query("DELETE FROM candles WHERE symbol = 'KLINEUSDT'")
+                .execute(&pool)
+                .await
+                .unwrap();
+        };
+        cleanup().await;
+
+        let open_time = 1_699_999_980_000;
+        let mock = MockExchange::start().await.unwrap();
+        mock.push_stream(vec![
+            kline_message(open_time, "100.5", false),
+            kline_message(open_time, "101.0", true),
+            kline_message(open_time + 60_000, "101.2", false),
+        ]);
+        let exchange = BinanceSpot::new(None).with_endpoints(mock.endpoints());
+        let cache = Arc::new(MarketDataCache::new(10));
+        let manager = Arc::new(
+            LiveKlineManager::new(
+                Arc::new(exchange),
+                MarketDataManager::new(pool.clone()),
+                &["klineusdt".to_string()],
+                &["1m".to_string()],
+            )
+            .with_cache(cache.clone()),
+        );
+        let mut updates = manager.subscribe();
+
+        let runner = manager.clone();
+        let handle = tokio::spawn(async move { runner.run().await });
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            let update = tokio::time::timeout(Duration::from_secs(5), updates.recv()).await.unwrap().unwrap();
+            received.push((update.candle.close, update.is_closed));
+        }
+        manager.stop();
+        handle.await.unwrap();
+
+        assert_eq!(received, vec![(100.5, false), (101.0, true), (101.2, false)]);
+        assert_eq!(mock.stream_connections(), vec!["/ws/klineusdt@kline_1m".to_string()]);
+
+        // 只有收盘的 K 线写入数据库，缓存中是最新的未收盘 K 线
+        let start = chrono::DateTime::from_timestamp_millis(open_time).unwrap();
+        let candles = MarketDataManager::new(pool.clone())
+            .get_candles("KLINEUSDT", "1m", start, start + chrono::Duration::minutes(5))
+            .await
+            .unwrap();
+        assert_eq!(candles.len(), 1);
+        assert_eq!((candles[0].close, candles[0].volume), (101.0, 3.5));
+
+        let live = cache.get_kline("binance", "KLINEUSDT", "1m").unwrap();
+        assert_eq!((live.candle.close, live.is_closed), (101.2, false));
+        assert_eq!(manager.latest("klineusdt", "1m").unwrap().candle.close, 101.2);
+
+        cleanup().await;
+    }
+}
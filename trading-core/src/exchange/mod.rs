@@ -1,2 +1,9 @@
 pub mod types;
-pub mod binance;
\ No newline at end of file
+pub mod binance;
+pub mod okx;
+pub mod orderbook;
+pub mod symbols;
+pub mod rate_limit;
+pub mod stream;
+pub mod reconnect;
+pub mod kline;
\ No newline at end of file
@@ -0,0 +1,976 @@
+// trading-core/src/exchange/okx.rs
+// OKX v5 现货接口。OKX 使用 BTC-USDT 形式的 instId，对外统一转换为 BTCUSDT；
+// 数值和时间戳均以字符串返回，成功与失败都包在 {"code","msg","data"} 中。
+
+use super::rate_limit::RateLimiter;
+use super::stream::{Channel, MarketEvent, MarketStream, StreamProtocol, Topic};
+use super::symbols::{normalize_symbol, SymbolInfo};
+use super::types::*;
+use crate::data::backfill::interval_duration;
+use crate::data::types::{MarketDataPoint, TickData};
+use chrono::{DateTime, Duration as ChronoDuration, TimeZone, Utc};
+use reqwest::{Client, StatusCode, Url};
+use rust_decimal::Decimal;
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+pub const EXCHANGE_NAME: &str = "okx";
+
+// 行情接口大多限制为每 2 秒 20 次
+const REQUESTS_PER_WINDOW: u32 = 20;
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(2);
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+// history-candles 和 history-trades 每次最多返回 100 条
+const MAX_PAGE_SIZE: u32 = 100;
+// 补成交时最多向前翻 1000 页（10 万笔）
+const MAX_HISTORY_TRADE_PAGES: usize = 1000;
+// 连接 30 秒无数据会被服务端断开，定期发送 ping
+const PING_INTERVAL: Duration = Duration::from_secs(25);
+
+// 按长度从长到短匹配，避免 USDT 被识别为 USD
+const QUOTE_ASSETS: &[&str] = &[
+    "USDT", "USDC", "EURT", "TUSD", "DAI", "USD", "EUR", "TRY", "BRL", "AUD", "SGD", "BTC", "ETH", "OKB",
+];
+
+/// BTCUSDT、btc/usdt、BTC-USDT -> BTC-USDT
+pub fn to_inst_id(symbol: &str) -> Result<String, ExchangeError> {
+    let upper = symbol.to_ascii_uppercase();
+    if let Some((base, quote)) = upper.split_once(['-', '/', '_']) {
+        return Ok(format!("{}-{}", base, quote));
+    }
+    QUOTE_ASSETS
+        .iter()
+        .find(|quote| upper.len() > quote.len() && upper.ends_with(*quote))
+        .map(|quote| format!("{}-{}", &upper[..upper.len() - quote.len()], quote))
+        .ok_or_else(|| ExchangeError::InvalidSymbol(symbol.to_string()))
+}
+
+// OKX 周期写法：分钟小写，小时及以上大写，日线及以上使用 UTC 时区
+fn okx_bar(interval: &str) -> Result<String, ExchangeError> {
+    let unsupported = || ExchangeError::ApiError(format!("Unsupported interval: {}", interval));
+    let unit = interval.chars().last().ok_or_else(unsupported)?;
+    let count = &interval[..interval.len() - unit.len_utf8()];
+    if count.parse::<u32>().is_err() {
+        return Err(unsupported());
+    }
+    match unit {
+        'm' => Ok(interval.to_string()),
+        'h' => Ok(format!("{}H", count)),
+        'd' => Ok(format!("{}Dutc", count)),
+        'w' => Ok(format!("{}Wutc", count)),
+        _ => Err(unsupported()),
+    }
+}
+
+// okx_bar 的逆转换：1H -> 1h，1Dutc -> 1d
+fn interval_from_bar(bar: &str) -> String {
+    bar.trim_end_matches("utc").to_ascii_lowercase()
+}
+
+fn from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    let value = String::deserialize(deserializer)?;
+    value.parse().map_err(serde::de::Error::custom)
+}
+
+fn to_f64(value: Decimal) -> Result<f64, ExchangeError> {
+    use rust_decimal::prelude::ToPrimitive;
+    value.to_f64()
+        .ok_or_else(|| ExchangeError::ApiError(format!("Invalid number: {}", value)))
+}
+
+fn timestamp_millis(millis: i64) -> Result<DateTime<Utc>, ExchangeError> {
+    Utc.timestamp_millis_opt(millis)
+        .single()
+        .ok_or_else(|| ExchangeError::ApiError(format!("Invalid timestamp: {}", millis)))
+}
+
+#[derive(Debug, Deserialize)]
+struct Envelope<T> {
+    code: String,
+    msg: String,
+    data: Option<T>,
+}
+
+fn decode_error(code: &str, msg: &str) -> ExchangeError {
+    match code {
+        "51001" => ExchangeError::InvalidSymbol(msg.to_string()),
+        "50011" | "50061" => ExchangeError::RateLimitExceeded,
+        "50100" | "50101" | "50102" | "50103" | "50104" | "50105" | "50111" | "50112" | "50113" => {
+            ExchangeError::AuthError(msg.to_string())
+        }
+        code => ExchangeError::ApiError(format!("OKX error {}: {}", code, msg)),
+    }
+}
+
+// 解析响应体：code 不为 "0" 时无论 HTTP 状态码都视为失败
+fn parse_response<T: DeserializeOwned>(status: StatusCode, body: &str) -> Result<T, ExchangeError> {
+    match serde_json::from_str::<Envelope<T>>(body) {
+        Ok(Envelope { code, data: Some(data), .. }) if code == "0" => Ok(data),
+        Ok(envelope) => Err(decode_error(&envelope.code, &envelope.msg)),
+        Err(e) => match serde_json::from_str::<Envelope<IgnoredAny>>(body) {
+            Ok(envelope) if envelope.code != "0" => Err(decode_error(&envelope.code, &envelope.msg)),
+            _ if !status.is_success() => Err(ExchangeError::ApiError(format!("HTTP {}: {}", status, body))),
+            _ => Err(ExchangeError::ApiError(format!("Unexpected OKX response: {}", e))),
+        },
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TickerResponse {
+    inst_id: String,
+    last: Decimal,
+    bid_px: Decimal,
+    ask_px: Decimal,
+    open24h: Decimal,
+    high24h: Decimal,
+    low24h: Decimal,
+    vol24h: Decimal,
+    #[serde(deserialize_with = "from_str")]
+    ts: i64,
+}
+
+impl TickerResponse {
+    fn into_ticker(self) -> Result<Ticker, ExchangeError> {
+        Ok(Ticker {
+            symbol: normalize_symbol(&self.inst_id),
+            timestamp: timestamp_millis(self.ts)?,
+            last_price: self.last,
+            bid_price: self.bid_px,
+            ask_price: self.ask_px,
+            volume_24h: self.vol24h,
+        })
+    }
+
+    fn into_market_data(self) -> Result<MarketDataPoint, ExchangeError> {
+        Ok(MarketDataPoint {
+            timestamp: timestamp_millis(self.ts)?,
+            symbol: normalize_symbol(&self.inst_id),
+            price: to_f64(self.last)?,
+            volume: to_f64(self.vol24h)?,
+            high: to_f64(self.high24h)?,
+            low: to_f64(self.low24h)?,
+            open: to_f64(self.open24h)?,
+            close: to_f64(self.last)?,
+        })
+    }
+}
+
+// 每档为 [价格, 数量, 已废弃字段, 订单数]
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct BookLevel(Decimal, Decimal, IgnoredAny, IgnoredAny);
+
+fn book_levels(levels: Vec<BookLevel>) -> Vec<OrderBookLevel> {
+    levels
+        .into_iter()
+        .map(|BookLevel(price, quantity, ..)| OrderBookLevel { price, quantity })
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BookResponse {
+    asks: Vec<BookLevel>,
+    bids: Vec<BookLevel>,
+    #[serde(deserialize_with = "from_str")]
+    ts: i64,
+    // 推送的深度带有序号，REST 快照没有
+    #[serde(default)]
+    prev_seq_id: Option<i64>,
+    #[serde(default)]
+    seq_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TradeResponse {
+    inst_id: String,
+    #[serde(deserialize_with = "from_str")]
+    trade_id: u64,
+    px: Decimal,
+    sz: Decimal,
+    // 主动方（taker）的方向
+    side: String,
+    #[serde(deserialize_with = "from_str")]
+    ts: i64,
+}
+
+impl TradeResponse {
+    fn is_buyer_maker(&self) -> bool {
+        self.side == "sell"
+    }
+
+    fn into_trade(self) -> Result<ExchangeTrade, ExchangeError> {
+        Ok(ExchangeTrade {
+            symbol: normalize_symbol(&self.inst_id),
+            timestamp: timestamp_millis(self.ts)?,
+            is_buyer_maker: self.is_buyer_maker(),
+            price: self.px,
+            quantity: self.sz,
+        })
+    }
+
+    fn into_tick(self) -> Result<TickData, ExchangeError> {
+        let is_buyer_maker = self.is_buyer_maker();
+        Ok(TickData {
+            exchange: EXCHANGE_NAME.to_string(),
+            timestamp: timestamp_millis(self.ts)?,
+            symbol: normalize_symbol(&self.inst_id),
+            price: to_f64(self.px)?,
+            volume: to_f64(self.sz)?,
+            side: if is_buyer_maker { "SELL" } else { "BUY" }.to_string(),
+            trade_id: self.trade_id.to_string(),
+            is_maker: is_buyer_maker,
+        })
+    }
+}
+
+// [开盘时间, 开, 高, 低, 收, 成交量(基础币), 成交额, 成交额(计价币), 是否收盘]
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct CandleResponse(
+    #[serde(deserialize_with = "from_str")] i64,
+    #[serde(deserialize_with = "from_str")] f64,
+    #[serde(deserialize_with = "from_str")] f64,
+    #[serde(deserialize_with = "from_str")] f64,
+    #[serde(deserialize_with = "from_str")] f64,
+    #[serde(deserialize_with = "from_str")] f64,
+    IgnoredAny,
+    IgnoredAny,
+    String,
+);
+
+impl CandleResponse {
+    fn is_closed(&self) -> bool {
+        self.8 == "1"
+    }
+
+    fn into_market_data(self, symbol: &str) -> Result<MarketDataPoint, ExchangeError> {
+        let CandleResponse(open_time, open, high, low, close, volume, ..) = self;
+        Ok(MarketDataPoint {
+            timestamp: timestamp_millis(open_time)?,
+            symbol: symbol.to_string(),
+            price: close,
+            volume,
+            high,
+            low,
+            open,
+            close,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InstrumentResponse {
+    base_ccy: String,
+    quote_ccy: String,
+    inst_id: String,
+    state: String,
+    tick_sz: Decimal,
+    lot_sz: Decimal,
+    min_sz: Decimal,
+    // 部分交易对为空字符串
+    #[serde(default)]
+    max_lmt_sz: String,
+}
+
+impl InstrumentResponse {
+    fn into_symbol_info(self) -> SymbolInfo {
+        // 统一为与 Binance 相同的状态写法
+        let status = match self.state.as_str() {
+            "live" => "TRADING".to_string(),
+            "suspend" => "BREAK".to_string(),
+            "preopen" => "PRE_TRADING".to_string(),
+            state => state.to_ascii_uppercase(),
+        };
+        SymbolInfo {
+            symbol: normalize_symbol(&self.inst_id),
+            base_asset: self.base_ccy,
+            quote_asset: self.quote_ccy,
+            status,
+            tick_size: self.tick_sz.normalize(),
+            min_price: Decimal::ZERO,
+            max_price: Decimal::ZERO,
+            step_size: self.lot_sz.normalize(),
+            min_qty: self.min_sz.normalize(),
+            max_qty: self.max_lmt_sz.parse::<Decimal>().map(|d| d.normalize()).unwrap_or_default(),
+            // 现货市价买单的 maxMktSz 以计价货币计，无法按数量统一检查，市价单只检查步长和下限
+            market_step_size: self.lot_sz.normalize(),
+            market_min_qty: self.min_sz.normalize(),
+            market_max_qty: Decimal::ZERO,
+            min_notional: Decimal::ZERO,
+        }
+    }
+}
+
+// 推送消息：{"arg":{"channel","instId"},"action":"update","data":[...]}
+#[derive(Debug, Deserialize)]
+struct PushMessage<T> {
+    data: Vec<T>,
+}
+
+// OKX 行情流：连接后通过 op subscribe/unsubscribe 增减订阅，并定期发送文本 ping
+struct OkxStream {
+    url: String,
+    business: bool,
+}
+
+impl OkxStream {
+    fn args(&self, topics: &[Topic]) -> Result<Vec<Value>, ExchangeError> {
+        topics
+            .iter()
+            .map(|topic| {
+                let channel = match &topic.channel {
+                    Channel::Ticker => "tickers".to_string(),
+                    Channel::Trades => "trades".to_string(),
+                    Channel::Depth => "books".to_string(),
+                    Channel::Kline(interval) => format!("candle{}", okx_bar(interval)?),
+                };
+                if channel.starts_with("candle") != self.business {
+                    return Err(ExchangeError::ApiError(format!(
+                        "OKX serves {} on a separate connection from other channels",
+                        channel
+                    )));
+                }
+                Ok(serde_json::json!({"channel": channel, "instId": to_inst_id(&topic.symbol)?}))
+            })
+            .collect()
+    }
+}
+
+impl StreamProtocol for OkxStream {
+    fn connect_request(&mut self, topics: &[Topic]) -> Result<(String, Option<String>), ExchangeError> {
+        let initial = if topics.is_empty() {
+            None
+        } else {
+            Some(self.subscription_message(topics, true)?)
+        };
+        Ok((self.url.clone(), initial))
+    }
+
+    fn subscription_message(&mut self, topics: &[Topic], subscribe: bool) -> Result<String, ExchangeError> {
+        Ok(serde_json::json!({
+            "op": if subscribe { "subscribe" } else { "unsubscribe" },
+            "args": self.args(topics)?,
+        })
+        .to_string())
+    }
+
+    fn parse(&self, text: &str) -> Result<Vec<MarketEvent>, ExchangeError> {
+        if text == "pong" {
+            return Ok(Vec::new());
+        }
+        let Ok(message) = serde_json::from_str::<Value>(text) else {
+            warn!("Invalid OKX stream message: {}", text);
+            return Ok(Vec::new());
+        };
+        // 订阅确认和错误通过 event 字段返回
+        match message.get("event").and_then(Value::as_str) {
+            Some("error") => {
+                let code = message["code"].as_str().unwrap_or_default();
+                let msg = message["msg"].as_str().unwrap_or_default();
+                return Err(decode_error(code, msg));
+            }
+            Some(_) => return Ok(Vec::new()),
+            None => {}
+        }
+
+        let channel = message["arg"]["channel"].as_str().unwrap_or_default();
+        let events = match channel {
+            "trades" => OkxSpot::parse_trade_message(text)
+                .map(|ticks| ticks.into_iter().map(MarketEvent::Trade).collect()),
+            "tickers" => OkxSpot::parse_ticker_message(text)
+                .map(|points| points.into_iter().map(MarketEvent::Ticker).collect()),
+            "books" => OkxSpot::parse_depth_message(text),
+            channel if channel.starts_with("candle") => OkxSpot::parse_candle_message(text)
+                .map(|klines| klines.into_iter().map(MarketEvent::Kline).collect()),
+            _ => Ok(Vec::new()),
+        };
+        Ok(events.unwrap_or_else(|e| {
+            warn!("Invalid OKX {} message: {}", channel, e);
+            Vec::new()
+        }))
+    }
+
+    fn heartbeat(&self) -> Option<(Duration, String)> {
+        Some((PING_INTERVAL, "ping".to_string()))
+    }
+}
+
+#[derive(Clone)]
+pub struct OkxSpot {
+    client: Client,
+    endpoints: ExchangeEndpoints,
+    rate_limiter: Arc<RateLimiter>,
+}
+
+impl Default for OkxSpot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OkxSpot {
+    pub fn new() -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            client,
+            endpoints: Self::default_endpoints(),
+            rate_limiter: Arc::new(RateLimiter::new(REQUESTS_PER_WINDOW, RATE_LIMIT_WINDOW)),
+        }
+    }
+
+    pub fn default_endpoints() -> ExchangeEndpoints {
+        ExchangeEndpoints {
+            rest_url: Url::parse("https://www.okx.com").expect("preset URL is valid"),
+            ws_url: Url::parse("wss://ws.okx.com:8443/ws/v5/public").expect("preset URL is valid"),
+        }
+    }
+
+    pub fn with_endpoints(mut self, endpoints: ExchangeEndpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    async fn make_request<T: DeserializeOwned>(&self, endpoint: &str, params: Vec<(&str, String)>)
+        -> Result<T, ExchangeError> {
+        let mut url = self.endpoints.rest_url.join(endpoint)
+            .map_err(|e| ExchangeError::NetworkError(e.to_string()))?;
+        if !params.is_empty() {
+            let mut query = url.query_pairs_mut();
+            for (key, value) in &params {
+                query.append_pair(key, value);
+            }
+        }
+
+        let mut attempt = 0;
+        loop {
+            self.rate_limiter.acquire(1).await;
+            let response = self.client
+                .get(url.clone())
+                .send()
+                .await
+                .map_err(|e| ExchangeError::NetworkError(e.to_string()))?;
+
+            let status = response.status();
+            if status == StatusCode::TOO_MANY_REQUESTS {
+                self.rate_limiter.back_off(Duration::from_secs(1 << attempt));
+                if attempt >= MAX_RATE_LIMIT_RETRIES {
+                    return Err(ExchangeError::RateLimitExceeded);
+                }
+                attempt += 1;
+                warn!("{} returned {}, retry {}/{}", endpoint, status, attempt, MAX_RATE_LIMIT_RETRIES);
+                continue;
+            }
+
+            let body = response
+                .text()
+                .await
+                .map_err(|e| ExchangeError::NetworkError(e.to_string()))?;
+            return parse_response(status, &body);
+        }
+    }
+
+    pub(crate) fn parse_trade_message(data: &str) -> Result<Vec<TickData>, ExchangeError> {
+        let message: PushMessage<TradeResponse> = serde_json::from_str(data)
+            .map_err(|e| ExchangeError::ApiError(e.to_string()))?;
+        message.data.into_iter().map(TradeResponse::into_tick).collect()
+    }
+
+    pub(crate) fn parse_ticker_message(data: &str) -> Result<Vec<MarketDataPoint>, ExchangeError> {
+        let message: PushMessage<TickerResponse> = serde_json::from_str(data)
+            .map_err(|e| ExchangeError::ApiError(e.to_string()))?;
+        message.data.into_iter().map(TickerResponse::into_market_data).collect()
+    }
+
+    // books 频道订阅后先推送完整快照（prevSeqId 为 -1），之后的增量满足 prevSeqId 等于上一条的 seqId
+    pub(crate) fn parse_depth_message(data: &str) -> Result<Vec<MarketEvent>, ExchangeError> {
+        let message: Value = serde_json::from_str(data)
+            .map_err(|e| ExchangeError::ApiError(e.to_string()))?;
+        let inst_id = message["arg"]["instId"]
+            .as_str()
+            .ok_or_else(|| ExchangeError::ApiError("Missing instId".to_string()))?;
+        let books: PushMessage<BookResponse> = serde_json::from_value(message.clone())
+            .map_err(|e| ExchangeError::ApiError(e.to_string()))?;
+
+        books.data
+            .into_iter()
+            .map(|book| {
+                let seq_id = book.seq_id.unwrap_or_default().max(0) as u64;
+                let prev_seq_id = book.prev_seq_id.unwrap_or_default().max(-1);
+                if prev_seq_id < 0 {
+                    return Ok(MarketEvent::DepthSnapshot(OrderBook {
+                        symbol: normalize_symbol(inst_id),
+                        timestamp: timestamp_millis(book.ts)?,
+                        last_update_id: Some(seq_id),
+                        bids: book_levels(book.bids),
+                        asks: book_levels(book.asks),
+                    }));
+                }
+                Ok(MarketEvent::Depth(DepthUpdate {
+                    symbol: normalize_symbol(inst_id),
+                    event_time: timestamp_millis(book.ts)?,
+                    first_update_id: (prev_seq_id + 1) as u64,
+                    final_update_id: seq_id,
+                    bids: book_levels(book.bids),
+                    asks: book_levels(book.asks),
+                }))
+            })
+            .collect()
+    }
+
+    pub(crate) fn parse_candle_message(data: &str) -> Result<Vec<KlineUpdate>, ExchangeError> {
+        let message: Value = serde_json::from_str(data)
+            .map_err(|e| ExchangeError::ApiError(e.to_string()))?;
+        let (Some(channel), Some(inst_id)) = (message["arg"]["channel"].as_str(), message["arg"]["instId"].as_str())
+        else {
+            return Err(ExchangeError::ApiError("Missing channel or instId".to_string()));
+        };
+        let interval = interval_from_bar(channel.trim_start_matches("candle"));
+        let symbol = normalize_symbol(inst_id);
+        let candles: PushMessage<CandleResponse> = serde_json::from_value(message)
+            .map_err(|e| ExchangeError::ApiError(e.to_string()))?;
+
+        candles.data
+            .into_iter()
+            .map(|candle| {
+                let is_closed = candle.is_closed();
+                Ok(KlineUpdate {
+                    symbol: symbol.clone(),
+                    interval: interval.clone(),
+                    candle: candle.into_market_data(&symbol)?,
+                    is_closed,
+                })
+            })
+            .collect()
+    }
+
+    async fn fetch_candles(
+        &self,
+        inst_id: &str,
+        bar: &str,
+        before: Option<i64>,
+        after: Option<i64>,
+        limit: u32,
+    ) -> Result<Vec<CandleResponse>, ExchangeError> {
+        let mut params = vec![
+            ("instId", inst_id.to_string()),
+            ("bar", bar.to_string()),
+            ("limit", limit.to_string()),
+        ];
+        if let Some(before) = before {
+            params.push(("before", before.to_string()));
+        }
+        if let Some(after) = after {
+            params.push(("after", after.to_string()));
+        }
+        let mut candles: Vec<CandleResponse> =
+            self.make_request("/api/v5/market/history-candles", params).await?;
+        // 返回结果从新到旧
+        candles.reverse();
+        Ok(candles)
+    }
+}
+
+#[async_trait::async_trait]
+impl Exchange for OkxSpot {
+    fn name(&self) -> &'static str {
+        EXCHANGE_NAME
+    }
+
+    async fn get_ticker(&self, symbol: &str) -> Result<Ticker, ExchangeError> {
+        let params = vec![("instId", to_inst_id(symbol)?)];
+        let data: Vec<TickerResponse> = self.make_request("/api/v5/market/ticker", params).await?;
+        data.into_iter()
+            .next()
+            .ok_or_else(|| ExchangeError::InvalidSymbol(symbol.to_string()))?
+            .into_ticker()
+    }
+
+    async fn get_orderbook(&self, symbol: &str, limit: u32) -> Result<OrderBook, ExchangeError> {
+        let params = vec![
+            ("instId", to_inst_id(symbol)?),
+            ("sz", limit.min(400).to_string()),
+        ];
+        let data: Vec<BookResponse> = self.make_request("/api/v5/market/books", params).await?;
+        let book = data
+            .into_iter()
+            .next()
+            .ok_or_else(|| ExchangeError::InvalidSymbol(symbol.to_string()))?;
+
+        // REST 快照没有序号，本地订单簿改由 books 频道推送的快照初始化
+        Ok(OrderBook {
+            symbol: normalize_symbol(symbol),
+            timestamp: timestamp_millis(book.ts)?,
+            last_update_id: None,
+            bids: book_levels(book.bids),
+            asks: book_levels(book.asks),
+        })
+    }
+
+    async fn get_recent_trades(&self, symbol: &str, limit: u32) -> Result<Vec<ExchangeTrade>, ExchangeError> {
+        let params = vec![
+            ("instId", to_inst_id(symbol)?),
+            ("limit", limit.min(500).to_string()),
+        ];
+        let data: Vec<TradeResponse> = self.make_request("/api/v5/market/trades", params).await?;
+        // 与 Binance 一致按时间从旧到新返回
+        data.into_iter()
+            .rev()
+            .map(TradeResponse::into_trade)
+            .collect()
+    }
+
+    async fn get_historical_trades(
+        &self,
+        symbol: &str,
+        from_id: u64,
+        limit: u32,
+    ) -> Result<Vec<TickData>, ExchangeError> {
+        // OKX 只能从最新的成交往旧翻页：用 after 逐页向前直到覆盖 from_id，再按 ID 升序返回。
+        // 返回从 from_id 到最新的全部成交，数量可能超过 limit，limit 只限制每页大小
+        let inst_id = to_inst_id(symbol)?;
+        let page_size = limit.clamp(1, MAX_PAGE_SIZE);
+        let mut trades: Vec<TradeResponse> = Vec::new();
+        let mut after: Option<u64> = None;
+        for _ in 0..MAX_HISTORY_TRADE_PAGES {
+            let mut params = vec![
+                ("instId", inst_id.clone()),
+                ("type", "1".to_string()),
+                ("limit", page_size.to_string()),
+            ];
+            if let Some(after) = after {
+                params.push(("after", after.to_string()));
+            }
+            let page: Vec<TradeResponse> = self.make_request("/api/v5/market/history-trades", params).await?;
+            // 每页从新到旧
+            let Some(oldest) = page.iter().map(|trade| trade.trade_id).min() else {
+                after = None;
+                break;
+            };
+            let full = page.len() >= page_size as usize;
+            trades.extend(page.into_iter().filter(|trade| trade.trade_id >= from_id));
+            if oldest <= from_id || !full {
+                after = None;
+                break;
+            }
+            after = Some(oldest);
+        }
+        if let Some(oldest) = after {
+            warn!(
+                "OKX history for {} stopped after {} pages, trades {} to {} are missing",
+                symbol, MAX_HISTORY_TRADE_PAGES, from_id, oldest - 1
+            );
+        }
+
+        trades.sort_by_key(|trade| trade.trade_id);
+        trades.dedup_by_key(|trade| trade.trade_id);
+        trades.into_iter().map(TradeResponse::into_tick).collect()
+    }
+
+    async fn get_symbols(&self) -> Result<Vec<SymbolInfo>, ExchangeError> {
+        let params = vec![("instType", "SPOT".to_string())];
+        let data: Vec<InstrumentResponse> = self.make_request("/api/v5/public/instruments", params).await?;
+        Ok(data.into_iter().map(InstrumentResponse::into_symbol_info).collect())
+    }
+
+    async fn get_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        limit: Option<u32>,
+    ) -> Result<Vec<MarketDataPoint>, ExchangeError> {
+        let inst_id = to_inst_id(symbol)?;
+        let bar = okx_bar(interval)?;
+        let symbol = normalize_symbol(symbol);
+        let limit = limit.unwrap_or(MAX_PAGE_SIZE);
+
+        let Some(start) = start_time else {
+            // 没有起点时返回截至 end_time 的最新数据
+            let after = end_time.map(|end| end.timestamp_millis() + 1);
+            return self
+                .fetch_candles(&inst_id, &bar, None, after, limit.min(MAX_PAGE_SIZE))
+                .await?
+                .into_iter()
+                .map(|candle| candle.into_market_data(&symbol))
+                .collect();
+        };
+
+        // OKX 只能从新往旧翻页；为了从 start_time 开始向后返回，按周期计算每页的右边界，
+        // 多次请求直到凑满 limit 或到达 end_time
+        let step = interval_duration(interval)
+            .ok_or_else(|| ExchangeError::ApiError(format!("Unsupported interval: {}", interval)))?;
+        let end = end_time.unwrap_or_else(Utc::now);
+        let mut cursor = start;
+        let mut klines = Vec::new();
+        while klines.len() < limit as usize && cursor <= end {
+            let page = (limit as usize - klines.len()).min(MAX_PAGE_SIZE as usize) as u32;
+            let page_end = (cursor + step * page as i32 - ChronoDuration::milliseconds(1)).min(end);
+            let candles = self
+                .fetch_candles(
+                    &inst_id,
+                    &bar,
+                    Some(cursor.timestamp_millis() - 1),
+                    Some(page_end.timestamp_millis() + 1),
+                    page,
+                )
+                .await?;
+            for candle in candles {
+                klines.push(candle.into_market_data(&symbol)?);
+            }
+            cursor = page_end + ChronoDuration::milliseconds(1);
+        }
+        Ok(klines)
+    }
+
+    fn streams_depth_snapshots(&self) -> bool {
+        true
+    }
+
+    fn stream(&self, topics: &[Topic]) -> Result<MarketStream, ExchangeError> {
+        // K 线频道只在 business 地址上推送，其他公共频道在 public 地址上
+        let business = topics.first().is_some_and(|topic| matches!(topic.channel, Channel::Kline(_)));
+        let url = if business {
+            let public = self.endpoints.ws_url.as_str();
+            match public.strip_suffix("/public") {
+                Some(prefix) => format!("{}/business", prefix),
+                None => public.to_string(),
+            }
+        } else {
+            self.endpoints.ws_url.to_string()
+        };
+        MarketStream::spawn(OkxStream { url, business }, topics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::binance::BinanceSpot;
+    use crate::tests::mock_exchange::{MockExchange, MockResponse, StreamEvent};
+    use tokio::sync::mpsc;
+
+    const TICKER: &str = include_str!("../../tests/fixtures/okx/ticker.json");
+    const BOOKS: &str = include_str!("../../tests/fixtures/okx/books.json");
+    const TRADES: &str = include_str!("../../tests/fixtures/okx/trades.json");
+    const CANDLES: &str = include_str!("../../tests/fixtures/okx/candles.json");
+    const INSTRUMENTS: &str = include_str!("../../tests/fixtures/okx/instruments.json");
+    const ERROR_INSTRUMENT: &str = include_str!("../../tests/fixtures/okx/error_instrument.json");
+    const WS_TRADES: &str = include_str!("../../tests/fixtures/okx/ws_trades.json");
+    const WS_TICKERS: &str = include_str!("../../tests/fixtures/okx/ws_tickers.json");
+    const WS_BOOKS: &str = include_str!("../../tests/fixtures/okx/ws_books.json");
+    const WS_BOOKS_SNAPSHOT: &str = include_str!("../../tests/fixtures/okx/ws_books_snapshot.json");
+    const WS_CANDLES: &str = include_str!("../../tests/fixtures/okx/ws_candles.json");
+
+    const BINANCE_TICKER: &str = include_str!("../../tests/fixtures/binance/ticker_24hr.json");
+    const BINANCE_DEPTH: &str = include_str!("../../tests/fixtures/binance/depth_snapshot.json");
+    const BINANCE_TRADES: &str = include_str!("../../tests/fixtures/binance/historical_trades.json");
+    const BINANCE_KLINES: &str = include_str!("../../tests/fixtures/binance/klines.json");
+    const BINANCE_TRADE: &str = include_str!("../../tests/fixtures/binance/trade.json");
+
+    #[test]
+    fn test_symbol_conversion() {
+        assert_eq!(to_inst_id("BTCUSDT").unwrap(), "BTC-USDT");
+        assert_eq!(to_inst_id("btc/usdt").unwrap(), "BTC-USDT");
+        assert_eq!(to_inst_id("ETH-BTC").unwrap(), "ETH-BTC");
+        assert_eq!(to_inst_id("BTCUSD").unwrap(), "BTC-USD");
+        assert_eq!(to_inst_id("USDCUSDT").unwrap(), "USDC-USDT");
+        assert!(matches!(to_inst_id("BTCXYZ"), Err(ExchangeError::InvalidSymbol(_))));
+
+        assert_eq!(okx_bar("1m").unwrap(), "1m");
+        assert_eq!(okx_bar("4h").unwrap(), "4H");
+        assert_eq!(okx_bar("1d").unwrap(), "1Dutc");
+        for interval in ["", "m", "1y"] {
+            assert!(matches!(okx_bar(interval), Err(ExchangeError::ApiError(_))));
+        }
+    }
+
+    #[test]
+    fn test_parse_rest_responses() {
+        let ticker = parse_response::<Vec<TickerResponse>>(StatusCode::OK, TICKER).unwrap()
+            .remove(0)
+            .into_ticker()
+            .unwrap();
+        assert_eq!(ticker.symbol, "BTCUSDT");
+        assert_eq!(ticker.bid_price.to_string(), "37250.49");
+
+        let book = parse_response::<Vec<BookResponse>>(StatusCode::OK, BOOKS).unwrap().remove(0);
+        assert_eq!(book_levels(book.asks)[0].quantity.to_string(), "1.5");
+
+        let symbols: Vec<SymbolInfo> = parse_response::<Vec<InstrumentResponse>>(StatusCode::OK, INSTRUMENTS)
+            .unwrap()
+            .into_iter()
+            .map(InstrumentResponse::into_symbol_info)
+            .collect();
+        assert_eq!(symbols[0].symbol, "BTCUSDT");
+        assert!(symbols[0].is_trading());
+        assert_eq!(symbols[0].tick_size.to_string(), "0.1");
+        assert_eq!(symbols[0].step_size.to_string(), "0.00000001");
+        assert_eq!(symbols[1].display_name(), "ETH/BTC");
+        assert!(!symbols[1].is_trading());
+        assert_eq!(symbols[1].max_qty, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        // OKX 的业务错误通常以 HTTP 200 返回
+        assert!(matches!(
+            parse_response::<Vec<TickerResponse>>(StatusCode::OK, ERROR_INSTRUMENT),
+            Err(ExchangeError::InvalidSymbol(msg)) if msg == "Instrument ID does not exist"
+        ));
+        assert!(matches!(
+            parse_response::<Vec<TickerResponse>>(
+                StatusCode::UNAUTHORIZED,
+                r#"{"code":"50113","msg":"Invalid Sign","data":[]}"#
+            ),
+            Err(ExchangeError::AuthError(_))
+        ));
+        assert!(matches!(
+            parse_response::<Vec<TickerResponse>>(StatusCode::BAD_GATEWAY, "<html>Bad Gateway</html>"),
+            Err(ExchangeError::ApiError(msg)) if msg.contains("502")
+        ));
+        assert!(matches!(
+            parse_response::<Vec<TickerResponse>>(StatusCode::OK, r#"{"code":"0","msg":"","data":[{"instId":1}]}"#),
+            Err(ExchangeError::ApiError(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_push_messages() {
+        let ticks = OkxSpot::parse_trade_message(WS_TRADES).unwrap();
+        assert_eq!(ticks[0].exchange, "okx");
+        assert_eq!(ticks[0].symbol, "BTCUSDT");
+        assert_eq!(ticks[0].trade_id, "3412093912");
+        assert_eq!(ticks[0].side, "SELL");
+        assert!(ticks[0].is_maker);
+
+        let points = OkxSpot::parse_ticker_message(WS_TICKERS).unwrap();
+        assert_eq!((points[0].price, points[0].high), (37250.5, 37700.0));
+
+        let MarketEvent::Depth(update) = OkxSpot::parse_depth_message(WS_BOOKS).unwrap().remove(0) else {
+            panic!("expected a depth update");
+        };
+        assert_eq!((update.first_update_id, update.final_update_id), (123457, 123460));
+        assert_eq!(update.asks[0].quantity, Decimal::ZERO);
+        let MarketEvent::DepthSnapshot(snapshot) = OkxSpot::parse_depth_message(WS_BOOKS_SNAPSHOT).unwrap().remove(0)
+        else {
+            panic!("expected a depth snapshot");
+        };
+        assert_eq!(snapshot.last_update_id, Some(123456));
+        assert_eq!(snapshot.bids[0].price.to_string(), "50000.1");
+
+        let klines = OkxSpot::parse_candle_message(WS_CANDLES).unwrap();
+        assert_eq!((klines[0].symbol.as_str(), klines[0].interval.as_str()), ("BTCUSDT", "1h"));
+        assert_eq!((klines[0].candle.open, klines[0].candle.close), (37240.0, 37250.5));
+        assert!(klines[0].is_closed);
+        assert_eq!(interval_from_bar(&okx_bar("1d").unwrap()), "1d");
+    }
+
+    // 同一份行情分别以 Binance 和 OKX 的格式提供，通过 Exchange trait 得到的结果应一致
+    #[tokio::test]
+    async fn test_adapters_agree_through_exchange_trait() {
+        let binance_mock = MockExchange::start().await.unwrap();
+        binance_mock.route("/api/v3/ticker/24hr", MockResponse::json(BINANCE_TICKER));
+        binance_mock.route("/api/v3/depth", MockResponse::json(BINANCE_DEPTH));
+        binance_mock.route("/api/v3/trades", MockResponse::json(BINANCE_TRADES));
+        binance_mock.route("/api/v3/klines", MockResponse::json(BINANCE_KLINES));
+        binance_mock.push_stream(StreamEvent::from_jsonl(BINANCE_TRADE));
+
+        let okx_mock = MockExchange::start().await.unwrap();
+        okx_mock.route("/api/v5/market/ticker", MockResponse::json(TICKER));
+        okx_mock.route("/api/v5/market/books", MockResponse::json(BOOKS));
+        okx_mock.route("/api/v5/market/trades", MockResponse::json(TRADES));
+        okx_mock.route("/api/v5/market/history-candles", MockResponse::json(CANDLES));
+        okx_mock.push_stream(StreamEvent::from_jsonl(WS_TRADES));
+
+        let binance = BinanceSpot::new(None).with_endpoints(binance_mock.endpoints());
+        let okx = OkxSpot::new().with_endpoints(ExchangeEndpoints {
+            ws_url: okx_mock.endpoints().ws_url.join("/ws/v5/public").unwrap(),
+            ..okx_mock.endpoints()
+        });
+        let exchanges: [&dyn Exchange; 2] = [&binance, &okx];
+
+        let mut results = Vec::new();
+        for exchange in exchanges {
+            let ticker = exchange.get_ticker("BTCUSDT").await.unwrap();
+            let book = exchange.get_orderbook("BTCUSDT", 100).await.unwrap();
+            let trades = exchange.get_recent_trades("BTCUSDT", 2).await.unwrap();
+            let klines = exchange.get_klines("BTCUSDT", "1m", None, None, Some(2)).await.unwrap();
+
+            let (tx, mut rx) = mpsc::channel(1);
+            let symbols = vec!["BTCUSDT".to_string()];
+            let tick = tokio::select! {
+                _ = exchange.subscribe_trades(&symbols, tx) => panic!("stream ended early"),
+                tick = rx.recv() => tick.unwrap(),
+            };
+
+            results.push((
+                (ticker.symbol, ticker.last_price.normalize(), ticker.bid_price.normalize(), ticker.volume_24h.normalize()),
+                book.bids.iter().map(|l| (l.price.normalize(), l.quantity.normalize())).collect::<Vec<_>>(),
+                trades.iter().map(|t| (t.timestamp, t.price.normalize(), t.quantity.normalize(), t.is_buyer_maker)).collect::<Vec<_>>(),
+                klines.iter().map(|k| (k.timestamp, k.open, k.high, k.low, k.close, k.volume)).collect::<Vec<_>>(),
+                (tick.symbol, tick.trade_id, tick.timestamp, tick.price, tick.volume, tick.side, tick.is_maker),
+            ));
+        }
+        assert_eq!(results[0], results[1]);
+        assert_eq!(okx_mock.stream_connections(), vec!["/ws/v5/public".to_string()]);
+    }
+
+    // 构造一页 history-trades 响应，成交 ID 从 newest 递减
+    fn history_trades_page(newest: u64, count: u64) -> MockResponse {
+        let data: Vec<Value> = (0..count)
+            .map(|i| {
+                let trade_id = newest - i;
+                serde_json::json!({
+                    "instId": "BTC-USDT", "tradeId": trade_id.to_string(), "px": "37250.5", "sz": "0.01",
+                    "side": "buy", "ts": (1700000000000u64 + trade_id).to_string()
+                })
+            })
+            .collect();
+        MockResponse::json(serde_json::json!({"code": "0", "msg": "", "data": data}).to_string())
+    }
+
+    // 缺口超过一页时要从最新的成交一直翻到 from_id
+    #[tokio::test]
+    async fn test_historical_trades_page_back_to_from_id() {
+        let mock = MockExchange::start().await.unwrap();
+        let path = "/api/v5/market/history-trades";
+        mock.respond_once(path, history_trades_page(1250, 100));
+        mock.respond_once(path, history_trades_page(1150, 100));
+        mock.respond_once(path, history_trades_page(1050, 100));
+        let okx = OkxSpot::new().with_endpoints(mock.endpoints());
+
+        let trades = okx.get_historical_trades("BTCUSDT", 1000, 100).await.unwrap();
+        let ids: Vec<u64> = trades.iter().map(|t| t.trade_id.parse().unwrap()).collect();
+        assert_eq!(ids, (1000..=1250).collect::<Vec<_>>());
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(!requests[0].contains("after="));
+        assert!(requests[1].contains("after=1151"));
+        assert!(requests[2].contains("after=1051"));
+    }
+}
@@ -0,0 +1,612 @@
+// trading-core/src/exchange/orderbook.rs
+// 本地 L2 订单簿：REST 快照（或行情流推送的快照）+ 增量深度流，按 U/u 序号校验并在断档时重新同步
+
+use super::stream::{ConnectionState, MarketEvent, Topic};
+use super::types::{DepthUpdate, Exchange, ExchangeError, OrderBook, OrderBookLevel};
+use crate::data::cache::MarketDataCache;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use thiserror::Error;
+use futures_util::StreamExt;
+use tokio::sync::broadcast;
+use tokio::time::{sleep, Duration};
+use tracing::{error, info, warn};
+
+const SNAPSHOT_LIMIT: u32 = 1000;
+const RESYNC_DELAY: Duration = Duration::from_millis(500);
+const CHANNEL_BUFFER_SIZE: usize = 1000;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum OrderBookError {
+    #[error("Sequence gap for {symbol}: expected update {expected}, got {first}..{last}")]
+    SequenceGap {
+        symbol: String,
+        expected: u64,
+        first: u64,
+        last: u64,
+    },
+    #[error("Snapshot for {0} has no update id")]
+    MissingUpdateId(String),
+    #[error("Snapshot for {symbol} ({snapshot_id}) is older than buffered update {first_buffered}")]
+    SnapshotTooOld {
+        symbol: String,
+        snapshot_id: u64,
+        first_buffered: u64,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct LocalOrderBook {
+    symbol: String,
+    bids: BTreeMap<Reverse<Decimal>, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+    last_update_id: u64,
+    timestamp: DateTime<Utc>,
+    // 快照之后的第一条增量需满足 U <= lastUpdateId + 1 <= u，之后要求 U == 上一条 u + 1
+    awaiting_first_update: bool,
+}
+
+impl LocalOrderBook {
+    pub fn from_snapshot(snapshot: &OrderBook) -> Result<Self, OrderBookError> {
+        let last_update_id = snapshot.last_update_id
+            .ok_or_else(|| OrderBookError::MissingUpdateId(snapshot.symbol.clone()))?;
+
+        let mut book = Self {
+            symbol: snapshot.symbol.clone(),
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            last_update_id,
+            timestamp: snapshot.timestamp,
+            awaiting_first_update: true,
+        };
+        book.apply_levels(&snapshot.bids, &snapshot.asks);
+        Ok(book)
+    }
+
+    /// 应用一条增量更新。返回 `Ok(false)` 表示更新已包含在快照中被丢弃：
+    /// 行情流按序号顺序到达，只有拉取快照期间排队的增量会早于快照。
+    pub fn apply(&mut self, update: &DepthUpdate) -> Result<bool, OrderBookError> {
+        if update.final_update_id <= self.last_update_id {
+            return Ok(false);
+        }
+
+        let expected = self.last_update_id + 1;
+        let in_sequence = if self.awaiting_first_update {
+            update.first_update_id <= expected
+        } else {
+            update.first_update_id == expected
+        };
+
+        if !in_sequence {
+            return Err(OrderBookError::SequenceGap {
+                symbol: self.symbol.clone(),
+                expected,
+                first: update.first_update_id,
+                last: update.final_update_id,
+            });
+        }
+
+        self.apply_levels(&update.bids, &update.asks);
+        self.last_update_id = update.final_update_id;
+        self.timestamp = update.event_time;
+        self.awaiting_first_update = false;
+        Ok(true)
+    }
+
+    fn apply_levels(&mut self, bids: &[OrderBookLevel], asks: &[OrderBookLevel]) {
+        // 数量为 0 表示删除该价位
+        for level in bids {
+            if level.quantity.is_zero() {
+                self.bids.remove(&Reverse(level.price));
+            } else {
+                self.bids.insert(Reverse(level.price), level.quantity);
+            }
+        }
+        for level in asks {
+            if level.quantity.is_zero() {
+                self.asks.remove(&level.price);
+            } else {
+                self.asks.insert(level.price, level.quantity);
+            }
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn last_update_id(&self) -> u64 {
+        self.last_update_id
+    }
+
+    pub fn top_bids(&self, n: usize) -> Vec<OrderBookLevel> {
+        self.bids
+            .iter()
+            .take(n)
+            .map(|(Reverse(price), quantity)| OrderBookLevel { price: *price, quantity: *quantity })
+            .collect()
+    }
+
+    pub fn top_asks(&self, n: usize) -> Vec<OrderBookLevel> {
+        self.asks
+            .iter()
+            .take(n)
+            .map(|(price, quantity)| OrderBookLevel { price: *price, quantity: *quantity })
+            .collect()
+    }
+
+    pub fn best_bid(&self) -> Option<OrderBookLevel> {
+        self.top_bids(1).pop()
+    }
+
+    pub fn best_ask(&self) -> Option<OrderBookLevel> {
+        self.top_asks(1).pop()
+    }
+
+    pub fn mid_price(&self) -> Option<Decimal> {
+        let bid = self.best_bid()?;
+        let ask = self.best_ask()?;
+        Some((bid.price + ask.price) / Decimal::TWO)
+    }
+
+    // 按对手方挂单量加权的中间价
+    pub fn microprice(&self) -> Option<Decimal> {
+        let bid = self.best_bid()?;
+        let ask = self.best_ask()?;
+        let total = bid.quantity + ask.quantity;
+        if total.is_zero() {
+            return None;
+        }
+        Some((bid.price * ask.quantity + ask.price * bid.quantity) / total)
+    }
+
+    // 前 n 档买卖量失衡，范围 [-1, 1]，正数表示买盘更厚
+    pub fn imbalance(&self, n: usize) -> Option<Decimal> {
+        let bid_volume: Decimal = self.bids.values().take(n).sum();
+        let ask_volume: Decimal = self.asks.values().take(n).sum();
+        let total = bid_volume + ask_volume;
+        if total.is_zero() {
+            return None;
+        }
+        Some((bid_volume - ask_volume) / total)
+    }
+
+    pub fn to_order_book(&self, depth: usize) -> OrderBook {
+        OrderBook {
+            symbol: self.symbol.clone(),
+            timestamp: self.timestamp,
+            last_update_id: Some(self.last_update_id),
+            bids: self.top_bids(depth),
+            asks: self.top_asks(depth),
+        }
+    }
+
+    pub fn summary(&self, depth: usize) -> OrderBookUpdate {
+        OrderBookUpdate {
+            symbol: self.symbol.clone(),
+            timestamp: self.timestamp,
+            last_update_id: self.last_update_id,
+            mid_price: self.mid_price(),
+            microprice: self.microprice(),
+            imbalance: self.imbalance(depth),
+            bids: self.top_bids(depth),
+            asks: self.top_asks(depth),
+        }
+    }
+}
+
+// 推送给策略的订单簿更新
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderBookUpdate {
+    pub symbol: String,
+    pub timestamp: DateTime<Utc>,
+    pub last_update_id: u64,
+    pub mid_price: Option<Decimal>,
+    pub microprice: Option<Decimal>,
+    pub imbalance: Option<Decimal>,
+    pub bids: Vec<OrderBookLevel>,
+    pub asks: Vec<OrderBookLevel>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyncStatus {
+    // 没有可用快照，需要拉取
+    NeedsSnapshot,
+    Applied,
+    // 拉取快照期间排队、已包含在快照中的增量
+    Stale,
+}
+
+enum SyncState {
+    Buffering(Vec<DepthUpdate>),
+    Synced(LocalOrderBook),
+}
+
+/// 单个交易对的同步状态机：缓存增量 -> 拉取快照 -> 回放缓存 -> 持续应用增量，
+/// 出现断档时退回缓存状态等待重新同步
+pub struct OrderBookSync {
+    symbol: String,
+    state: SyncState,
+}
+
+impl OrderBookSync {
+    pub fn new(symbol: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            state: SyncState::Buffering(Vec::new()),
+        }
+    }
+
+    pub fn book(&self) -> Option<&LocalOrderBook> {
+        match &self.state {
+            SyncState::Synced(book) => Some(book),
+            SyncState::Buffering(_) => None,
+        }
+    }
+
+    pub fn on_update(&mut self, update: DepthUpdate) -> SyncStatus {
+        match &mut self.state {
+            SyncState::Buffering(buffer) => {
+                buffer.push(update);
+                SyncStatus::NeedsSnapshot
+            }
+            SyncState::Synced(book) => match book.apply(&update) {
+                Ok(true) => SyncStatus::Applied,
+                Ok(false) => SyncStatus::Stale,
+                Err(e) => {
+                    warn!("{}, resyncing order book", e);
+                    self.state = SyncState::Buffering(vec![update]);
+                    SyncStatus::NeedsSnapshot
+                }
+            },
+        }
+    }
+
+    pub fn on_snapshot(&mut self, snapshot: &OrderBook) -> Result<(), OrderBookError> {
+        let mut book = LocalOrderBook::from_snapshot(snapshot)?;
+        let buffered = match &mut self.state {
+            SyncState::Buffering(buffer) => std::mem::take(buffer),
+            SyncState::Synced(_) => Vec::new(),
+        };
+
+        // 快照必须覆盖到第一条缓存增量，否则需要重新拉取
+        if let Some(first) = buffered.first() {
+            if book.last_update_id + 1 < first.first_update_id {
+                let error = OrderBookError::SnapshotTooOld {
+                    symbol: self.symbol.clone(),
+                    snapshot_id: book.last_update_id,
+                    first_buffered: first.first_update_id,
+                };
+                self.state = SyncState::Buffering(buffered);
+                return Err(error);
+            }
+        }
+
+        for update in &buffered {
+            if let Err(e) = book.apply(update) {
+                self.state = SyncState::Buffering(Vec::new());
+                return Err(e);
+            }
+        }
+
+        info!(
+            "Order book for {} synced at update {}",
+            self.symbol, book.last_update_id
+        );
+        self.state = SyncState::Synced(book);
+        Ok(())
+    }
+}
+
+/// 维护多个交易对的本地订单簿，并把更新推送给策略和 `MarketDataCache`
+pub struct OrderBookManager {
+    exchange: Arc<dyn Exchange>,
+    symbols: Vec<String>,
+    depth: usize,
+    books: Arc<RwLock<HashMap<String, LocalOrderBook>>>,
+    cache: Option<Arc<MarketDataCache>>,
+    update_tx: broadcast::Sender<OrderBookUpdate>,
+}
+
+impl OrderBookManager {
+    pub fn new(exchange: Arc<dyn Exchange>, symbols: Vec<String>, depth: usize) -> Self {
+        let (update_tx, _) = broadcast::channel(CHANNEL_BUFFER_SIZE);
+        Self {
+            exchange,
+            symbols: symbols.iter().map(|s| s.to_uppercase()).collect(),
+            depth,
+            books: Arc::new(RwLock::new(HashMap::new())),
+            cache: None,
+            update_tx,
+        }
+    }
+
+    pub fn with_cache(mut self, cache: Arc<MarketDataCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// 订阅订单簿更新
+    pub fn subscribe(&self) -> broadcast::Receiver<OrderBookUpdate> {
+        self.update_tx.subscribe()
+    }
+
+    pub fn book(&self, symbol: &str) -> Option<LocalOrderBook> {
+        self.books.read().ok()?.get(&symbol.to_uppercase()).cloned()
+    }
+
+    /// 运行深度订阅和同步，直到连接断开
+    pub async fn run(&self) -> Result<(), ExchangeError> {
+        let topics: Vec<Topic> = self.symbols.iter().map(Topic::depth).collect();
+        let mut stream = self.exchange.stream(&topics)?;
+        let mut syncs: HashMap<String, OrderBookSync> = HashMap::new();
+        // 已重新订阅、等待推送快照的交易对
+        let mut resubscribed: HashSet<String> = HashSet::new();
+
+        while let Some(event) = stream.next().await {
+            let update = match event {
+                MarketEvent::Depth(update) => update,
+                MarketEvent::DepthSnapshot(snapshot) => {
+                    let symbol = snapshot.symbol.clone();
+                    resubscribed.remove(&symbol);
+                    // 快照之后的增量都在它之后到达，之前缓存的增量直接丢弃
+                    let mut sync = OrderBookSync::new(&symbol);
+                    if let Err(e) = sync.on_snapshot(&snapshot) {
+                        warn!("Failed to apply order book snapshot: {}", e);
+                        continue;
+                    }
+                    if let Some(book) = sync.book() {
+                        self.publish(book);
+                    }
+                    syncs.insert(symbol, sync);
+                    continue;
+                }
+                MarketEvent::State(ConnectionState::Failed(e)) => return Err(e),
+                _ => continue,
+            };
+            let symbol = update.symbol.clone();
+            let sync = syncs
+                .entry(symbol.clone())
+                .or_insert_with(|| OrderBookSync::new(&symbol));
+
+            match sync.on_update(update) {
+                SyncStatus::NeedsSnapshot => {
+                    if let Ok(mut books) = self.books.write() {
+                        books.remove(&symbol);
+                    }
+                    if !self.exchange.streams_depth_snapshots() {
+                        self.resync(sync).await;
+                    } else if resubscribed.insert(symbol.clone()) {
+                        // 重新订阅后交易所会先推送新的快照
+                        info!("Resubscribing {} depth to get a new snapshot", symbol);
+                        let topic = [Topic::depth(symbol.as_str())];
+                        stream.unsubscribe(&topic).await?;
+                        stream.subscribe(&topic).await?;
+                    }
+                }
+                SyncStatus::Applied => {}
+                SyncStatus::Stale => continue,
+            }
+
+            if let Some(book) = sync.book() {
+                self.publish(book);
+            }
+        }
+        Ok(())
+    }
+
+    async fn resync(&self, sync: &mut OrderBookSync) {
+        match self.exchange.get_orderbook(&sync.symbol, SNAPSHOT_LIMIT).await {
+            Ok(snapshot) => {
+                if let Err(e) = sync.on_snapshot(&snapshot) {
+                    warn!("Failed to apply order book snapshot: {}", e);
+                    sleep(RESYNC_DELAY).await;
+                }
+            }
+            Err(e) => {
+                error!("Failed to fetch order book snapshot for {}: {}", sync.symbol, e);
+                sleep(RESYNC_DELAY).await;
+            }
+        }
+    }
+
+    fn publish(&self, book: &LocalOrderBook) {
+        if let Ok(mut books) = self.books.write() {
+            books.insert(book.symbol.clone(), book.clone());
+        }
+        if let Some(cache) = &self.cache {
+            cache.update_order_book(self.exchange.name(), book.to_order_book(self.depth));
+        }
+        // 没有订阅者时发送会失败，忽略即可
+        let _ = self.update_tx.send(book.summary(self.depth));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::binance::BinanceSpot;
+    use crate::exchange::okx::OkxSpot;
+    use crate::exchange::types::ExchangeEndpoints;
+    use crate::tests::mock_exchange::{MockExchange, StreamEvent};
+    use serde_json::Value;
+
+    const SNAPSHOT: &str = include_str!("../../tests/fixtures/binance/depth_snapshot.json");
+    const UPDATES: &str = include_str!("../../tests/fixtures/binance/depth_updates.jsonl");
+    const UPDATES_WITH_GAP: &str = include_str!("../../tests/fixtures/binance/depth_updates_gap.jsonl");
+
+    fn snapshot() -> OrderBook {
+        let data: Value = serde_json::from_str(SNAPSHOT).unwrap();
+        let parse_levels = |levels: &Value| -> Vec<OrderBookLevel> {
+            levels.as_array().unwrap()
+                .iter()
+                .map(|level| OrderBookLevel {
+                    price: level[0].as_str().unwrap().parse().unwrap(),
+                    quantity: level[1].as_str().unwrap().parse().unwrap(),
+                })
+                .collect()
+        };
+        OrderBook {
+            symbol: "BTCUSDT".to_string(),
+            timestamp: Utc::now(),
+            last_update_id: data["lastUpdateId"].as_u64(),
+            bids: parse_levels(&data["bids"]),
+            asks: parse_levels(&data["asks"]),
+        }
+    }
+
+    fn updates(fixture: &str) -> Vec<DepthUpdate> {
+        fixture
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let data: Value = serde_json::from_str(line).unwrap();
+                BinanceSpot::parse_depth_message(data.get("data").unwrap_or(&data)).unwrap()
+            })
+            .collect()
+    }
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_sync_replays_buffered_updates() {
+        let mut sync = OrderBookSync::new("BTCUSDT");
+        let updates = updates(UPDATES);
+
+        // 前两条在快照之前到达并被缓存
+        assert_eq!(sync.on_update(updates[0].clone()), SyncStatus::NeedsSnapshot);
+        assert_eq!(sync.on_update(updates[1].clone()), SyncStatus::NeedsSnapshot);
+        sync.on_snapshot(&snapshot()).unwrap();
+
+        for update in &updates[2..] {
+            assert_eq!(sync.on_update(update.clone()), SyncStatus::Applied);
+        }
+
+        let book = sync.book().unwrap();
+        assert_eq!(book.last_update_id(), 1027030);
+        assert_eq!(book.best_bid().unwrap().price, dec("50000.10"));
+        assert_eq!(book.best_bid().unwrap().quantity, dec("0.80000000"));
+        assert_eq!(book.best_ask().unwrap().price, dec("50000.20"));
+        // 50000.30 的卖单在增量中被删除
+        assert!(book.top_asks(10).iter().all(|level| level.price != dec("50000.30")));
+    }
+
+    #[test]
+    fn test_stale_updates_are_dropped() {
+        let mut book = LocalOrderBook::from_snapshot(&snapshot()).unwrap();
+        let updates = updates(UPDATES);
+        // 第一条增量完全早于快照
+        assert_eq!(book.apply(&updates[0]), Ok(false));
+        assert_eq!(book.last_update_id(), 1027024);
+    }
+
+    #[test]
+    fn test_gap_triggers_resync() {
+        let mut sync = OrderBookSync::new("BTCUSDT");
+        sync.on_snapshot(&snapshot()).unwrap();
+
+        let updates = updates(UPDATES_WITH_GAP);
+        assert_eq!(sync.on_update(updates[0].clone()), SyncStatus::Applied);
+        assert_eq!(sync.on_update(updates[1].clone()), SyncStatus::NeedsSnapshot);
+        assert!(sync.book().is_none());
+
+        // 旧快照不能覆盖缓存的增量
+        let result = sync.on_snapshot(&snapshot());
+        assert!(matches!(result, Err(OrderBookError::SnapshotTooOld { first_buffered: 1027030, .. })));
+
+        let mut fresh = snapshot();
+        fresh.last_update_id = Some(1027031);
+        sync.on_snapshot(&fresh).unwrap();
+        assert_eq!(sync.on_update(updates[2].clone()), SyncStatus::Applied);
+        assert_eq!(sync.book().unwrap().last_update_id(), 1027033);
+    }
+
+    #[test]
+    fn test_book_metrics() {
+        let book = LocalOrderBook::from_snapshot(&snapshot()).unwrap();
+
+        assert_eq!(book.mid_price(), Some(dec("50000.15")));
+        // (50000.10 * 1.5 + 50000.20 * 0.5) / 2.0
+        assert_eq!(book.microprice(), Some(dec("50000.125")));
+        // 前两档：买 0.5 + 2.0，卖 1.5 + 1.0
+        assert_eq!(book.imbalance(2), Some(Decimal::ZERO));
+        assert_eq!(book.top_bids(2).len(), 2);
+        assert_eq!(book.top_asks(5).len(), 3);
+    }
+
+    fn okx_books(action: &str, prev_seq_id: i64, seq_id: i64, bids: Value, asks: Value) -> StreamEvent {
+        StreamEvent::Text(
+            serde_json::json!({
+                "arg": {"channel": "books", "instId": "BTC-USDT"},
+                "action": action,
+                "data": [{
+                    "asks": asks, "bids": bids, "ts": "1700000000456",
+                    "prevSeqId": prev_seq_id, "seqId": seq_id
+                }]
+            })
+            .to_string(),
+        )
+    }
+
+    // OKX 的订单簿由推送的快照初始化，断档后重新订阅获取新快照
+    #[tokio::test]
+    async fn test_okx_book_through_manager() {
+        let mock = MockExchange::start().await.unwrap();
+        mock.push_stream(vec![
+            okx_books(
+                "snapshot", -1, 100,
+                serde_json::json!([["50000.1", "0.5", "0", "1"]]),
+                serde_json::json!([["50000.2", "1.5", "0", "2"]]),
+            ),
+            okx_books("update", 100, 105, serde_json::json!([["50000.1", "0.7", "0", "3"]]), serde_json::json!([])),
+            // prevSeqId 与上一条的 seqId 不连续
+            okx_books("update", 110, 112, serde_json::json!([["50000.0", "9", "0", "1"]]), serde_json::json!([])),
+            StreamEvent::Delay(Duration::from_millis(200)),
+            okx_books(
+                "snapshot", -1, 120,
+                serde_json::json!([["50000.0", "2", "0", "1"]]),
+                serde_json::json!([["50000.3", "1", "0", "1"]]),
+            ),
+            okx_books("update", 120, 121, serde_json::json!([]), serde_json::json!([["50000.3", "0.4", "0", "1"]])),
+        ]);
+        let okx = OkxSpot::new().with_endpoints(ExchangeEndpoints {
+            ws_url: mock.endpoints().ws_url.join("/ws/v5/public").unwrap(),
+            ..mock.endpoints()
+        });
+        let manager = OrderBookManager::new(Arc::new(okx), vec!["BTCUSDT".to_string()], 10);
+        let mut updates = manager.subscribe();
+
+        let mut seen = Vec::new();
+        tokio::select! {
+            result = manager.run() => panic!("order book stream ended: {:?}", result),
+            _ = async {
+                while let Ok(update) = updates.recv().await {
+                    seen.push(update.last_update_id);
+                    if update.last_update_id == 121 {
+                        break;
+                    }
+                }
+            } => {}
+            _ = sleep(Duration::from_secs(5)) => panic!("timed out, saw updates {:?}", seen),
+        }
+
+        // 断档的增量没有被应用
+        assert_eq!(seen, vec![100, 105, 120, 121]);
+        let book = manager.book("BTCUSDT").unwrap();
+        let best_bid = book.best_bid().unwrap();
+        assert_eq!((best_bid.price, best_bid.quantity), (dec("50000.0"), dec("2")));
+        assert_eq!(book.best_ask().unwrap().quantity, dec("0.4"));
+
+        // 没有拉取不带序号的 REST 快照
+        assert!(mock.requests().is_empty());
+        let messages = mock.stream_messages();
+        assert!(messages.iter().any(|m| m.contains("unsubscribe") && m.contains("books")));
+        assert_eq!(messages.iter().filter(|m| m.contains("\"subscribe\"")).count(), 2);
+    }
+}
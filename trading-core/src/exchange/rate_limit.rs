@@ -0,0 +1,179 @@
+// trading-core/src/exchange/rate_limit.rs
+// 按请求权重限流的令牌桶：令牌按时间匀速恢复，每个请求消耗与其权重相同的令牌。
+// 所有调用方按到达顺序排队，收到交易所返回的已用权重或 429/418 后同步本地状态。
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+// Binance 现货 REST 每个 IP 每分钟 6000 权重
+pub const BINANCE_WEIGHT_PER_MINUTE: u32 = 6000;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+    // 收到 429/418 后在此之前不再发送请求
+    blocked_until: Option<Instant>,
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    capacity: u32,
+    interval: Duration,
+    bucket: Mutex<Bucket>,
+    // 公平锁，保证等待中的调用方按顺序获得令牌
+    queue: tokio::sync::Mutex<()>,
+}
+
+impl RateLimiter {
+    /// 每个 interval 内最多消耗 capacity 权重
+    pub fn new(capacity: u32, interval: Duration) -> Self {
+        Self {
+            capacity,
+            interval,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity as f64,
+                last_refill: Instant::now(),
+                blocked_until: None,
+            }),
+            queue: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn binance() -> Self {
+        Self::new(BINANCE_WEIGHT_PER_MINUTE, Duration::from_secs(60))
+    }
+
+    /// 等待直到有足够的令牌发送权重为 weight 的请求
+    pub async fn acquire(&self, weight: u32) {
+        let _turn = self.queue.lock().await;
+        loop {
+            let wait = match self.try_acquire_at(weight, Instant::now()) {
+                Ok(()) => return,
+                Err(wait) => wait,
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// 当前剩余的权重
+    pub fn available(&self) -> u32 {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket, Instant::now());
+        bucket.tokens as u32
+    }
+
+    /// 根据响应头中交易所统计的已用权重校正本地令牌数
+    pub fn record_used_weight(&self, used: u32) {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket, Instant::now());
+        let remaining = self.capacity.saturating_sub(used) as f64;
+        if remaining < bucket.tokens {
+            bucket.tokens = remaining;
+        }
+    }
+
+    /// 收到 429/418 后暂停全部请求 retry_after，并清空令牌
+    pub fn back_off(&self, retry_after: Duration) {
+        warn!("Rate limited by exchange, pausing requests for {:?}", retry_after);
+        self.back_off_at(retry_after, Instant::now());
+    }
+
+    fn back_off_at(&self, retry_after: Duration, now: Instant) {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket, now);
+        bucket.tokens = 0.0;
+        let until = now + retry_after;
+        if bucket.blocked_until.is_none_or(|blocked| blocked < until) {
+            bucket.blocked_until = Some(until);
+        }
+    }
+
+    // 成功时扣除令牌，否则返回需要等待的时间
+    fn try_acquire_at(&self, weight: u32, now: Instant) -> Result<(), Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        if let Some(until) = bucket.blocked_until {
+            if now < until {
+                return Err(until - now);
+            }
+            bucket.blocked_until = None;
+        }
+
+        self.refill(&mut bucket, now);
+        // 权重超过容量的请求在令牌满时放行，避免永远等待
+        let weight = weight.min(self.capacity) as f64;
+        if bucket.tokens >= weight {
+            bucket.tokens -= weight;
+            return Ok(());
+        }
+        let missing = weight - bucket.tokens;
+        Err(self.interval.mul_f64(missing / self.capacity as f64))
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        if now <= bucket.last_refill {
+            return;
+        }
+        let elapsed = now - bucket.last_refill;
+        let restored = elapsed.as_secs_f64() / self.interval.as_secs_f64() * self.capacity as f64;
+        bucket.tokens = (bucket.tokens + restored).min(self.capacity as f64);
+        bucket.last_refill = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_drains_and_refills() {
+        let limiter = RateLimiter::new(100, Duration::from_secs(60));
+        let start = limiter.bucket.lock().unwrap().last_refill;
+
+        assert!(limiter.try_acquire_at(60, start).is_ok());
+        assert!(limiter.try_acquire_at(40, start).is_ok());
+        // 桶已空，缺 10 权重需要等待 6 秒
+        let wait = limiter.try_acquire_at(10, start).unwrap_err();
+        assert!((wait.as_secs_f64() - 6.0).abs() < 1e-6);
+        assert!(limiter.try_acquire_at(10, start + Duration::from_secs(7)).is_ok());
+
+        // 超过容量的请求在桶满后放行
+        assert!(limiter.try_acquire_at(500, start + Duration::from_secs(120)).is_ok());
+    }
+
+    #[test]
+    fn test_used_weight_and_back_off() {
+        let limiter = RateLimiter::new(100, Duration::from_secs(60));
+        let start = limiter.bucket.lock().unwrap().last_refill;
+
+        limiter.record_used_weight(90);
+        assert!(limiter.available() <= 10);
+        // 交易所统计的用量更低时不增加令牌
+        limiter.record_used_weight(0);
+        assert!(limiter.available() <= 10);
+
+        limiter.back_off_at(Duration::from_secs(30), start);
+        assert_eq!(limiter.try_acquire_at(1, start + Duration::from_secs(10)), Err(Duration::from_secs(20)));
+        assert!(limiter.try_acquire_at(1, start + Duration::from_secs(30)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waits_for_tokens() {
+        let limiter = std::sync::Arc::new(RateLimiter::new(10, Duration::from_millis(200)));
+        limiter.acquire(10).await;
+
+        let started = Instant::now();
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move { limiter.acquire(5).await })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        // 两个请求共需 10 权重，即一个完整周期
+        assert!(started.elapsed() >= Duration::from_millis(190));
+    }
+}
@@ -0,0 +1,141 @@
+// trading-core/src/exchange/reconnect.rs
+// 行情连接的重连策略：断线后按带随机抖动的指数退避等待，避免大量客户端同时重连；
+// 各交易对长时间没有数据时重新订阅，接近交易所的连接时长上限时提前轮换连接。
+
+use serde::Deserialize;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ReconnectConfig {
+    // 第一次重连前的等待时间（毫秒），之后每次失败翻倍，最多 max_delay_secs 秒
+    pub initial_delay_ms: u64,
+    pub max_delay_secs: u64,
+    // 交易对超过该秒数没有成交时重新订阅，所有交易对都没有数据时重建连接
+    pub stale_after_secs: u64,
+    // 连接建立多久后轮换；未配置时按交易所的连接时长上限提前 5 分钟轮换
+    pub rotate_after_secs: Option<u64>,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay_ms: 500,
+            max_delay_secs: 60,
+            stale_after_secs: 60,
+            rotate_after_secs: None,
+        }
+    }
+}
+
+// 交易所强制断开前预留的轮换时间
+const ROTATE_MARGIN: Duration = Duration::from_secs(300);
+
+impl ReconnectConfig {
+    pub fn backoff(&self) -> Backoff {
+        Backoff::new(
+            Duration::from_millis(self.initial_delay_ms),
+            Duration::from_secs(self.max_delay_secs),
+        )
+    }
+
+    pub fn stale_after(&self) -> Duration {
+        Duration::from_secs(self.stale_after_secs)
+    }
+
+    /// 连接需要轮换的时间；交易所没有时长上限且未配置时不轮换
+    pub fn rotate_after(&self, max_connection_age: Option<Duration>) -> Option<Duration> {
+        match self.rotate_after_secs {
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => max_connection_age.map(|age| age.saturating_sub(ROTATE_MARGIN.min(age / 10))),
+        }
+    }
+}
+
+/// 带抖动的指数退避：第 n 次等待在 [上限/2, 上限] 内随机，上限为 initial * 2^n 且不超过 max
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            attempt: 0,
+        }
+    }
+
+    /// 下一次重连前的等待时间
+    pub fn next_delay(&mut self) -> Duration {
+        self.next_delay_with(random_fraction())
+    }
+
+    /// 连接恢复正常后重新从 initial 开始
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    fn next_delay_with(&mut self, fraction: f64) -> Duration {
+        let ceiling = self
+            .initial
+            .saturating_mul(1u32.checked_shl(self.attempt).unwrap_or(u32::MAX))
+            .min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+        ceiling / 2 + (ceiling / 2).mul_f64(fraction.clamp(0.0, 1.0))
+    }
+}
+
+// [0, 1) 内的随机数；RandomState 每次使用不同的随机种子，足以用于抖动
+fn random_fraction() -> f64 {
+    let value = RandomState::new().build_hasher().finish();
+    (value >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_with_jitter_and_resets() {
+        let mut backoff = Backoff::new(Duration::from_millis(500), Duration::from_secs(4));
+
+        assert_eq!(backoff.next_delay_with(0.0), Duration::from_millis(250));
+        assert_eq!(backoff.next_delay_with(1.0), Duration::from_secs(1));
+        assert_eq!(backoff.next_delay_with(0.5), Duration::from_millis(1500));
+        // 达到上限后不再增长
+        for _ in 0..40 {
+            assert!(backoff.next_delay() <= Duration::from_secs(4));
+        }
+        assert!(backoff.next_delay() >= Duration::from_secs(2));
+
+        backoff.reset();
+        assert_eq!(backoff.attempt(), 0);
+        assert!(backoff.next_delay() <= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_rotate_after() {
+        let config = ReconnectConfig::default();
+        let day = Duration::from_secs(24 * 3600);
+        assert_eq!(config.rotate_after(Some(day)), Some(day - Duration::from_secs(300)));
+        assert_eq!(config.rotate_after(None), None);
+        // 时长上限较短时按 10% 预留
+        assert_eq!(config.rotate_after(Some(Duration::from_secs(600))), Some(Duration::from_secs(540)));
+
+        let config = ReconnectConfig {
+            rotate_after_secs: Some(30),
+            ..ReconnectConfig::default()
+        };
+        assert_eq!(config.rotate_after(None), Some(Duration::from_secs(30)));
+    }
+}
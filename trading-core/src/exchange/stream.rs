@@ -0,0 +1,402 @@
+// trading-core/src/exchange/stream.rs
+// 行情订阅句柄：一个 WebSocket 连接上的全部订阅以 Stream 的形式产出类型化事件，
+// 连接存续期间可以增减订阅，连接状态的变化也作为事件按顺序推送。
+// 各交易所只需实现 StreamProtocol，描述如何连接、如何编码订阅请求以及如何解析推送。
+
+use super::types::{DepthUpdate, ExchangeError, KlineUpdate, OrderBook};
+use crate::data::types::{MarketDataPoint, TickData};
+use futures_util::{SinkExt, Stream, StreamExt};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, error, info};
+
+const EVENT_BUFFER_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Channel {
+    Ticker,
+    Trades,
+    Depth,
+    // K 线周期，例如 "1m"、"1h"
+    Kline(String),
+}
+
+/// 一个交易对上的一个频道
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Topic {
+    pub channel: Channel,
+    pub symbol: String,
+}
+
+impl Topic {
+    pub fn new(channel: Channel, symbol: impl Into<String>) -> Self {
+        Self {
+            channel,
+            symbol: symbol.into(),
+        }
+    }
+
+    pub fn ticker(symbol: impl Into<String>) -> Self {
+        Self::new(Channel::Ticker, symbol)
+    }
+
+    pub fn trades(symbol: impl Into<String>) -> Self {
+        Self::new(Channel::Trades, symbol)
+    }
+
+    pub fn depth(symbol: impl Into<String>) -> Self {
+        Self::new(Channel::Depth, symbol)
+    }
+
+    pub fn kline(symbol: impl Into<String>, interval: impl Into<String>) -> Self {
+        Self::new(Channel::Kline(interval.into()), symbol)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    // 调用方关闭订阅，或服务端正常结束了连接
+    Closed,
+    // 连接异常断开或交易所返回错误，调用方应重新订阅
+    Failed(ExchangeError),
+}
+
+impl ConnectionState {
+    /// 连接已结束，之后不会再有事件
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Closed | Self::Failed(_))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MarketEvent {
+    Ticker(MarketDataPoint),
+    Trade(TickData),
+    Depth(DepthUpdate),
+    // 行情流推送的完整深度快照（OKX books 频道），last_update_id 为快照的序号
+    DepthSnapshot(OrderBook),
+    Kline(KlineUpdate),
+    State(ConnectionState),
+}
+
+enum Command {
+    Subscribe(Vec<Topic>, oneshot::Sender<Result<(), ExchangeError>>),
+    Unsubscribe(Vec<Topic>, oneshot::Sender<Result<(), ExchangeError>>),
+    Close,
+}
+
+/// 交易所 WebSocket 协议的差异部分
+pub(crate) trait StreamProtocol: Send + Sync + 'static {
+    /// 连接地址，以及连接建立后需要立即发送的订阅消息
+    fn connect_request(&mut self, topics: &[Topic]) -> Result<(String, Option<String>), ExchangeError>;
+
+    /// 在已建立的连接上订阅或取消订阅
+    fn subscription_message(&mut self, topics: &[Topic], subscribe: bool) -> Result<String, ExchangeError>;
+
+    /// 解析一条文本消息；订阅确认等控制消息返回空，交易所返回的错误以 Err 结束连接
+    fn parse(&self, text: &str) -> Result<Vec<MarketEvent>, ExchangeError>;
+
+    /// 应用层心跳的间隔和内容，只用协议层 ping/pong 的交易所返回 None
+    fn heartbeat(&self) -> Option<(Duration, String)> {
+        None
+    }
+}
+
+/// 行情订阅句柄，按到达顺序产出事件；最后一个事件总是 Closed 或 Failed 状态。
+/// 句柄被丢弃时连接随之关闭
+pub struct MarketStream {
+    events: mpsc::Receiver<MarketEvent>,
+    commands: mpsc::UnboundedSender<Command>,
+    state: watch::Receiver<ConnectionState>,
+    task: JoinHandle<()>,
+}
+
+impl MarketStream {
+    /// 在后台连接并运行协议；连接地址和初始订阅在这里校验
+    pub(crate) fn spawn<P: StreamProtocol>(mut protocol: P, topics: &[Topic]) -> Result<Self, ExchangeError> {
+        let (url, initial) = protocol.connect_request(topics)?;
+        let (event_tx, events) = mpsc::channel(EVENT_BUFFER_SIZE);
+        let (commands, command_rx) = mpsc::unbounded_channel();
+        let (state_tx, state) = watch::channel(ConnectionState::Connecting);
+
+        let task = tokio::spawn(async move {
+            let mut connection = Connection {
+                protocol,
+                events: event_tx,
+                state: state_tx,
+            };
+            let result = connection.run(&url, initial, command_rx).await;
+            let state = match result {
+                Ok(()) => ConnectionState::Closed,
+                Err(e) => {
+                    error!("Market data stream {} failed: {}", url, e);
+                    ConnectionState::Failed(e)
+                }
+            };
+            connection.report(state).await;
+        });
+
+        Ok(Self {
+            events,
+            commands,
+            state,
+            task,
+        })
+    }
+
+    /// 在当前连接上追加订阅，请求发出后返回
+    pub async fn subscribe(&self, topics: &[Topic]) -> Result<(), ExchangeError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.send(Command::Subscribe(topics.to_vec(), reply_tx))?;
+        Self::reply(reply_rx).await
+    }
+
+    /// 在当前连接上取消订阅，请求发出后返回
+    pub async fn unsubscribe(&self, topics: &[Topic]) -> Result<(), ExchangeError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.send(Command::Unsubscribe(topics.to_vec(), reply_tx))?;
+        Self::reply(reply_rx).await
+    }
+
+    /// 关闭连接，事件流在推送 Closed 状态后结束
+    pub fn close(&self) {
+        let _ = self.commands.send(Command::Close);
+    }
+
+    /// 当前的连接状态
+    pub fn state(&self) -> ConnectionState {
+        self.state.borrow().clone()
+    }
+
+    fn send(&self, command: Command) -> Result<(), ExchangeError> {
+        self.commands
+            .send(command)
+            .map_err(|_| ExchangeError::NetworkError("Stream is closed".to_string()))
+    }
+
+    async fn reply(reply: oneshot::Receiver<Result<(), ExchangeError>>) -> Result<(), ExchangeError> {
+        reply
+            .await
+            .unwrap_or_else(|_| Err(ExchangeError::NetworkError("Stream is closed".to_string())))
+    }
+}
+
+impl Stream for MarketStream {
+    type Item = MarketEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().events.poll_recv(cx)
+    }
+}
+
+impl Drop for MarketStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct Connection<P> {
+    protocol: P,
+    events: mpsc::Sender<MarketEvent>,
+    state: watch::Sender<ConnectionState>,
+}
+
+impl<P: StreamProtocol> Connection<P> {
+    async fn report(&self, state: ConnectionState) {
+        self.state.send_replace(state.clone());
+        let _ = self.events.send(MarketEvent::State(state)).await;
+    }
+
+    // 正常结束（调用方关闭、服务端结束连接或事件接收端被丢弃）返回 Ok
+    async fn run(
+        &mut self,
+        url: &str,
+        initial: Option<String>,
+        mut commands: mpsc::UnboundedReceiver<Command>,
+    ) -> Result<(), ExchangeError> {
+        self.report(ConnectionState::Connecting).await;
+        info!("Connecting to market data stream: {}", url);
+        let (ws_stream, _response) = connect_async(url)
+            .await
+            .map_err(|e| ExchangeError::NetworkError(format!("WebSocket connection failed: {}", e)))?;
+        let (mut write, mut read) = ws_stream.split();
+
+        if let Some(message) = initial {
+            write
+                .send(Message::Text(message))
+                .await
+                .map_err(|e| ExchangeError::NetworkError(format!("Failed to send subscription: {}", e)))?;
+        }
+        self.report(ConnectionState::Connected).await;
+
+        let heartbeat = self.protocol.heartbeat();
+        let period = heartbeat.as_ref().map_or(Duration::from_secs(3600), |(period, _)| *period);
+        let mut ping = tokio::time::interval(period);
+        ping.tick().await;
+
+        loop {
+            tokio::select! {
+                command = commands.recv() => {
+                    let (topics, subscribe, reply) = match command {
+                        Some(Command::Subscribe(topics, reply)) => (topics, true, reply),
+                        Some(Command::Unsubscribe(topics, reply)) => (topics, false, reply),
+                        Some(Command::Close) | None => {
+                            let _ = write.send(Message::Close(None)).await;
+                            return Ok(());
+                        }
+                    };
+                    let message = match self.protocol.subscription_message(&topics, subscribe) {
+                        Ok(message) => message,
+                        Err(e) => {
+                            let _ = reply.send(Err(e));
+                            continue;
+                        }
+                    };
+                    debug!("Sending stream request: {}", message);
+                    let result = write
+                        .send(Message::Text(message))
+                        .await
+                        .map_err(|e| ExchangeError::NetworkError(format!("Failed to send subscription: {}", e)));
+                    let _ = reply.send(result.clone());
+                    result?;
+                }
+                _ = ping.tick(), if heartbeat.is_some() => {
+                    if let Some((_, message)) = &heartbeat {
+                        write
+                            .send(Message::Text(message.clone()))
+                            .await
+                            .map_err(|e| ExchangeError::NetworkError(format!("Failed to send ping: {}", e)))?;
+                    }
+                }
+                msg = read.next() => {
+                    let Some(msg) = msg else {
+                        return Ok(());
+                    };
+                    match msg {
+                        Ok(Message::Text(text)) => {
+                            debug!("Received message: {}", text);
+                            for event in self.protocol.parse(&text)? {
+                                if self.events.send(event).await.is_err() {
+                                    info!("Stream consumer closed, disconnecting");
+                                    return Ok(());
+                                }
+                            }
+                        }
+                        Ok(Message::Ping(data)) => {
+                            write
+                                .send(Message::Pong(data))
+                                .await
+                                .map_err(|e| ExchangeError::NetworkError(format!("Failed to send pong: {}", e)))?;
+                        }
+                        Ok(Message::Close(frame)) => {
+                            error!("WebSocket closed by server: {:?}", frame);
+                            return Err(ExchangeError::NetworkError("Connection closed by server".into()));
+                        }
+                        Ok(_) => {}
+                        Err(e) => return Err(ExchangeError::NetworkError(e.to_string())),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::binance::BinanceSpot;
+    use crate::exchange::okx::OkxSpot;
+    use crate::exchange::types::{Exchange, ExchangeEndpoints};
+    use crate::tests::mock_exchange::{MockExchange, StreamEvent};
+
+    const TRADE: &str = include_str!("../../tests/fixtures/binance/trade.json");
+    const KLINE: &str = include_str!("../../tests/fixtures/binance/kline.json");
+
+    async fn next(stream: &mut MarketStream) -> MarketEvent {
+        tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("event within timeout")
+            .expect("stream still open")
+    }
+
+    // 模拟服务器在后台记录客户端消息，等待记录到足够的条数
+    async fn stream_messages(mock: &MockExchange, count: usize) -> Vec<String> {
+        for _ in 0..100 {
+            let messages = mock.stream_messages();
+            if messages.len() >= count {
+                return messages;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        mock.stream_messages()
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_subscription_and_close() {
+        let mock = MockExchange::start().await.unwrap();
+        let mut events = StreamEvent::from_jsonl(TRADE);
+        events.push(StreamEvent::Delay(Duration::from_millis(100)));
+        events.extend(StreamEvent::from_jsonl(KLINE));
+        mock.push_stream(events);
+        let spot = BinanceSpot::new(None).with_endpoints(mock.endpoints());
+
+        let mut stream = spot.stream(&[Topic::trades("BTCUSDT")]).unwrap();
+        assert!(matches!(next(&mut stream).await, MarketEvent::State(ConnectionState::Connecting)));
+        assert!(matches!(next(&mut stream).await, MarketEvent::State(ConnectionState::Connected)));
+        assert!(matches!(next(&mut stream).await, MarketEvent::Trade(tick) if tick.trade_id == "3412093912"));
+
+        stream.subscribe(&[Topic::kline("BTCUSDT", "1m")]).await.unwrap();
+        stream.unsubscribe(&[Topic::trades("BTCUSDT")]).await.unwrap();
+        assert!(matches!(
+            next(&mut stream).await,
+            MarketEvent::Kline(kline) if kline.interval == "1m" && !kline.is_closed
+        ));
+
+        stream.close();
+        assert!(matches!(next(&mut stream).await, MarketEvent::State(ConnectionState::Closed)));
+        assert!(stream.next().await.is_none());
+        assert!(matches!(stream.state(), ConnectionState::Closed));
+        assert!(stream.subscribe(&[Topic::ticker("BTCUSDT")]).await.is_err());
+
+        // 初始订阅在 URL 中，之后的增减通过同一连接发送
+        assert_eq!(mock.stream_connections(), vec!["/ws/btcusdt@trade".to_string()]);
+        let messages = stream_messages(&mock, 2).await;
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains("SUBSCRIBE") && messages[0].contains("btcusdt@kline_1m"));
+        assert!(messages[1].contains("UNSUBSCRIBE") && messages[1].contains("btcusdt@trade"));
+    }
+
+    #[tokio::test]
+    async fn test_exchange_error_fails_stream() {
+        let mock = MockExchange::start().await.unwrap();
+        mock.push_stream(vec![StreamEvent::Text(
+            r#"{"event":"error","code":"60018","msg":"Wrong URL or channel:candle1m doesn't exist."}"#.to_string(),
+        )]);
+        let okx = OkxSpot::new().with_endpoints(ExchangeEndpoints {
+            ws_url: mock.endpoints().ws_url.join("/ws/v5/public").unwrap(),
+            ..mock.endpoints()
+        });
+
+        // K 线在单独的 business 连接上推送，不能和其他频道混用
+        assert!(okx.stream(&[Topic::trades("BTCUSDT"), Topic::kline("BTCUSDT", "1m")]).is_err());
+
+        let mut stream = okx.stream(&[Topic::kline("BTCUSDT", "1m")]).unwrap();
+        let mut last = None;
+        while let Some(event) = stream.next().await {
+            last = Some(event);
+        }
+        assert!(matches!(
+            last,
+            Some(MarketEvent::State(ConnectionState::Failed(ExchangeError::ApiError(msg)))) if msg.contains("60018")
+        ));
+        assert_eq!(mock.stream_connections(), vec!["/ws/v5/business".to_string()]);
+        assert!(stream_messages(&mock, 1).await[0].contains(r#""channel":"candle1m""#));
+    }
+}
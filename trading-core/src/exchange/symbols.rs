@@ -0,0 +1,408 @@
+// trading-core/src/exchange/symbols.rs
+// 交易对元数据：基础/计价资产、交易状态以及价格、数量和最小名义价值等交易规则。
+// 从交易所 exchangeInfo 加载并按交易所保存到 symbols 表；查询时统一 "BTCUSDT"、"btcusdt"、"BTC/USDT" 等写法。
+
+use super::types::{Exchange, ExchangeError};
+use crate::data::market_data::MarketDataError;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use thiserror::Error;
+use tracing::info;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum FilterViolation {
+    #[error("Unknown symbol: {0}")]
+    UnknownSymbol(String),
+
+    #[error("{symbol} is not trading (status {status})")]
+    NotTrading { symbol: String, status: String },
+
+    #[error("Price {price} is outside [{min}, {max}]")]
+    PriceOutOfRange { price: Decimal, min: Decimal, max: Decimal },
+
+    #[error("Price {price} is not a multiple of tick size {tick_size}")]
+    InvalidTickSize { price: Decimal, tick_size: Decimal },
+
+    #[error("Quantity {quantity} is outside [{min}, {max}]")]
+    QuantityOutOfRange { quantity: Decimal, min: Decimal, max: Decimal },
+
+    #[error("Quantity {quantity} is not a multiple of step size {step_size}")]
+    InvalidStepSize { quantity: Decimal, step_size: Decimal },
+
+    #[error("Order notional {notional} is below minimum {min_notional}")]
+    MinNotional { notional: Decimal, min_notional: Decimal },
+}
+
+// 交易规则中为 0 的上下限和步长表示不限制
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SymbolInfo {
+    pub symbol: String,
+    pub base_asset: String,
+    pub quote_asset: String,
+    pub status: String,
+    pub tick_size: Decimal,
+    pub min_price: Decimal,
+    pub max_price: Decimal,
+    pub step_size: Decimal,
+    pub min_qty: Decimal,
+    pub max_qty: Decimal,
+    // 市价单的数量规则（Binance 的 MARKET_LOT_SIZE），与限价单分开检查
+    pub market_step_size: Decimal,
+    pub market_min_qty: Decimal,
+    pub market_max_qty: Decimal,
+    pub min_notional: Decimal,
+}
+
+impl SymbolInfo {
+    pub fn is_trading(&self) -> bool {
+        self.status == "TRADING"
+    }
+
+    /// BTC/USDT 形式的名称
+    pub fn display_name(&self) -> String {
+        format!("{}/{}", self.base_asset, self.quote_asset)
+    }
+
+    /// 价格向下取整到 tick size
+    pub fn round_price(&self, price: Decimal) -> Decimal {
+        floor_to_step(price, self.tick_size)
+    }
+
+    /// 数量向下取整到 step size
+    pub fn round_quantity(&self, quantity: Decimal) -> Decimal {
+        floor_to_step(quantity, self.step_size)
+    }
+
+    /// 按交易所规则检查限价单的价格和数量
+    pub fn check_order(&self, price: Decimal, quantity: Decimal) -> Result<(), FilterViolation> {
+        self.check_status()?;
+        self.check_price(price)?;
+        check_quantity(quantity, self.min_qty, self.max_qty, self.step_size)?;
+        self.check_notional(price * quantity)
+    }
+
+    /// 市价单不检查价格，数量按市价单规则检查，最小名义价值按参考价估算
+    pub fn check_market_order(
+        &self,
+        reference_price: Decimal,
+        quantity: Decimal,
+    ) -> Result<(), FilterViolation> {
+        self.check_status()?;
+        check_quantity(quantity, self.market_min_qty, self.market_max_qty, self.market_step_size)?;
+        self.check_notional(reference_price * quantity)
+    }
+
+    fn check_status(&self) -> Result<(), FilterViolation> {
+        if !self.is_trading() {
+            return Err(FilterViolation::NotTrading {
+                symbol: self.symbol.clone(),
+                status: self.status.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    fn check_price(&self, price: Decimal) -> Result<(), FilterViolation> {
+        if price < self.min_price || (!self.max_price.is_zero() && price > self.max_price) {
+            return Err(FilterViolation::PriceOutOfRange {
+                price,
+                min: self.min_price,
+                max: self.max_price,
+            });
+        }
+        if !self.tick_size.is_zero() && !(price % self.tick_size).is_zero() {
+            return Err(FilterViolation::InvalidTickSize {
+                price,
+                tick_size: self.tick_size,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_notional(&self, notional: Decimal) -> Result<(), FilterViolation> {
+        if notional < self.min_notional {
+            return Err(FilterViolation::MinNotional {
+                notional,
+                min_notional: self.min_notional,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn check_quantity(
+    quantity: Decimal,
+    min: Decimal,
+    max: Decimal,
+    step_size: Decimal,
+) -> Result<(), FilterViolation> {
+    if quantity < min || (!max.is_zero() && quantity > max) {
+        return Err(FilterViolation::QuantityOutOfRange { quantity, min, max });
+    }
+    if !step_size.is_zero() && !(quantity % step_size).is_zero() {
+        return Err(FilterViolation::InvalidStepSize { quantity, step_size });
+    }
+    Ok(())
+}
+
+fn floor_to_step(value: Decimal, step: Decimal) -> Decimal {
+    if step.is_zero() {
+        return value;
+    }
+    ((value / step).floor() * step).normalize()
+}
+
+/// 去掉分隔符并转为大写："btc/usdt"、"BTC-USDT" -> "BTCUSDT"
+pub fn normalize_symbol(symbol: &str) -> String {
+    symbol
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SymbolRegistry {
+    symbols: HashMap<String, SymbolInfo>,
+}
+
+impl SymbolRegistry {
+    pub fn new(symbols: Vec<SymbolInfo>) -> Self {
+        Self {
+            symbols: symbols
+                .into_iter()
+                .map(|info| (info.symbol.clone(), info))
+                .collect(),
+        }
+    }
+
+    /// 从交易所获取全部交易对
+    pub async fn fetch(exchange: &dyn Exchange) -> Result<Self, ExchangeError> {
+        Ok(Self::new(exchange.get_symbols().await?))
+    }
+
+    /// 接受任意常见写法
+    pub fn get(&self, symbol: &str) -> Option<&SymbolInfo> {
+        self.symbols.get(&normalize_symbol(symbol))
+    }
+
+    /// 转为交易所使用的写法，未知交易对返回 None
+    pub fn normalize(&self, symbol: &str) -> Option<&str> {
+        self.get(symbol).map(|info| info.symbol.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SymbolInfo> {
+        self.symbols.values()
+    }
+
+    /// 加载该交易所保存的交易对
+    pub async fn load(pool: &PgPool, exchange: &str) -> Result<Self, MarketDataError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT symbol, base_asset, quote_asset, status,
+                tick_size as "tick_size: Decimal",
+                min_price as "min_price: Decimal",
+                max_price as "max_price: Decimal",
+                step_size as "step_size: Decimal",
+                min_qty as "min_qty: Decimal",
+                max_qty as "max_qty: Decimal",
+                market_step_size as "market_step_size: Decimal",
+                market_min_qty as "market_min_qty: Decimal",
+                market_max_qty as "market_max_qty: Decimal",
+                min_notional as "min_notional: Decimal"
+            FROM symbols
+            WHERE exchange = $1
+            "#,
+            exchange
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(Self::new(
+            rows.into_iter()
+                .map(|row| SymbolInfo {
+                    symbol: row.symbol,
+                    base_asset: row.base_asset,
+                    quote_asset: row.quote_asset,
+                    status: row.status,
+                    tick_size: row.tick_size,
+                    min_price: row.min_price,
+                    max_price: row.max_price,
+                    step_size: row.step_size,
+                    min_qty: row.min_qty,
+                    max_qty: row.max_qty,
+                    market_step_size: row.market_step_size,
+                    market_min_qty: row.market_min_qty,
+                    market_max_qty: row.market_max_qty,
+                    min_notional: row.min_notional,
+                })
+                .collect(),
+        ))
+    }
+
+    /// 保存该交易所的全部交易对，已存在的交易对更新交易规则
+    pub async fn save(&self, pool: &PgPool, exchange: &str) -> Result<u64, MarketDataError> {
+        let mut tx = pool.begin().await?;
+        for info in self.symbols.values() {
+            // 同时启用了 bigdecimal 特性，NUMERIC 参数默认推断为 BigDecimal，需用 `as _` 传入 Decimal
+            sqlx::query!(
+                r#"
+                INSERT INTO symbols
+                (exchange, symbol, base_asset, quote_asset, status, tick_size, min_price, max_price,
+                 step_size, min_qty, max_qty, market_step_size, market_min_qty, market_max_qty, min_notional)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+                ON CONFLICT (exchange, symbol) DO UPDATE SET
+                    base_asset = EXCLUDED.base_asset,
+                    quote_asset = EXCLUDED.quote_asset,
+                    status = EXCLUDED.status,
+                    tick_size = EXCLUDED.tick_size,
+                    min_price = EXCLUDED.min_price,
+                    max_price = EXCLUDED.max_price,
+                    step_size = EXCLUDED.step_size,
+                    min_qty = EXCLUDED.min_qty,
+                    max_qty = EXCLUDED.max_qty,
+                    market_step_size = EXCLUDED.market_step_size,
+                    market_min_qty = EXCLUDED.market_min_qty,
+                    market_max_qty = EXCLUDED.market_max_qty,
+                    min_notional = EXCLUDED.min_notional,
+                    updated_at = NOW()
+                "#,
+                exchange,
+                info.symbol,
+                info.base_asset,
+                info.quote_asset,
+                info.status,
+                info.tick_size as _,
+                info.min_price as _,
+                info.max_price as _,
+                info.step_size as _,
+                info.min_qty as _,
+                info.max_qty as _,
+                info.market_step_size as _,
+                info.market_min_qty as _,
+                info.market_max_qty as _,
+                info.min_notional as _
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        info!("Saved {} {} symbols", self.symbols.len(), exchange);
+        Ok(self.symbols.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn btcusdt() -> SymbolInfo {
+        SymbolInfo {
+            symbol: "BTCUSDT".to_string(),
+            base_asset: "BTC".to_string(),
+            quote_asset: "USDT".to_string(),
+            status: "TRADING".to_string(),
+            tick_size: Decimal::from_str("0.01").unwrap(),
+            min_price: Decimal::from_str("0.01").unwrap(),
+            max_price: Decimal::from(1_000_000),
+            step_size: Decimal::from_str("0.00001").unwrap(),
+            min_qty: Decimal::from_str("0.00001").unwrap(),
+            max_qty: Decimal::from(9000),
+            market_step_size: Decimal::ZERO,
+            market_min_qty: Decimal::ZERO,
+            market_max_qty: Decimal::from_str("83.52391474").unwrap(),
+            min_notional: Decimal::from(5),
+        }
+    }
+
+    #[test]
+    fn test_normalize_and_lookup() {
+        let registry = SymbolRegistry::new(vec![btcusdt()]);
+        for name in ["BTCUSDT", "btcusdt", "BTC/USDT", "btc-usdt", "BTC_USDT"] {
+            assert_eq!(registry.normalize(name), Some("BTCUSDT"));
+        }
+        assert_eq!(registry.normalize("ETHUSDT"), None);
+        assert_eq!(registry.get("btc/usdt").unwrap().display_name(), "BTC/USDT");
+    }
+
+    #[test]
+    fn test_round_and_check_order() {
+        let info = btcusdt();
+        let d = |s: &str| Decimal::from_str(s).unwrap();
+
+        assert_eq!(info.round_price(d("37250.567")), d("37250.56"));
+        assert_eq!(info.round_quantity(d("0.0123456")), d("0.01234"));
+        assert!(info.check_order(d("37250.56"), d("0.01234")).is_ok());
+
+        assert!(matches!(
+            info.check_order(d("37250.567"), d("0.01234")),
+            Err(FilterViolation::InvalidTickSize { .. })
+        ));
+        assert!(matches!(
+            info.check_order(d("37250.56"), d("0.0123456")),
+            Err(FilterViolation::InvalidStepSize { .. })
+        ));
+        assert!(matches!(
+            info.check_order(d("37250.56"), d("0.0001")),
+            Err(FilterViolation::MinNotional { .. })
+        ));
+        assert!(matches!(
+            info.check_order(d("37250.56"), d("10000")),
+            Err(FilterViolation::QuantityOutOfRange { .. })
+        ));
+
+        let halted = SymbolInfo { status: "BREAK".to_string(), ..btcusdt() };
+        assert!(matches!(
+            halted.check_order(d("37250.56"), d("0.01234")),
+            Err(FilterViolation::NotTrading { .. })
+        ));
+        // 市价单不检查价格精度，数量按 MARKET_LOT_SIZE 检查
+        assert!(info.check_market_order(d("37250.567"), d("0.01234")).is_ok());
+        assert!(info.check_market_order(d("37250.56"), d("0.0123456")).is_ok());
+        assert!(info.check_order(d("37250.56"), d("100")).is_ok());
+        assert!(matches!(
+            info.check_market_order(d("37250.56"), d("100")),
+            Err(FilterViolation::QuantityOutOfRange { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load_per_exchange() {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL")
+            .expect("DATABASE_URL must be set for tests");
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&database_url)
+            .await
+            .expect("Failed to create test database pool");
+        let symbol = "TESTSYMBOLUSDT";
+        let cleanup = || sqlx::query!("DELETE FROM symbols WHERE symbol = $1", symbol).execute(&pool);
+        cleanup().await.unwrap();
+
+        // 同一交易对在两个交易所的规则分别保存
+        let binance = SymbolInfo { symbol: symbol.to_string(), ..btcusdt() };
+        let okx = SymbolInfo { tick_size: Decimal::from_str("0.1").unwrap(), ..binance.clone() };
+        SymbolRegistry::new(vec![binance.clone()]).save(&pool, "test_binance").await.unwrap();
+        SymbolRegistry::new(vec![okx.clone()]).save(&pool, "test_okx").await.unwrap();
+
+        let loaded = SymbolRegistry::load(&pool, "test_binance").await.unwrap();
+        assert_eq!(loaded.get(symbol), Some(&binance));
+        let loaded = SymbolRegistry::load(&pool, "test_okx").await.unwrap();
+        assert_eq!(loaded.get(symbol), Some(&okx));
+
+        cleanup().await.unwrap();
+    }
+}
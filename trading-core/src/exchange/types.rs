@@ -1,11 +1,16 @@
 // services/exchange/types.rs
-use crate::data::types::MarketDataPoint;
+use crate::data::types::{MarketDataPoint, TickData};
+use super::stream::{ConnectionState, MarketEvent, MarketStream, Topic};
+use super::symbols::SymbolInfo;
 use chrono::{DateTime, Utc};
+use reqwest::Url;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use futures_util::StreamExt;
 use thiserror::Error;
+use tokio::sync::mpsc;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum ExchangeError {
     #[error("API error: {0}")]
     ApiError(String),
@@ -19,6 +24,42 @@ pub enum ExchangeError {
     AuthError(String),
 }
 
+// 已接入的交易所，名称与 Exchange::name 一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExchangeKind {
+    Binance,
+    Okx,
+}
+
+impl ExchangeKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Binance => "binance",
+            Self::Okx => "okx",
+        }
+    }
+}
+
+impl std::str::FromStr for ExchangeKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "binance" => Ok(Self::Binance),
+            "okx" => Ok(Self::Okx),
+            other => Err(format!("unsupported exchange: {}", other)),
+        }
+    }
+}
+
+// REST 和 WebSocket 的基础地址，也可以指向本地的模拟服务器
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExchangeEndpoints {
+    pub rest_url: Url,
+    pub ws_url: Url,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderBookLevel {
     pub price: Decimal,
@@ -29,10 +70,32 @@ pub struct OrderBookLevel {
 pub struct OrderBook {
     pub symbol: String,
     pub timestamp: DateTime<Utc>,
+    // 快照对应的交易所更新序号，用于和增量深度流对齐
+    pub last_update_id: Option<u64>,
+    pub bids: Vec<OrderBookLevel>,
+    pub asks: Vec<OrderBookLevel>,
+}
+
+// 增量深度更新，first/final_update_id 对应 Binance 的 U/u
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepthUpdate {
+    pub symbol: String,
+    pub event_time: DateTime<Utc>,
+    pub first_update_id: u64,
+    pub final_update_id: u64,
     pub bids: Vec<OrderBookLevel>,
     pub asks: Vec<OrderBookLevel>,
 }
 
+// 实时 K 线；is_closed 为 false 时是尚未收盘、仍在变化的当前 K 线
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KlineUpdate {
+    pub symbol: String,
+    pub interval: String,
+    pub candle: MarketDataPoint,
+    pub is_closed: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ticker {
     pub symbol: String,
@@ -59,6 +122,9 @@ pub struct RecentTradesQuery {
 
 #[async_trait::async_trait]
 pub trait Exchange: Send + Sync {
+    /// 交易所名称，写入 tick_data 和 candles 的 exchange 列，例如 "binance"
+    fn name(&self) -> &'static str;
+
     /// 获取交易对的最新行情
     async fn get_ticker(&self, symbol: &str) -> Result<Ticker, ExchangeError>;
     
@@ -68,6 +134,18 @@ pub trait Exchange: Send + Sync {
     /// 获取最近的成交记录
     async fn get_recent_trades(&self, symbol: &str, limit: u32) -> Result<Vec<ExchangeTrade>, ExchangeError>;
     
+    /// 从指定成交 ID 开始获取历史成交（含 from_id），用于重连后补齐缺失的成交；
+    /// 返回的 trade_id 与 subscribe_trades 推送的一致
+    async fn get_historical_trades(
+        &self,
+        symbol: &str,
+        from_id: u64,
+        limit: u32,
+    ) -> Result<Vec<TickData>, ExchangeError>;
+    
+    /// 获取交易所全部交易对及其交易规则
+    async fn get_symbols(&self) -> Result<Vec<SymbolInfo>, ExchangeError>;
+    
     /// 获取K线数据
     async fn get_klines(
         &self,
@@ -78,10 +156,41 @@ pub trait Exchange: Send + Sync {
         limit: Option<u32>,
     ) -> Result<Vec<MarketDataPoint>, ExchangeError>;
     
-    /// 订阅实时市场数据
-    async fn subscribe_market_data(
+    /// 深度订阅是否先推送完整快照（`MarketEvent::DepthSnapshot`）。为 true 时本地订单簿
+    /// 由推送的快照初始化，出现断档后重新订阅深度，而不是拉取 REST 快照
+    fn streams_depth_snapshots(&self) -> bool {
+        false
+    }
+
+    /// 交易所强制断开 WebSocket 连接前允许的最长连接时间，没有限制时返回 None
+    fn max_connection_age(&self) -> Option<std::time::Duration> {
+        None
+    }
+
+    /// 打开行情订阅连接。返回的 MarketStream 按到达顺序产出各订阅的事件，
+    /// 之后可以在同一连接上增减订阅；连接结束时先推送 Closed 或 Failed 状态
+    fn stream(&self, topics: &[Topic]) -> Result<MarketStream, ExchangeError>;
+
+    /// 订阅逐笔成交数据，成交通过有界通道发送；接收端关闭时订阅结束
+    async fn subscribe_trades(
         &self,
         symbols: &[String],
-        callback: Box<dyn Fn(MarketDataPoint) + Send + Sync>,
-    ) -> Result<(), ExchangeError>;
-}
\ No newline at end of file
+        sender: mpsc::Sender<TickData>,
+    ) -> Result<(), ExchangeError> {
+        let topics: Vec<Topic> = symbols.iter().map(Topic::trades).collect();
+        let mut stream = self.stream(&topics)?;
+
+        // 通过有界通道发送，消费端处理不过来时这里会等待，形成背压
+        while let Some(event) = stream.next().await {
+            let tick = match event {
+                MarketEvent::Trade(tick) => tick,
+                MarketEvent::State(ConnectionState::Failed(e)) => return Err(e),
+                _ => continue,
+            };
+            if sender.send(tick).await.is_err() {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+}
@@ -10,5 +10,6 @@ pub mod state;
 pub mod config;
 pub mod backtest;
 pub mod exchange;
+pub mod risk;
 pub mod blockchain;
 pub mod market_data_collector;
\ No newline at end of file
@@ -1,16 +1,18 @@
 use bigdecimal::{FromPrimitive, Zero};
 use clap::{Parser, Subcommand};
 use dotenv::dotenv;
-use tracing::{info, error};
+use tracing::{info, warn, error};
 use std::sync::Arc;
 use std::str::FromStr;
-use chrono::{Duration, Utc};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use rust_decimal::Decimal;
 
 use trading_core::{
    backtest::{engine::BacktestEngine, sma::SMAStrategy, types::OrderSide, BacktestConfig}, 
-   config::Settings, data::{database::Database, types::MarketDataManager}, 
-   exchange::binance::BinanceSpot, market_data_collector::MarketDataCollector
+   config::Settings, data::{backfill::KlineBackfill, cache::MarketDataCache, database::Database, partition::PartitionManager, spool::TickSpool, transfer::{ExportFilter, FileFormat}, types::MarketDataManager}, 
+   exchange::{kline::LiveKlineManager, symbols::SymbolRegistry, types::{Exchange, ExchangeKind}},
+   market_data_collector::MarketDataCollector
 };
 
 #[derive(Parser)]
@@ -19,6 +21,9 @@ use trading_core::{
 struct Cli {
    #[command(subcommand)]
    command: Option<Commands>,
+   /// Exchange whose stored data the command reads and writes (binance or okx)
+   #[arg(long, global = true, default_value = "binance")]
+   exchange: ExchangeKind,
 }
 
 #[derive(Subcommand)]
@@ -39,9 +44,131 @@ enum Commands {
        short_period: usize,
        #[arg(long, default_value = "20")]
        long_period: usize,
+       /// Run on stored candles of this interval (e.g. 1h) instead of raw ticks
+       #[arg(long)]
+       interval: Option<String>,
+       /// Skip trades flagged by data quality validation
+       #[arg(long)]
+       exclude_flagged: bool,
+   },
+   /// Remove duplicate trades from tick_data and enforce the unique (exchange, symbol, trade_id, timestamp) index
+   Dedup,
+   /// Download historical klines into the candles table; resumes where the last run stopped
+   Backfill {
+       #[arg(short, long, default_value = "BTCUSDT")]
+       symbol: String,
+       #[arg(short, long, default_value = "1h")]
+       interval: String,
+       /// Start date, YYYY-MM-DD
+       #[arg(long)]
+       start: String,
+       /// End date, YYYY-MM-DD (defaults to now)
+       #[arg(long)]
+       end: Option<String>,
+   },
+   /// Rebuild stored candles of every interval from tick_data
+   RebuildCandles {
+       #[arg(short, long, default_value = "BTCUSDT")]
+       symbol: String,
+       #[arg(short, long, default_value = "30")]
+       days: i64,
+   },
+   /// Manage tick_data partitions and retention
+   Partitions {
+       #[command(subcommand)]
+       action: PartitionAction,
+   },
+   /// Export ticks, or candles of one interval, to a CSV or Parquet file
+   Export {
+       /// Export every symbol when omitted
+       #[arg(short, long)]
+       symbol: Option<String>,
+       /// Export stored candles of this interval (e.g. 1h) instead of raw ticks
+       #[arg(short, long)]
+       interval: Option<String>,
+       /// Start date, YYYY-MM-DD
+       #[arg(long)]
+       start: String,
+       /// End date, YYYY-MM-DD (defaults to now)
+       #[arg(long)]
+       end: Option<String>,
+       #[arg(short, long)]
+       output: PathBuf,
+       /// csv or parquet; inferred from the file extension by default
+       #[arg(long)]
+       format: Option<FileFormat>,
+   },
+   /// Import ticks or candles from a CSV or Parquet file; the kind is detected from its columns
+   Import {
+       input: PathBuf,
+       /// csv or parquet; inferred from the file extension by default
+       #[arg(long)]
+       format: Option<FileFormat>,
+   },
+   /// Manage the database schema
+   Db {
+       #[command(subcommand)]
+       action: DbAction,
+   },
+   /// Check stored trades for bad prices, timestamps, spikes and stale feeds and flag them
+   Validate {
+       #[arg(short, long, default_value = "BTCUSDT")]
+       symbol: String,
+       #[arg(short, long, default_value = "1")]
+       days: i64,
+       /// Report issues without recording them
+       #[arg(long)]
+       dry_run: bool,
+   },
+   /// Manage exchange symbol metadata and trading rules
+   Symbols {
+       #[command(subcommand)]
+       action: SymbolAction,
+   },
+   /// List holes in the stored trade data for a symbol
+   Gaps {
+       #[arg(short, long, default_value = "BTCUSDT")]
+       symbol: String,
+       #[arg(short, long, default_value = "1")]
+       days: i64,
+       /// Report gaps longer than this many seconds
+       #[arg(long, default_value = "60")]
+       min_gap_secs: f64,
    },
 }
 
+#[derive(Subcommand)]
+enum PartitionAction {
+   /// List existing tick_data partitions
+   List,
+   /// Create upcoming partitions and apply the retention policy
+   Maintain,
+}
+
+#[derive(Subcommand)]
+enum SymbolAction {
+   /// Fetch the exchange's symbols and trading rules and store them
+   Refresh,
+   /// Show the stored trading rules of a symbol
+   Show {
+       /// Accepts BTCUSDT, btcusdt or BTC/USDT
+       symbol: String,
+   },
+}
+
+#[derive(Subcommand)]
+enum DbAction {
+   /// Apply all pending schema migrations
+   Migrate,
+   /// Revert migrations newer than the target version (0 reverts everything)
+   Rollback {
+       #[arg(long)]
+       target: i64,
+   },
+   /// Show the current schema version and pending migrations
+   Status,
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
    // 加载环境变量和初始化日志
@@ -56,15 +183,45 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
    database.check_connection().await?;
    info!("Database connection established");
 
-   match Cli::parse().command.unwrap_or(Commands::Server) {
+   let cli = Cli::parse();
+   let exchange = cli.exchange;
+   let command = cli.command.unwrap_or(Commands::Server);
+   // db 子命令用于修复版本不一致，其余命令要求数据库结构与程序一致
+   if !matches!(command, Commands::Db { .. }) {
+       database.check_schema_version().await?;
+       // 从没有 candles 表的版本升级后，K 线查询在生成 K 线之前没有数据
+       if MarketDataManager::new(database.pool.clone()).candles_missing().await? {
+           warn!("candles table is empty but tick_data has trades; run `db migrate` to build candles from stored trades");
+       }
+   }
+
+   match command {
        Commands::Server => {
-           // 初始化交易所和数据收集器
-           let exchange = BinanceSpot::new(None);
-           let collector = Arc::new(MarketDataCollector::new(
-               Box::new(exchange),
+           // 初始化配置的各个交易所和数据收集器
+           let mut feeds = settings.collector.feeds.iter();
+           let first = feeds.next().ok_or("No exchanges configured in [[collector.feeds]]")?;
+           let mut collector = MarketDataCollector::new(
+               exchange_client(first.exchange, &settings)?,
                MarketDataManager::new(database.pool.clone()),
-               vec!["BTCUSDT".to_string()],
-           ));
+               first.symbols.clone(),
+           );
+           for feed in feeds {
+               collector = collector.with_exchange(exchange_client(feed.exchange, &settings)?, feed.symbols.clone());
+           }
+           let spool = TickSpool::open(
+               &settings.collector.spool_dir,
+               settings.collector.spool_segment_bytes,
+           )?;
+           let collector = Arc::new(
+               collector
+               .with_spool(spool)
+               .with_partitions(PartitionManager::new(
+                   database.pool.clone(),
+                   settings.partitioning.partition_config(),
+               ))
+               .with_quality(settings.quality.clone())
+               .with_reconnect(settings.collector.reconnect.clone()),
+           );
 
            // 启动收集器
            let collector_clone = collector.clone();
@@ -74,13 +231,47 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                }
            });
 
+           // 配置了 kline_intervals 的交易所另外订阅实时 K 线，未收盘的 K 线推送到共享的行情缓存
+           let cache = Arc::new(MarketDataCache::new(
+               settings.collector.feeds.iter().map(|feed| feed.symbols.len()).sum(),
+           ));
+           let mut kline_managers = Vec::new();
+           for feed in settings.collector.feeds.iter().filter(|feed| !feed.kline_intervals.is_empty()) {
+               let manager = Arc::new(
+                   LiveKlineManager::new(
+                       Arc::from(exchange_client(feed.exchange, &settings)?),
+                       MarketDataManager::new(database.pool.clone()),
+                       &feed.symbols,
+                       &feed.kline_intervals,
+                   )
+                   .with_cache(cache.clone())
+                   .with_reconnect(settings.collector.reconnect.clone()),
+               );
+               let runner = manager.clone();
+               kline_managers.push((manager, tokio::spawn(async move { runner.run().await })));
+           }
+
            info!("Market data collector started. Press Ctrl+C to stop.");
 
-           // 等待中断信号
-           tokio::signal::ctrl_c().await?;
+           // 等待中断信号，期间定期输出采集器健康状态
+           let mut health_interval = tokio::time::interval(std::time::Duration::from_secs(60));
+           loop {
+               tokio::select! {
+                   result = tokio::signal::ctrl_c() => {
+                       result?;
+                       break;
+                   }
+                   _ = health_interval.tick() => log_collector_health(&collector),
+               }
+           }
            info!("Shutting down server...");
            collector.stop();
+           for (manager, kline_handle) in kline_managers {
+               manager.stop();
+               kline_handle.await?;
+           }
            handle.await?;
+           log_collector_health(&collector);
            info!("Server shutdown complete");
        }
 
@@ -91,15 +282,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
            commission_rate,
            short_period,
            long_period,
+           interval,
+           exclude_flagged,
        } => {
-           let market_data = MarketDataManager::new(database.pool);
+           // 统一交易对写法，并按交易所规则取整和检查订单
+           let registry = SymbolRegistry::load(&database.pool, exchange.name()).await?;
+           let symbol_info = registry.get(&symbol).cloned();
+           let symbol = match &symbol_info {
+               Some(info) => info.symbol.clone(),
+               None => {
+                   warn!("No symbol metadata for {}, run `symbols refresh` to enforce exchange rules", symbol);
+                   symbol
+               }
+           };
+           let market_data = MarketDataManager::new(database.pool).with_exchange(exchange.name());
            
            // 设置回测时间范围
            let start_time = Utc::now() - Duration::days(days);
            let end_time = Utc::now();
            
            // 检查数据可用性
-           let data = market_data.get_market_data(&symbol, start_time, end_time).await?;
+           let data = match &interval {
+               Some(interval) => market_data.get_candles(&symbol, interval, start_time, end_time).await?,
+               None if exclude_flagged => market_data.get_clean_market_data(&symbol, start_time, end_time).await?,
+               None => market_data.get_market_data(&symbol, start_time, end_time).await?,
+           };
            if data.is_empty() {
                error!("No historical data found for {} in the specified time range", symbol);
                return Err("Insufficient historical data for backtest".into());
@@ -113,6 +320,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                initial_capital: Decimal::from_str(&initial_capital)?,
                symbol: symbol.clone(),
                commission_rate: Decimal::from_str(&commission_rate)?,
+               interval,
+               exclude_flagged,
            };
 
            // 创建策略实例
@@ -133,6 +342,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
            // 运行回测
            let mut engine = BacktestEngine::new(market_data, config);
+           if let Some(info) = symbol_info {
+               engine = engine.with_symbol_info(info);
+           }
            let result = engine.run_strategy(Box::new(strategy)).await?;
 
            // 打印回测结果
@@ -153,7 +365,212 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                );
            }
        }
+
+       Commands::Dedup => {
+           let market_data = MarketDataManager::new(database.pool).with_exchange(exchange.name());
+           let removed = market_data.remove_duplicate_ticks().await?;
+           println!("Removed {} duplicate ticks", removed);
+       }
+
+       Commands::Backfill { symbol, interval, start, end } => {
+           let start_time = parse_date(&start)?;
+           let end_time = match end {
+               Some(end) => parse_date(&end)?,
+               None => Utc::now(),
+           };
+
+           let backfill = KlineBackfill::new(
+               Arc::from(exchange_client(exchange, &settings)?),
+               MarketDataManager::new(database.pool),
+           );
+           let report = backfill.run(&symbol, &interval, start_time, end_time).await?;
+           if let Some(resumed_from) = report.resumed_from {
+               println!("Resumed from {}", resumed_from.format("%Y-%m-%d %H:%M:%S"));
+           }
+           println!(
+               "Stored {} {} candles for {} in {} requests",
+               report.candles, interval, symbol, report.requests
+           );
+       }
+
+       Commands::RebuildCandles { symbol, days } => {
+           let market_data = MarketDataManager::new(database.pool).with_exchange(exchange.name());
+           let end_time = Utc::now();
+           let start_time = end_time - Duration::days(days);
+           let updated = market_data
+               .update_candles_from_ticks(&symbol, start_time, end_time)
+               .await?;
+           println!("Rebuilt {} 1m candles for {} and rolled them up", updated, symbol);
+       }
+
+       Commands::Partitions { action } => {
+           let partitions = PartitionManager::new(
+               database.pool,
+               settings.partitioning.partition_config(),
+           );
+           match action {
+               PartitionAction::List => {
+                   for partition in partitions.list_partitions().await? {
+                       println!(
+                           "{}: {} -> {}",
+                           partition.name,
+                           partition.start.format("%Y-%m-%d"),
+                           partition.end.format("%Y-%m-%d")
+                       );
+                   }
+               }
+               PartitionAction::Maintain => {
+                   let report = partitions.maintain(Utc::now()).await?;
+                   println!(
+                       "Dropped {} partitions {:?}, deleted {} expired rows",
+                       report.dropped_partitions.len(),
+                       report.dropped_partitions,
+                       report.deleted_rows
+                   );
+               }
+           }
+       }
+
+       Commands::Export { symbol, interval, start, end, output, format } => {
+           let format = resolve_format(&output, format)?;
+           let filter = ExportFilter {
+               symbol,
+               start: parse_date(&start)?,
+               end: match end {
+                   Some(end) => parse_date(&end)?,
+                   None => Utc::now(),
+               },
+           };
+           let market_data = MarketDataManager::new(database.pool).with_exchange(exchange.name());
+           let rows = match &interval {
+               Some(interval) => market_data.export_candles(&output, format, interval, &filter).await?,
+               None => market_data.export_ticks(&output, format, &filter).await?,
+           };
+           println!("Exported {} rows to {}", rows, output.display());
+       }
+
+       Commands::Import { input, format } => {
+           let format = resolve_format(&input, format)?;
+           let market_data = MarketDataManager::new(database.pool).with_exchange(exchange.name());
+           let report = market_data.import_file(&input, format, Some(&settings.quality)).await?;
+           println!(
+               "Imported {} {:?} rows from {} ({} stored)",
+               report.rows, report.kind, input.display(), report.stored
+           );
+       }
+
+       Commands::Db { action } => match action {
+           DbAction::Migrate => {
+               database.migrate().await?;
+               // 升级后只执行一次：candles 为空时由已有成交生成 K 线
+               let market_data = MarketDataManager::new(database.pool.clone());
+               if market_data.candles_missing().await? {
+                   let updated = market_data.materialize_candles().await?;
+                   println!("Built {} 1m candles from stored trades", updated);
+               }
+           }
+           DbAction::Rollback { target } => database.rollback(target).await?,
+           DbAction::Status => {
+               let status = database.schema_status().await?;
+               let current = status
+                   .current
+                   .map_or_else(|| "none".to_string(), |version| version.to_string());
+               println!("Schema version: {} (latest {})", current, status.expected);
+               if let Some(version) = status.dirty {
+                   println!("Migration {} is dirty", version);
+               }
+               println!("Pending migrations: {:?}", status.pending);
+           }
+       },
+
+       Commands::Symbols { action } => match action {
+           SymbolAction::Refresh => {
+               let registry = SymbolRegistry::fetch(exchange_client(exchange, &settings)?.as_ref()).await?;
+               let saved = registry.save(&database.pool, exchange.name()).await?;
+               println!("Stored {} {} symbols", saved, exchange.name());
+           }
+           SymbolAction::Show { symbol } => {
+               let registry = SymbolRegistry::load(&database.pool, exchange.name()).await?;
+               let info = registry
+                   .get(&symbol)
+                   .ok_or_else(|| format!("Unknown symbol {}, run `symbols refresh` first", symbol))?;
+               println!("\n{} ({}) - {}", info.symbol, info.display_name(), info.status);
+               println!("Price:    tick {} in [{}, {}]", info.tick_size, info.min_price, info.max_price);
+               println!("Quantity: step {} in [{}, {}]", info.step_size, info.min_qty, info.max_qty);
+               println!("Market:   step {} in [{}, {}]", info.market_step_size, info.market_min_qty, info.market_max_qty);
+               println!("Min notional: {}", info.min_notional);
+           }
+       },
+
+       Commands::Validate { symbol, days, dry_run } => {
+           let market_data = MarketDataManager::new(database.pool).with_exchange(exchange.name());
+           let end_time = Utc::now();
+           let start_time = end_time - Duration::days(days);
+           let report = market_data
+               .validate_stored_ticks(&symbol, start_time, end_time, &settings.quality, !dry_run)
+               .await?;
+
+           println!("\nChecked {} trades for {} in the last {} days:", report.checked, symbol, days);
+           for (kind, count) in &report.flagged {
+               println!("{}: {}", kind.as_str(), count);
+           }
+           if report.flagged.is_empty() {
+               println!("No issues found");
+           } else if dry_run {
+               println!("Dry run, no flags recorded");
+           }
+       }
+
+       Commands::Gaps { symbol, days, min_gap_secs } => {
+           let market_data = MarketDataManager::new(database.pool).with_exchange(exchange.name());
+           let end_time = Utc::now();
+           let start_time = end_time - Duration::days(days);
+           let gaps = market_data
+               .find_tick_gaps(&symbol, start_time, end_time, min_gap_secs)
+               .await?;
+
+           println!("\nGaps longer than {}s for {} in the last {} days:", min_gap_secs, symbol, days);
+           for gap in &gaps {
+               let missing = gap
+                   .missing_trades
+                   .map_or_else(|| "unknown".to_string(), |count| count.to_string());
+               println!(
+                   "{} -> {} ({}s, missing trades: {})",
+                   gap.start.format("%Y-%m-%d %H:%M:%S"),
+                   gap.end.format("%Y-%m-%d %H:%M:%S"),
+                   (gap.end - gap.start).num_seconds(),
+                   missing
+               );
+           }
+           println!("Total: {} gaps", gaps.len());
+       }
    }
 
    Ok(())
+}
+
+fn parse_date(date: &str) -> Result<DateTime<Utc>, chrono::ParseError> {
+   let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")?;
+   Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+}
+
+// 按配置的接入环境创建 Binance 客户端
+fn exchange_client(kind: ExchangeKind, settings: &Settings) -> Result<Box<dyn Exchange>, config::ConfigError> {
+   settings.exchange.client(kind)
+}
+
+fn resolve_format(path: &Path, format: Option<FileFormat>) -> Result<FileFormat, String> {
+   format
+       .or_else(|| FileFormat::from_path(path))
+       .ok_or_else(|| format!("Cannot infer file format of {}, pass --format", path.display()))
+}
+
+fn log_collector_health(collector: &MarketDataCollector) {
+   let health = collector.health();
+   let stats = &health.stats;
+   info!(
+       "Collector health: received={}, stored={}, duplicates={}, failed={}, spooled={}, replayed={}, backfilled={}, spool_depth={} ({} segments)",
+       stats.received, stats.stored, stats.duplicates, stats.failed,
+       stats.spooled, stats.replayed, stats.backfilled, health.spool_depth, health.spool_segments
+   );
 }
\ No newline at end of file
@@ -1,18 +1,79 @@
-use crate::data::types::{MarketDataManager, MarketDataPoint};
+use crate::data::batch_writer::{replay_spool, BatchWriterConfig, FlushOutcome, TickBatchWriter};
+use crate::data::partition::PartitionManager;
+use crate::data::quality::{QualityConfig, TickValidator};
+use crate::data::spool::TickSpool;
+use crate::data::types::{MarketDataManager, TickData};
+use crate::exchange::reconnect::{Backoff, ReconnectConfig};
+use crate::exchange::stream::{ConnectionState, MarketEvent, MarketStream, Topic};
+use crate::exchange::symbols::normalize_symbol;
 use crate::exchange::types::{Exchange, ExchangeError};
+use futures_util::StreamExt;
 use tokio::sync::{broadcast, mpsc};
-use tokio::time::{sleep, Duration};
+use tokio::time::{sleep, Duration, Instant};
 use tracing::{error, info, warn};
-use std::sync::Arc;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
-const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+// 轮换连接时新连接建立失败后的重试间隔
+const ROTATE_RETRY_DELAY: Duration = Duration::from_secs(30);
+const SPOOL_REPLAY_INTERVAL: Duration = Duration::from_secs(5);
+const PARTITION_MAINTENANCE_INTERVAL: Duration = Duration::from_secs(3600);
+// 补数据每页 1000 笔（交易所上限），单次最多补 100 页
+const BACKFILL_PAGE_SIZE: u32 = 1000;
+const MAX_BACKFILL_PAGES: usize = 100;
 const CHANNEL_BUFFER_SIZE: usize = 1000;
 
-pub struct MarketDataCollector {
+#[derive(Debug, Default)]
+struct CollectorCounters {
+    received: AtomicU64,
+    stored: AtomicU64,
+    duplicates: AtomicU64,
+    failed: AtomicU64,
+    spooled: AtomicU64,
+    replayed: AtomicU64,
+    backfilled: AtomicU64,
+}
+
+// 采集统计：duplicates 为因 (exchange, symbol, trade_id, timestamp) 已存在而被丢弃的成交数，
+// spooled 为数据库不可用时落盘的成交数，replayed 为从落盘回放写入数据库的成交数，
+// backfilled 为重连后通过 REST 补写的成交数
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectorStats {
+    pub received: u64,
+    pub stored: u64,
+    pub duplicates: u64,
+    pub failed: u64,
+    pub spooled: u64,
+    pub replayed: u64,
+    pub backfilled: u64,
+}
+
+// 采集器健康状态：spool_depth 为落盘中等待回放的成交数
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectorHealth {
+    pub stats: CollectorStats,
+    pub spool_depth: u64,
+    pub spool_segments: usize,
+}
+
+// 一个交易所及其订阅的交易对
+struct Feed {
     exchange: Arc<Box<dyn Exchange>>,
-    market_data_manager: Arc<MarketDataManager>,
     symbols: Vec<String>,
+}
+
+pub struct MarketDataCollector {
+    feeds: Vec<Feed>,
+    market_data_manager: Arc<MarketDataManager>,
     shutdown_tx: broadcast::Sender<()>,
+    counters: Arc<CollectorCounters>,
+    batch_config: BatchWriterConfig,
+    spool: Option<Arc<Mutex<TickSpool>>>,
+    partitions: Option<Arc<PartitionManager>>,
+    quality: Option<QualityConfig>,
+    reconnect: ReconnectConfig,
 }
 
 impl MarketDataCollector {
@@ -23,84 +84,217 @@ impl MarketDataCollector {
     ) -> Self {
         let (shutdown_tx, _) = broadcast::channel(1);
         Self {
-            exchange: Arc::new(exchange),
+            feeds: vec![Feed {
+                exchange: Arc::new(exchange),
+                symbols,
+            }],
             market_data_manager: Arc::new(market_data_manager),
-            symbols,
             shutdown_tx,
+            counters: Arc::new(CollectorCounters::default()),
+            batch_config: BatchWriterConfig::default(),
+            spool: None,
+            partitions: None,
+            quality: None,
+            reconnect: ReconnectConfig::default(),
+        }
+    }
+
+    /// 同时采集另一个交易所的交易对，所有交易所的成交共用一个写入任务
+    pub fn with_exchange(mut self, exchange: Box<dyn Exchange>, symbols: Vec<String>) -> Self {
+        self.feeds.push(Feed {
+            exchange: Arc::new(exchange),
+            symbols,
+        });
+        self
+    }
+
+    pub fn with_batch_config(mut self, batch_config: BatchWriterConfig) -> Self {
+        self.batch_config = batch_config;
+        self
+    }
+
+    /// 数据库写入失败时落盘到 spool，数据库恢复后按顺序回放
+    pub fn with_spool(mut self, spool: TickSpool) -> Self {
+        self.spool = Some(Arc::new(Mutex::new(spool)));
+        self
+    }
+
+    /// 启动时及之后每小时创建未来分区并执行保留策略
+    pub fn with_partitions(mut self, partitions: PartitionManager) -> Self {
+        self.partitions = Some(Arc::new(partitions));
+        self
+    }
+
+    /// 写入前检查数据质量，问题成交记录到 tick_flags
+    pub fn with_quality(mut self, config: QualityConfig) -> Self {
+        self.quality = Some(config);
+        self
+    }
+
+    /// 重连退避、行情中断检测和连接轮换的参数
+    pub fn with_reconnect(mut self, config: ReconnectConfig) -> Self {
+        self.reconnect = config;
+        self
+    }
+
+    pub fn stats(&self) -> CollectorStats {
+        CollectorStats {
+            received: self.counters.received.load(Ordering::Relaxed),
+            stored: self.counters.stored.load(Ordering::Relaxed),
+            duplicates: self.counters.duplicates.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+            spooled: self.counters.spooled.load(Ordering::Relaxed),
+            replayed: self.counters.replayed.load(Ordering::Relaxed),
+            backfilled: self.counters.backfilled.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn health(&self) -> CollectorHealth {
+        let (spool_depth, spool_segments) = match &self.spool {
+            Some(spool) => {
+                let spool = spool.lock().unwrap_or_else(|e| e.into_inner());
+                (spool.depth(), spool.segment_count())
+            }
+            None => (0, 0),
+        };
+        CollectorHealth {
+            stats: self.stats(),
+            spool_depth,
+            spool_segments,
         }
     }
     
     pub async fn start(&self) -> Result<(), ExchangeError> {
-        info!("Starting market data collection for symbols: {:?}", self.symbols);
+        for feed in &self.feeds {
+            info!(
+                "Starting {} market data collection for symbols: {:?}",
+                feed.exchange.name(), feed.symbols
+            );
+        }
+
+        // 写入前先确保当前和未来的分区存在
+        if let Some(partitions) = &self.partitions {
+            if let Err(e) = partitions.maintain(chrono::Utc::now()).await {
+                error!("Partition maintenance failed: {}", e);
+            }
+        }
         
         // 创建数据通道
-        let (data_tx, mut data_rx) = mpsc::channel::<MarketDataPoint>(CHANNEL_BUFFER_SIZE);
-        let mut shutdown_rx = self.shutdown_tx.subscribe();
+        let (data_tx, data_rx) = mpsc::channel::<TickData>(CHANNEL_BUFFER_SIZE);
+        let mut replay_shutdown_rx = self.shutdown_tx.subscribe();
+        let mut maintenance_shutdown_rx = self.shutdown_tx.subscribe();
         
         // 克隆需要的变量用于异步任务
-        let exchange = self.exchange.clone();
-        let symbols = self.symbols.clone();
         let market_data_manager = self.market_data_manager.clone();
+        let counters = self.counters.clone();
+        
+        // 每个交易所一个 WebSocket 订阅任务，成交写入同一个通道
+        let mut handles = Vec::with_capacity(self.feeds.len() + 3);
+        for feed in &self.feeds {
+            let exchange = feed.exchange.clone();
+            let symbols = feed.symbols.clone();
+            let data_tx = data_tx.clone();
+            let shutdown_rx = self.shutdown_tx.subscribe();
+            // 补数据按交易所查询最后一笔成交
+            let backfill_manager = Arc::new(
+                market_data_manager.as_ref().clone().with_exchange(exchange.name()),
+            );
+            let backfill_counters = counters.clone();
+            let backfill_quality = self.quality.clone();
+
+            let feed_stream = FeedStream {
+                exchange,
+                symbols,
+                config: self.reconnect.clone(),
+                data_tx,
+            };
+            handles.push(tokio::spawn(feed_stream.run(
+                backfill_manager,
+                backfill_counters,
+                backfill_quality,
+                shutdown_rx,
+            )));
+        }
+        // 所有订阅任务结束后通道关闭，写入任务随之退出
+        drop(data_tx);
         
-        // 启动WebSocket订阅任务
-        let subscription_handle = tokio::spawn(async move {
+        // 启动 spool 回放任务：数据库恢复后按写入顺序补写落盘数据
+        let replay_spool_handle = self.spool.clone();
+        let replay_manager = market_data_manager.clone();
+        let replay_counters = counters.clone();
+        let mut replay_validator = self.quality.clone().map(TickValidator::new);
+        handles.push(tokio::spawn(async move {
+            let Some(spool) = replay_spool_handle else {
+                return;
+            };
             loop {
-                let callback = {
-                    let data_tx = data_tx.clone();
-                    Box::new(move |data: MarketDataPoint| {
-                        let data_tx = data_tx.clone();
-                        tokio::spawn(async move {
-                            if let Err(e) = data_tx.send(data).await {
-                                error!("Failed to send market data through channel: {}", e);
-                            }
-                        });
-                    })
-                };
-                
-                match exchange.subscribe_market_data(&symbols, callback).await {
-                    Ok(()) => {
-                        info!("Successfully subscribed to market data");
-                    }
-                    Err(e) => {
-                        error!("Failed to subscribe to market data: {}", e);
-                        sleep(RECONNECT_DELAY).await;
-                        continue;
+                tokio::select! {
+                    _ = replay_shutdown_rx.recv() => break,
+                    _ = sleep(SPOOL_REPLAY_INTERVAL) => {}
+                }
+
+                let depth = spool.lock().unwrap_or_else(|e| e.into_inner()).depth();
+                if depth == 0 {
+                    continue;
+                }
+                match replay_spool(&replay_manager, &spool, replay_validator.as_mut()).await {
+                    Ok(stored) => {
+                        replay_counters.replayed.fetch_add(stored, Ordering::Relaxed);
+                        info!("Replayed {} spooled trades ({} stored)", depth, stored);
                     }
+                    Err(e) => warn!("Spool replay failed, {} trades still pending: {}", depth, e),
                 }
-                
-                // 等待关闭信号或重连
+            }
+        }));
+
+        // 启动分区维护任务
+        let partitions = self.partitions.clone();
+        handles.push(tokio::spawn(async move {
+            let Some(partitions) = partitions else {
+                return;
+            };
+            loop {
                 tokio::select! {
-                    _ = shutdown_rx.recv() => {
-                        info!("Received shutdown signal, stopping subscription");
-                        break;
-                    }
-                    _ = sleep(Duration::from_secs(60)) => {
-                        warn!("WebSocket connection timeout, reconnecting...");
-                        continue;
-                    }
+                    _ = maintenance_shutdown_rx.recv() => break,
+                    _ = sleep(PARTITION_MAINTENANCE_INTERVAL) => {}
+                }
+                if let Err(e) = partitions.maintain(chrono::Utc::now()).await {
+                    error!("Partition maintenance failed: {}", e);
                 }
             }
-        });
-        
+        }));
+
         // 启动数据处理任务
-        let processing_handle = tokio::spawn(async move {
-            while let Some(data) = data_rx.recv().await {
-                match market_data_manager.store_market_data(&data).await {
-                    Ok(()) => {
-                        info!(
-                            "Successfully stored market data: symbol={}, price={}, volume={}", 
-                            data.symbol, data.price, data.volume
-                        );
-                    }
-                    Err(e) => {
-                        error!("Failed to store market data: {}", e);
-                    }
+        let mut writer = TickBatchWriter::new(market_data_manager, self.batch_config);
+        if let Some(spool) = &self.spool {
+            writer = writer.with_spool(spool.clone());
+        }
+        if let Some(config) = &self.quality {
+            writer = writer.with_validator(TickValidator::new(config.clone()));
+        }
+        handles.push(tokio::spawn(writer.run(data_rx, move |batch, outcome| {
+            let received = batch.len() as u64;
+            counters.received.fetch_add(received, Ordering::Relaxed);
+            match outcome {
+                FlushOutcome::Stored(stored) => {
+                    counters.stored.fetch_add(stored, Ordering::Relaxed);
+                    counters.duplicates.fetch_add(received - stored, Ordering::Relaxed);
+                    info!("Stored {} trades, dropped {} duplicates", stored, received - stored);
+                }
+                FlushOutcome::Spooled => {
+                    counters.spooled.fetch_add(received, Ordering::Relaxed);
+                    warn!("Spooled {} trades while the database is unavailable", received);
+                }
+                FlushOutcome::Failed(e) => {
+                    counters.failed.fetch_add(received, Ordering::Relaxed);
+                    error!("Failed to store {} trades: {}", received, e);
                 }
             }
-        });
+        })));
         
         // 等待任务完成
-        tokio::try_join!(subscription_handle, processing_handle)
+        futures_util::future::try_join_all(handles)
+            .await
             .map_err(|e| ExchangeError::NetworkError(e.to_string()))?;
         
         Ok(())
@@ -112,14 +306,551 @@ impl MarketDataCollector {
     }
 }
 
+// 一个交易所的成交订阅：断线后按退避重连；行情中断的交易对在原连接上重新订阅；
+// 接近交易所的连接时长上限时先建立新连接，新连接收到数据后再关闭旧连接。
+// 重叠期间两个连接推送的相同成交由 (exchange, symbol, trade_id, timestamp) 唯一索引去重
+struct FeedStream {
+    exchange: Arc<Box<dyn Exchange>>,
+    symbols: Vec<String>,
+    config: ReconnectConfig,
+    data_tx: mpsc::Sender<TickData>,
+}
+
+impl FeedStream {
+    async fn run(
+        self,
+        backfill_manager: Arc<MarketDataManager>,
+        counters: Arc<CollectorCounters>,
+        quality: Option<QualityConfig>,
+        shutdown_rx: broadcast::Receiver<()>,
+    ) {
+        // 补数据在单独的任务中按顺序执行，同一交易对同时只有一个补数据在进行；
+        // 关闭时取消未完成的补数据，下次启动会从数据库中的最后一笔成交重新补
+        let (backfill_tx, backfill_rx) = mpsc::unbounded_channel();
+        let backfill = tokio::spawn(backfill_gaps(
+            self.exchange.clone(),
+            backfill_manager.clone(),
+            counters,
+            quality,
+            backfill_rx,
+        ));
+        self.subscribe(&backfill_manager, backfill_tx, shutdown_rx).await;
+        backfill.abort();
+    }
+
+    async fn subscribe(
+        &self,
+        manager: &MarketDataManager,
+        backfill_tx: mpsc::UnboundedSender<HashMap<String, u64>>,
+        mut shutdown_rx: broadcast::Receiver<()>,
+    ) {
+        let name = self.exchange.name();
+        let mut backoff = self.config.backoff();
+        // 各交易对最后一笔成交 ID：先取数据库中的最后一笔，之后取本连接转发的最后一笔。
+        // 在连接前确定起点，避免新连接写入的成交被当作补数据的起点
+        let mut last_trade_ids = last_stored_trade_ids(manager, &self.symbols).await;
+        loop {
+            // 每次（重新）订阅时补齐断线期间缺失的成交
+            if !last_trade_ids.is_empty() {
+                let _ = backfill_tx.send(last_trade_ids.clone());
+            }
+
+            // 订阅期间也要响应关闭信号，否则连接保持时无法停止
+            let result = tokio::select! {
+                result = self.run_connection(&mut backoff, &mut last_trade_ids) => result,
+                _ = shutdown_rx.recv() => {
+                    info!("Received shutdown signal, stopping {} subscription", name);
+                    return;
+                }
+            };
+            let Err(e) = result else {
+                info!("Trade channel closed, stopping {} subscription", name);
+                return;
+            };
+
+            let delay = backoff.next_delay();
+            error!(
+                "{} market data connection lost: {}, reconnecting in {:?} (attempt {})",
+                name, e, delay, backoff.attempt()
+            );
+            tokio::select! {
+                _ = sleep(delay) => {}
+                _ = shutdown_rx.recv() => {
+                    info!("Received shutdown signal, stopping {} subscription", name);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn topics(&self) -> Vec<Topic> {
+        self.symbols.iter().map(Topic::trades).collect()
+    }
+
+    // 运行到需要重连时返回 Err；成交通道关闭时返回 Ok。转发的成交 ID 记录到 last_trade_ids
+    async fn run_connection(
+        &self,
+        backoff: &mut Backoff,
+        last_trade_ids: &mut HashMap<String, u64>,
+    ) -> Result<(), ExchangeError> {
+        let name = self.exchange.name();
+        let stale_after = self.config.stale_after();
+        let rotate_after = self.config.rotate_after(self.exchange.max_connection_age());
+
+        let mut active = self.exchange.stream(&self.topics())?;
+        let mut standby: Option<MarketStream> = None;
+        let mut rotate_at = rotate_after.map(|after| Instant::now() + after);
+        let mut watchdog = StaleWatchdog::new(&self.symbols, Instant::now());
+        let mut check = tokio::time::interval((stale_after / 2).max(Duration::from_secs(1)));
+
+        loop {
+            tokio::select! {
+                event = active.next() => match event {
+                    Some(MarketEvent::Trade(tick)) => {
+                        if !self.forward(tick, &mut watchdog, backoff, last_trade_ids).await {
+                            return Ok(());
+                        }
+                    }
+                    Some(MarketEvent::State(state)) if !state.is_terminal() => {}
+                    Some(MarketEvent::State(state)) => {
+                        let reason = match state {
+                            ConnectionState::Failed(e) => e,
+                            _ => ExchangeError::NetworkError("Connection closed".to_string()),
+                        };
+                        // 轮换中旧连接提前断开时直接切换到新连接
+                        let Some(next) = standby.take() else {
+                            return Err(reason);
+                        };
+                        warn!("{} connection ended during rotation ({}), switching to the new connection", name, reason);
+                        active = next;
+                        rotate_at = rotate_after.map(|after| Instant::now() + after);
+                    }
+                    Some(_) => {}
+                    None => return Err(ExchangeError::NetworkError("Stream ended".to_string())),
+                },
+                event = next_event(&mut standby), if standby.is_some() => match event {
+                    Some(MarketEvent::Trade(tick)) => {
+                        // 新连接已经在推送数据，关闭旧连接
+                        let next = standby.take().expect("standby connection is set");
+                        let old = std::mem::replace(&mut active, next);
+                        old.close();
+                        tokio::spawn(async move {
+                            let mut old = old;
+                            while old.next().await.is_some() {}
+                        });
+                        rotate_at = rotate_after.map(|after| Instant::now() + after);
+                        info!("Rotated {} market data connection", name);
+
+                        if !self.forward(tick, &mut watchdog, backoff, last_trade_ids).await {
+                            return Ok(());
+                        }
+                    }
+                    Some(MarketEvent::State(state)) if !state.is_terminal() => {}
+                    Some(MarketEvent::State(_)) | None => {
+                        warn!("Replacement {} connection failed, retrying in {:?}", name, ROTATE_RETRY_DELAY);
+                        standby = None;
+                        rotate_at = Some(Instant::now() + ROTATE_RETRY_DELAY);
+                    }
+                    Some(_) => {}
+                },
+                _ = tokio::time::sleep_until(rotate_at.unwrap_or_else(Instant::now)), if rotate_at.is_some() && standby.is_none() => {
+                    info!("Opening a replacement {} connection before the connection age limit", name);
+                    match self.exchange.stream(&self.topics()) {
+                        Ok(stream) => standby = Some(stream),
+                        Err(e) => {
+                            warn!("Failed to open replacement {} connection: {}", name, e);
+                            rotate_at = Some(Instant::now() + ROTATE_RETRY_DELAY);
+                        }
+                    }
+                }
+                _ = check.tick() => {
+                    let now = Instant::now();
+                    let stale = watchdog.stale(now, stale_after);
+                    if !stale.is_empty() && stale.len() == watchdog.len() {
+                        return Err(ExchangeError::NetworkError(format!(
+                            "No trades for {:?} on any symbol", stale_after
+                        )));
+                    }
+                    for symbol in stale {
+                        warn!("No {} trades for {} in {:?}, resubscribing", name, symbol, stale_after);
+                        let topic = [Topic::trades(symbol.as_str())];
+                        active.unsubscribe(&topic).await?;
+                        active.subscribe(&topic).await?;
+                        watchdog.record(&symbol, now);
+                    }
+                }
+            }
+        }
+    }
+
+    // 转发成交并更新行情中断检测；成交通道关闭时返回 false
+    async fn forward(
+        &self,
+        tick: TickData,
+        watchdog: &mut StaleWatchdog,
+        backoff: &mut Backoff,
+        last_trade_ids: &mut HashMap<String, u64>,
+    ) -> bool {
+        watchdog.record(&tick.symbol, Instant::now());
+        backoff.reset();
+        if let Ok(trade_id) = tick.trade_id.parse::<u64>() {
+            let last = last_trade_ids.entry(tick.symbol.clone()).or_insert(trade_id);
+            *last = (*last).max(trade_id);
+        }
+        self.data_tx.send(tick).await.is_ok()
+    }
+}
+
+async fn next_event(stream: &mut Option<MarketStream>) -> Option<MarketEvent> {
+    match stream {
+        Some(stream) => stream.next().await,
+        None => std::future::pending().await,
+    }
+}
+
+// 各交易对最后一次收到成交的时间
+struct StaleWatchdog {
+    last_seen: HashMap<String, Instant>,
+}
+
+impl StaleWatchdog {
+    fn new(symbols: &[String], now: Instant) -> Self {
+        Self {
+            last_seen: symbols.iter().map(|symbol| (normalize_symbol(symbol), now)).collect(),
+        }
+    }
+
+    fn record(&mut self, symbol: &str, now: Instant) {
+        if let Some(last_seen) = self.last_seen.get_mut(symbol) {
+            *last_seen = now;
+        }
+    }
+
+    // 超过 stale_after 没有成交的交易对
+    fn stale(&self, now: Instant, stale_after: Duration) -> Vec<String> {
+        let mut stale: Vec<String> = self
+            .last_seen
+            .iter()
+            .filter(|(_, last_seen)| now.duration_since(**last_seen) >= stale_after)
+            .map(|(symbol, _)| symbol.clone())
+            .collect();
+        stale.sort();
+        stale
+    }
+
+    fn len(&self) -> usize {
+        self.last_seen.len()
+    }
+}
+
+// 数据库中各交易对最后一笔成交的 ID
+async fn last_stored_trade_ids(manager: &MarketDataManager, symbols: &[String]) -> HashMap<String, u64> {
+    let mut last_trade_ids = HashMap::new();
+    for symbol in symbols {
+        let symbol = normalize_symbol(symbol);
+        match manager.get_last_trade(&symbol).await {
+            Ok(Some(last)) => match last.trade_id.parse::<u64>() {
+                Ok(trade_id) => {
+                    info!(
+                        "Last stored {} trade for {} is {} at {}",
+                        manager.exchange(), symbol, trade_id, last.timestamp
+                    );
+                    last_trade_ids.insert(symbol, trade_id);
+                }
+                Err(e) => warn!("Invalid {} trade id {} for {}: {}", manager.exchange(), last.trade_id, symbol, e),
+            },
+            Ok(None) => {}
+            Err(e) => warn!("Failed to load last {} trade for {}: {}", manager.exchange(), symbol, e),
+        }
+    }
+    last_trade_ids
+}
+
+// 依次处理补数据请求。执行期间到达的多个请求合并，同一交易对取最早的起点。
+// 补写的成交与实时成交做同样的质量检查，每段缺口按成交 ID 顺序单独检查
+async fn backfill_gaps(
+    exchange: Arc<Box<dyn Exchange>>,
+    manager: Arc<MarketDataManager>,
+    counters: Arc<CollectorCounters>,
+    quality: Option<QualityConfig>,
+    mut requests: mpsc::UnboundedReceiver<HashMap<String, u64>>,
+) {
+    while let Some(mut pending) = requests.recv().await {
+        while let Ok(next) = requests.try_recv() {
+            for (symbol, last_trade_id) in next {
+                let last = pending.entry(symbol).or_insert(last_trade_id);
+                *last = (*last).min(last_trade_id);
+            }
+        }
+
+        let mut symbols: Vec<_> = pending.into_iter().collect();
+        symbols.sort();
+        for (symbol, last_trade_id) in symbols {
+            let mut validator = quality.clone().map(TickValidator::new);
+            let result = backfill_symbol(
+                exchange.as_ref().as_ref(),
+                &manager,
+                &symbol,
+                last_trade_id,
+                validator.as_mut(),
+            )
+            .await;
+            match result {
+                Ok(0) => {}
+                Ok(stored) => {
+                    counters.backfilled.fetch_add(stored, Ordering::Relaxed);
+                    info!("Backfilled {} missing {} trades for {}", stored, manager.exchange(), symbol);
+                }
+                Err(e) => warn!("Failed to backfill {} trades for {}: {}", manager.exchange(), symbol, e),
+            }
+        }
+    }
+}
+
+// 从 last_trade_id 的下一笔成交开始，通过 REST 补齐到当前；
+// 与实时推送重叠的成交由 (exchange, symbol, trade_id, timestamp) 唯一索引去重
+async fn backfill_symbol(
+    exchange: &dyn Exchange,
+    manager: &MarketDataManager,
+    symbol: &str,
+    last_trade_id: u64,
+    mut validator: Option<&mut TickValidator>,
+) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    let mut from_id = last_trade_id + 1;
+    info!("Checking gap for {} since trade {}", symbol, last_trade_id);
+
+    let mut stored = 0;
+    for _ in 0..MAX_BACKFILL_PAGES {
+        let trades = exchange.get_historical_trades(symbol, from_id, BACKFILL_PAGE_SIZE).await?;
+        let Some(newest) = trades.last() else {
+            return Ok(stored);
+        };
+        from_id = newest.trade_id.parse::<u64>()? + 1;
+        let flags: Vec<_> = match validator.as_deref_mut() {
+            Some(validator) => {
+                let now = chrono::Utc::now();
+                trades.iter().flat_map(|tick| validator.check(tick, now)).collect()
+            }
+            None => Vec::new(),
+        };
+        stored += manager.store_tick_batch(&trades).await?;
+        // 先记录问题成交，再更新 K 线，使其不计入 K 线
+        if !flags.is_empty() {
+            warn!("Flagged {} data quality issues in {} backfilled {} trades", flags.len(), trades.len(), symbol);
+            manager.store_tick_flags(&flags).await?;
+        }
+        manager.update_candles_for_ticks(&trades).await?;
+
+        // 不足一页说明已经追上最新成交
+        if trades.len() < BACKFILL_PAGE_SIZE as usize {
+            return Ok(stored);
+        }
+    }
+
+    warn!(
+        "Backfill for {} stopped after {} pages, trades from id {} are still missing",
+        symbol, MAX_BACKFILL_PAGES, from_id
+    );
+    Ok(stored)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::exchange::binance::BinanceSpot;
+    use crate::exchange::okx::OkxSpot;
+    use crate::tests::mock_exchange::{MockExchange, MockResponse, StreamEvent};
     use sqlx::postgres::PgPoolOptions;
     use std::time::Duration;
     use dotenv::dotenv;
+
+    fn trade_message(trade_id: u64, price: &str) -> StreamEvent {
+        StreamEvent::Text(
+            serde_json::json!({
+                "e": "trade", "E": 1700000000125u64, "s": "MOCKUSDT", "t": trade_id,
+                "p": price, "q": "0.5", "T": 1700000000000u64 + trade_id, "m": false, "M": true
+            })
+            .to_string(),
+        )
+    }
+
+    fn okx_trade_message(trade_id: u64, price: &str) -> StreamEvent {
+        StreamEvent::Text(
+            serde_json::json!({
+                "arg": {"channel": "trades", "instId": "MOCK-USDT"},
+                "data": [{
+                    "instId": "MOCK-USDT", "tradeId": trade_id.to_string(), "px": price, "sz": "0.5",
+                    "side": "buy", "ts": (1700000000000u64 + trade_id).to_string(), "count": "1"
+                }]
+            })
+            .to_string(),
+        )
+    }
     
+    fn feed_stream(mock: &MockExchange, symbols: &[&str], config: ReconnectConfig) -> (FeedStream, mpsc::Receiver<TickData>) {
+        let (data_tx, data_rx) = mpsc::channel(100);
+        let exchange = BinanceSpot::new(None).with_endpoints(mock.endpoints());
+        let feed = FeedStream {
+            exchange: Arc::new(Box::new(exchange)),
+            symbols: symbols.iter().map(|s| s.to_string()).collect(),
+            config,
+            data_tx,
+        };
+        (feed, data_rx)
+    }
+
+    #[tokio::test]
+    async fn test_rotation_overlaps_connections() {
+        let mock = MockExchange::start().await.unwrap();
+        // 旧连接在轮换后还会推送成交 3，但新连接接管后旧连接已被关闭
+        mock.push_stream(vec![
+            trade_message(1, "100.0"),
+            trade_message(2, "100.5"),
+            StreamEvent::Delay(Duration::from_secs(3)),
+            trade_message(3, "101.0"),
+        ]);
+        mock.push_stream(vec![trade_message(2, "100.5"), trade_message(3, "101.0"), trade_message(4, "101.5")]);
+        let config = ReconnectConfig {
+            rotate_after_secs: Some(1),
+            ..ReconnectConfig::default()
+        };
+        let (feed, mut data_rx) = feed_stream(&mock, &["MOCKUSDT"], config);
+
+        let mut backoff = feed.config.backoff();
+        let mut last_trade_ids = HashMap::new();
+        let mut trade_ids = Vec::new();
+        let collect = async {
+            while let Some(tick) = data_rx.recv().await {
+                trade_ids.push(tick.trade_id);
+                if trade_ids.last().map(String::as_str) == Some("4") {
+                    break;
+                }
+            }
+        };
+        tokio::select! {
+            result = feed.run_connection(&mut backoff, &mut last_trade_ids) => panic!("connection ended: {:?}", result),
+            _ = tokio::time::timeout(Duration::from_secs(5), collect) => {}
+        }
+
+        // 重叠期间的成交 2 会重复推送，由数据库唯一索引去重
+        assert_eq!(trade_ids, ["1", "2", "2", "3", "4"]);
+        assert_eq!(mock.stream_connections().len(), 2);
+        // 重连时从转发的最后一笔成交之后补数据
+        assert_eq!(last_trade_ids.get("MOCKUSDT"), Some(&4));
+    }
+
+    #[tokio::test]
+    async fn test_watchdog_resubscribes_stale_symbol() {
+        let mock = MockExchange::start().await.unwrap();
+        let mut events = Vec::new();
+        for trade_id in 1..=8 {
+            events.push(trade_message(trade_id, "100.0"));
+            events.push(StreamEvent::Delay(Duration::from_millis(250)));
+        }
+        mock.push_stream(events);
+        let config = ReconnectConfig {
+            stale_after_secs: 1,
+            ..ReconnectConfig::default()
+        };
+        let (feed, mut data_rx) = feed_stream(&mock, &["MOCKUSDT", "idleusdt"], config);
+
+        let mut backoff = feed.config.backoff();
+        let mut last_trade_ids = HashMap::new();
+        let collect = async { for _ in 0..8 { data_rx.recv().await; } };
+        tokio::select! {
+            result = feed.run_connection(&mut backoff, &mut last_trade_ids) => panic!("connection ended: {:?}", result),
+            _ = tokio::time::timeout(Duration::from_secs(5), collect) => {}
+        }
+
+        // 只有没有数据的交易对在原连接上重新订阅
+        assert_eq!(mock.stream_connections().len(), 1);
+        let messages = mock.stream_messages();
+        assert!(messages.len() >= 2);
+        assert!(messages[0].contains("UNSUBSCRIBE") && messages[0].contains("idleusdt@trade"));
+        assert!(messages[1].contains("\"SUBSCRIBE\"") && messages[1].contains("idleusdt@trade"));
+        assert!(messages.iter().all(|message| !message.contains("mockusdt")));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_when_all_symbols_stale() {
+        let mock = MockExchange::start().await.unwrap();
+        mock.push_stream(vec![trade_message(1, "100.0")]);
+        let config = ReconnectConfig {
+            stale_after_secs: 1,
+            ..ReconnectConfig::default()
+        };
+        let (feed, mut data_rx) = feed_stream(&mock, &["MOCKUSDT"], config);
+
+        let mut backoff = feed.config.backoff();
+        let mut last_trade_ids = HashMap::new();
+        let result = tokio::time::timeout(Duration::from_secs(5), feed.run_connection(&mut backoff, &mut last_trade_ids))
+            .await
+            .expect("watchdog fires");
+        assert!(matches!(result, Err(ExchangeError::NetworkError(_))));
+        assert_eq!(data_rx.recv().await.unwrap().trade_id, "1");
+    }
+
+    // 补写的成交同样经过质量检查，异常成交不计入 K 线
+    #[tokio::test]
+    async fn test_backfill_flags_spikes() {
+        dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL")
+            .expect("DATABASE_URL must be set for tests");
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&database_url)
+            .await
+            .expect("Failed to create database pool");
+        let symbol = "BACKFILLQA";
+        let cleanup = || async {
+            for table in ["tick_data", "candles", "tick_flags"] {
+                sqlx::query(&format!("DELETE FROM {} WHERE symbol = $1", table))
+                    .bind(symbol)
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+            }
+        };
+        cleanup().await;
+        let manager = MarketDataManager::new(pool.clone());
+
+        // 成交 11..=40 在同一分钟内，成交 35 是异常价格
+        let trades: Vec<serde_json::Value> = (11..=40u64)
+            .map(|id| {
+                let price = if id == 35 { "90000.0" } else { "100.0" };
+                serde_json::json!({
+                    "id": id, "price": price, "qty": "0.5", "quoteQty": "50.0",
+                    "time": 1699999980000u64 + id * 100, "isBuyerMaker": false, "isBestMatch": true
+                })
+            })
+            .collect();
+        let mock = MockExchange::start().await.unwrap();
+        mock.route("/api/v3/historicalTrades", MockResponse::json(serde_json::Value::from(trades).to_string()));
+        let exchange = BinanceSpot::new(None).with_endpoints(mock.endpoints());
+
+        let mut validator = TickValidator::new(QualityConfig::default());
+        let stored = backfill_symbol(&exchange, &manager, symbol, 10, Some(&mut validator))
+            .await
+            .unwrap();
+        assert_eq!(stored, 30);
+        assert!(mock.requests()[0].contains("fromId=11"));
+
+        let flagged: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tick_flags WHERE symbol = $1 AND kind = 'price_spike'")
+            .bind(symbol)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(flagged, 1);
+        let start = chrono::DateTime::from_timestamp(1_699_999_980, 0).unwrap();
+        let candles = manager
+            .get_candles(symbol, "1m", start, start + chrono::Duration::minutes(1))
+            .await
+            .unwrap();
+        assert_eq!(candles[0].high, 100.0);
+
+        cleanup().await;
+    }
+
     #[tokio::test]
     async fn test_market_data_collection() {
         // 加载环境变量
@@ -141,21 +872,46 @@ mod tests {
             .connect(&database_url)
             .await
             .expect("Failed to create database pool");
+        let cleanup = || async {
+            for table in ["tick_data", "candles", "tick_flags"] {
+                sqlx::query(&format!("DELETE FROM {} WHERE symbol = 'MOCKUSDT'", table))
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+            }
+        };
+        cleanup().await;
             
         // 创建市场数据管理器
-        let market_data_manager = MarketDataManager::new(pool);
+        let market_data_manager = MarketDataManager::new(pool.clone());
 
-        info!("Initializing Binance connection...");
-        
-        // 创建交易所服务
-        let exchange = BinanceSpot::new(None);
+        // 模拟交易所：第一个连接推送 3 笔成交和一条格式错误的消息后断线，重连后再推送 2 笔
+        let mock = MockExchange::start().await.unwrap();
+        mock.route("/api/v3/historicalTrades", MockResponse::json("[]"));
+        mock.push_stream(vec![
+            trade_message(1, "100.0"),
+            trade_message(2, "100.5"),
+            StreamEvent::Text("{not json".to_string()),
+            trade_message(3, "101.0"),
+            StreamEvent::Disconnect,
+        ]);
+        mock.push_stream(vec![trade_message(4, "101.5"), trade_message(5, "102.0")]);
+        let exchange = BinanceSpot::new(None).with_endpoints(mock.endpoints());
+
+        // 同时采集 OKX 的同一交易对，成交 ID 与 Binance 重叠但不会被当作重复成交
+        let okx_mock = MockExchange::start().await.unwrap();
+        okx_mock.push_stream(vec![okx_trade_message(1, "100.2"), okx_trade_message(2, "100.7")]);
+        let okx = OkxSpot::new().with_endpoints(okx_mock.endpoints());
         
         // 创建数据采集器
-        let collector = Arc::new(MarketDataCollector::new(
-            Box::new(exchange),
-            market_data_manager,
-            vec!["btcusdt".to_string()],
-        ));
+        let collector = Arc::new(
+            MarketDataCollector::new(
+                Box::new(exchange),
+                market_data_manager.clone(),
+                vec!["mockusdt".to_string()],
+            )
+            .with_exchange(Box::new(okx), vec!["MOCKUSDT".to_string()]),
+        );
 
         info!("Starting data collection...");
         
@@ -166,14 +922,43 @@ mod tests {
             info!("Collector task finished");
         });
         
-        // 运行一段时间后停止
-        info!("Waiting for 10 seconds to collect data...");
-        tokio::time::sleep(Duration::from_secs(10)).await;
+        // 等待全部成交写入，断线后按退避等待重连
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(20);
+        while collector.stats().stored < 7 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
         info!("Stopping collector...");
         collector.stop();
         
         // 等待采集器停止
         handle.await.expect("Collector task failed");
+
+        let stats = collector.stats();
+        assert_eq!(stats.stored, 7);
+        assert_eq!(stats.duplicates, 0);
+        assert_eq!(mock.stream_connections().len(), 2);
+        assert_eq!(okx_mock.stream_connections().len(), 1);
+
+        // 查询按交易所区分
+        // 成交所在分钟的开始
+        let start = chrono::DateTime::from_timestamp(1_699_999_980, 0).unwrap();
+        let end = start + chrono::Duration::minutes(1);
+        let binance_ticks = market_data_manager.get_market_data("MOCKUSDT", start, end).await.unwrap();
+        let okx_manager = market_data_manager.clone().with_exchange("okx");
+        let okx_ticks = okx_manager.get_market_data("MOCKUSDT", start, end).await.unwrap();
+        assert_eq!((binance_ticks.len(), okx_ticks.len()), (5, 2));
+        assert_eq!(okx_ticks[1].price, 100.7);
+        assert_eq!(okx_manager.get_candles("MOCKUSDT", "1m", start, end).await.unwrap()[0].volume, 1.0);
+        // 启动时数据库中没有成交，不补数据；重连后从断线前转发的最后一笔成交之后补数据
+        let backfills: Vec<String> = mock
+            .requests()
+            .into_iter()
+            .filter(|request| request.starts_with("/api/v3/historicalTrades"))
+            .collect();
+        assert_eq!(backfills.len(), 1);
+        assert!(backfills[0].contains("fromId=4"));
+
+        cleanup().await;
         info!("Test completed successfully");
     }
-}
\ No newline at end of file
+}
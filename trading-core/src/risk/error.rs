@@ -0,0 +1,58 @@
+use crate::backtest::types::Order;
+use crate::exchange::symbols::FilterViolation;
+use crate::exchange::types::ExchangeError;
+use rust_decimal::Decimal;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum RiskViolation {
+    #[error("Kill switch is active, all new orders are rejected")]
+    KillSwitchActive,
+
+    #[error("Invalid order quantity: {0}")]
+    InvalidQuantity(Decimal),
+
+    #[error("No reference price for {0}, cannot run price checks")]
+    NoReferencePrice(String),
+
+    #[error("Order price {price} deviates from last price {reference} by more than {band}")]
+    PriceBandExceeded {
+        price: Decimal,
+        reference: Decimal,
+        band: Decimal,
+    },
+
+    #[error("Order notional {notional} exceeds limit {limit}")]
+    MaxNotionalExceeded { notional: Decimal, limit: Decimal },
+
+    #[error("Position for {symbol} would reach {projected}, limit is {limit}")]
+    MaxPositionExceeded {
+        symbol: String,
+        projected: Decimal,
+        limit: Decimal,
+    },
+
+    #[error("Open orders {open} reached limit {limit}")]
+    MaxOpenOrdersExceeded { open: usize, limit: usize },
+
+    #[error("Daily loss {loss} reached limit {limit}")]
+    DailyLossLimitExceeded { loss: Decimal, limit: Decimal },
+
+    #[error("Drawdown {drawdown} from peak reached limit {limit}")]
+    MaxDrawdownExceeded { drawdown: Decimal, limit: Decimal },
+
+    #[error("Order rate limit reached: {count} orders in {window_secs}s")]
+    OrderRateExceeded { count: usize, window_secs: u64 },
+
+    #[error("Order violates exchange rules: {0}")]
+    ExchangeFilter(#[from] FilterViolation),
+}
+
+/// kill switch 没有完整执行：撤单失败或部分平仓订单提交失败，kill switch 本身仍保持开启
+#[derive(Error, Debug, Clone)]
+#[error("Kill switch incomplete: {} of {} flatten orders failed", .failed.len(), .failed.len() + .submitted.len())]
+pub struct KillSwitchError {
+    pub cancel_error: Option<ExchangeError>,
+    pub submitted: Vec<Order>,
+    pub failed: Vec<(Order, ExchangeError)>,
+}
@@ -0,0 +1,609 @@
+// trading-core/src/risk/mod.rs
+
+pub mod error;
+pub mod types;
+
+use std::collections::{HashMap, VecDeque};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use tracing::{error, info, warn};
+
+use crate::backtest::types::{Order, OrderSide, OrderType, Trade};
+use crate::exchange::symbols::{FilterViolation, SymbolRegistry};
+use crate::exchange::types::Ticker;
+pub use error::{KillSwitchError, RiskViolation};
+pub use types::*;
+
+/// 下单前风控：所有实盘/模拟盘订单发出前都需要通过 `check_order`
+pub struct RiskManager {
+    limits: RiskLimits,
+    positions: HashMap<String, Decimal>,
+    last_prices: HashMap<String, Decimal>,
+    open_orders: usize,
+    // 各交易对已通过风控、尚未成交的买卖数量，成交后转入持仓
+    working: HashMap<String, WorkingQuantity>,
+    recent_orders: VecDeque<DateTime<Utc>>,
+    equity: Decimal,
+    peak_equity: Decimal,
+    day_start_equity: Decimal,
+    trading_day: NaiveDate,
+    kill_switch: bool,
+    // 配置后按交易所规则检查价格精度、数量步长和最小名义价值
+    symbols: Option<SymbolRegistry>,
+}
+
+impl RiskManager {
+    pub fn new(limits: RiskLimits, initial_equity: Decimal) -> Self {
+        Self {
+            limits,
+            positions: HashMap::new(),
+            last_prices: HashMap::new(),
+            open_orders: 0,
+            working: HashMap::new(),
+            recent_orders: VecDeque::new(),
+            equity: initial_equity,
+            peak_equity: initial_equity,
+            day_start_equity: initial_equity,
+            trading_day: Utc::now().date_naive(),
+            kill_switch: false,
+            symbols: None,
+        }
+    }
+
+    pub fn with_symbols(mut self, symbols: SymbolRegistry) -> Self {
+        self.symbols = Some(symbols);
+        self
+    }
+
+    pub fn limits(&self) -> &RiskLimits {
+        &self.limits
+    }
+
+    pub fn update_ticker(&mut self, ticker: &Ticker) {
+        self.update_price(&ticker.symbol, ticker.last_price);
+    }
+
+    pub fn update_price(&mut self, symbol: &str, price: Decimal) {
+        self.last_prices.insert(symbol.to_string(), price);
+    }
+
+    pub fn update_equity(&mut self, equity: Decimal, now: DateTime<Utc>) {
+        // 跨日时以上一日最后的权益作为当日起始权益
+        let today = now.date_naive();
+        if today != self.trading_day {
+            self.trading_day = today;
+            self.day_start_equity = self.equity;
+        }
+
+        self.equity = equity;
+        self.peak_equity = self.peak_equity.max(equity);
+    }
+
+    pub fn on_fill(&mut self, trade: &Trade) {
+        let position = self.positions.entry(trade.symbol.clone()).or_insert(Decimal::ZERO);
+        match trade.side {
+            OrderSide::Buy => *position += trade.quantity,
+            OrderSide::Sell => *position -= trade.quantity,
+        }
+        if position.is_zero() {
+            self.positions.remove(&trade.symbol);
+        }
+        if let Some(working) = self.working.get_mut(&trade.symbol) {
+            working.release(&trade.side, trade.quantity);
+        }
+    }
+
+    /// 订单成交完毕、撤单或被拒绝后调用，unfilled 为未成交的数量
+    pub fn on_order_closed(&mut self, order: &Order, unfilled: Decimal) {
+        self.open_orders = self.open_orders.saturating_sub(1);
+        if let Some(working) = self.working.get_mut(&order.symbol) {
+            working.release(&order.side, unfilled);
+        }
+    }
+
+    pub fn position(&self, symbol: &str) -> Decimal {
+        self.positions.get(symbol).copied().unwrap_or(Decimal::ZERO)
+    }
+
+    /// 检查订单；通过后订单计入挂单数、未成交数量和下单频率统计。
+    /// 只减少持仓绝对值的订单（连同同方向挂单不会反向开仓）不受持仓、日内亏损和回撤限制
+    pub fn check_order(&mut self, order: &Order) -> Result<(), RiskViolation> {
+        if self.kill_switch {
+            return Err(RiskViolation::KillSwitchActive);
+        }
+
+        if order.quantity <= Decimal::ZERO {
+            return Err(RiskViolation::InvalidQuantity(order.quantity));
+        }
+
+        let reference = *self.last_prices
+            .get(&order.symbol)
+            .ok_or_else(|| RiskViolation::NoReferencePrice(order.symbol.clone()))?;
+
+        let price = match order.order_type {
+            OrderType::Market => reference,
+            OrderType::Limit(price) => {
+                self.check_price_band(price, reference)?;
+                price
+            }
+        };
+
+        if let Some(symbols) = &self.symbols {
+            let info = symbols
+                .get(&order.symbol)
+                .ok_or_else(|| FilterViolation::UnknownSymbol(order.symbol.clone()))?;
+            match order.order_type {
+                OrderType::Market => info.check_market_order(price, order.quantity)?,
+                OrderType::Limit(_) => info.check_order(price, order.quantity)?,
+            }
+        }
+
+        let notional = order.quantity * price;
+        if notional > self.limits.max_order_notional {
+            return Err(RiskViolation::MaxNotionalExceeded {
+                notional,
+                limit: self.limits.max_order_notional,
+            });
+        }
+
+        // 同方向的挂单全部成交后的持仓
+        let position = self.position(&order.symbol);
+        let working = self.working.get(&order.symbol).copied().unwrap_or_default();
+        let projected = match order.side {
+            OrderSide::Buy => position + working.buy + order.quantity,
+            OrderSide::Sell => position - working.sell - order.quantity,
+        };
+        let reducing = match order.side {
+            OrderSide::Buy => position < Decimal::ZERO && projected <= Decimal::ZERO,
+            OrderSide::Sell => position > Decimal::ZERO && projected >= Decimal::ZERO,
+        };
+
+        let position_limit = self.limits.position_limit(&order.symbol);
+        if !reducing && projected.abs() > position_limit {
+            return Err(RiskViolation::MaxPositionExceeded {
+                symbol: order.symbol.clone(),
+                projected,
+                limit: position_limit,
+            });
+        }
+
+        if self.open_orders >= self.limits.max_open_orders {
+            return Err(RiskViolation::MaxOpenOrdersExceeded {
+                open: self.open_orders,
+                limit: self.limits.max_open_orders,
+            });
+        }
+
+        let daily_loss = self.day_start_equity - self.equity;
+        if !reducing && daily_loss >= self.limits.daily_loss_limit {
+            return Err(RiskViolation::DailyLossLimitExceeded {
+                loss: daily_loss,
+                limit: self.limits.daily_loss_limit,
+            });
+        }
+
+        if !reducing && self.peak_equity > Decimal::ZERO {
+            let drawdown = (self.peak_equity - self.equity) / self.peak_equity;
+            if drawdown >= self.limits.max_drawdown {
+                return Err(RiskViolation::MaxDrawdownExceeded {
+                    drawdown,
+                    limit: self.limits.max_drawdown,
+                });
+            }
+        }
+
+        self.check_order_rate(order.timestamp)?;
+
+        self.recent_orders.push_back(order.timestamp);
+        self.open_orders += 1;
+        let working = self.working.entry(order.symbol.clone()).or_default();
+        match order.side {
+            OrderSide::Buy => working.buy += order.quantity,
+            OrderSide::Sell => working.sell += order.quantity,
+        }
+        Ok(())
+    }
+
+    fn check_price_band(&self, price: Decimal, reference: Decimal) -> Result<(), RiskViolation> {
+        if reference.is_zero() {
+            return Ok(());
+        }
+
+        let deviation = (price - reference).abs() / reference;
+        if deviation > self.limits.price_band {
+            return Err(RiskViolation::PriceBandExceeded {
+                price,
+                reference,
+                band: self.limits.price_band,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_order_rate(&mut self, now: DateTime<Utc>) -> Result<(), RiskViolation> {
+        let window_start = now - Duration::seconds(self.limits.order_rate_window_secs as i64);
+        while self.recent_orders.front().is_some_and(|t| *t <= window_start) {
+            self.recent_orders.pop_front();
+        }
+
+        if self.recent_orders.len() >= self.limits.max_orders_per_window {
+            return Err(RiskViolation::OrderRateExceeded {
+                count: self.recent_orders.len(),
+                window_secs: self.limits.order_rate_window_secs,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn is_kill_switch_active(&self) -> bool {
+        self.kill_switch
+    }
+
+    /// 触发 kill switch：拒绝所有新订单，撤销全部挂单并以市价单平掉所有持仓。
+    /// 平仓订单不经过风控检查，返回已提交的平仓订单。撤单或某笔平仓失败时仍会
+    /// 尝试其余持仓，最后把所有失败一起返回。
+    pub async fn trigger_kill_switch(
+        &mut self,
+        gateway: &dyn OrderGateway,
+    ) -> Result<Vec<Order>, KillSwitchError> {
+        warn!("Kill switch triggered, cancelling all orders and flattening positions");
+        self.kill_switch = true;
+
+        let cancel_error = match gateway.cancel_all_orders().await {
+            Ok(()) => {
+                self.open_orders = 0;
+                self.working.clear();
+                None
+            }
+            Err(e) => {
+                error!("Kill switch failed to cancel open orders: {}", e);
+                Some(e)
+            }
+        };
+
+        let now = Utc::now();
+        let orders: Vec<Order> = self.positions
+            .iter()
+            .filter(|(_, quantity)| !quantity.is_zero())
+            .map(|(symbol, quantity)| Order {
+                symbol: symbol.clone(),
+                order_type: OrderType::Market,
+                side: if quantity.is_sign_positive() { OrderSide::Sell } else { OrderSide::Buy },
+                quantity: quantity.abs(),
+                timestamp: now,
+            })
+            .collect();
+
+        let mut submitted = Vec::with_capacity(orders.len());
+        let mut failed = Vec::new();
+        for order in orders {
+            match gateway.submit_order(&order).await {
+                Ok(()) => submitted.push(order),
+                Err(e) => {
+                    error!("Kill switch failed to flatten {}: {}", order.symbol, e);
+                    failed.push((order, e));
+                }
+            }
+        }
+
+        info!("Kill switch submitted {} flatten orders", submitted.len());
+        if cancel_error.is_some() || !failed.is_empty() {
+            return Err(KillSwitchError { cancel_error, submitted, failed });
+        }
+        Ok(submitted)
+    }
+
+    pub fn reset_kill_switch(&mut self) {
+        info!("Kill switch reset");
+        self.kill_switch = false;
+    }
+
+    pub fn snapshot(&self) -> RiskSnapshot {
+        RiskSnapshot {
+            kill_switch_active: self.kill_switch,
+            positions: self.positions.clone(),
+            open_orders: self.open_orders,
+            equity: self.equity,
+            peak_equity: self.peak_equity,
+            daily_pnl: self.equity - self.day_start_equity,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct WorkingQuantity {
+    buy: Decimal,
+    sell: Decimal,
+}
+
+impl WorkingQuantity {
+    fn release(&mut self, side: &OrderSide, quantity: Decimal) {
+        let working = match side {
+            OrderSide::Buy => &mut self.buy,
+            OrderSide::Sell => &mut self.sell,
+        };
+        *working = (*working - quantity).max(Decimal::ZERO);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::types::ExchangeError;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingGateway {
+        cancelled: Mutex<bool>,
+        submitted: Mutex<Vec<Order>>,
+        // 这些交易对的订单提交失败
+        failing: Vec<String>,
+    }
+
+    #[async_trait::async_trait]
+    impl OrderGateway for RecordingGateway {
+        async fn cancel_all_orders(&self) -> Result<(), ExchangeError> {
+            *self.cancelled.lock().unwrap() = true;
+            Ok(())
+        }
+
+        async fn submit_order(&self, order: &Order) -> Result<(), ExchangeError> {
+            if self.failing.contains(&order.symbol) {
+                return Err(ExchangeError::NetworkError("connection reset".to_string()));
+            }
+            self.submitted.lock().unwrap().push(order.clone());
+            Ok(())
+        }
+    }
+
+    fn ticker(symbol: &str, price: i64) -> Ticker {
+        Ticker {
+            symbol: symbol.to_string(),
+            timestamp: Utc::now(),
+            last_price: Decimal::from(price),
+            bid_price: Decimal::from(price),
+            ask_price: Decimal::from(price),
+            volume_24h: Decimal::ZERO,
+        }
+    }
+
+    fn order(side: OrderSide, order_type: OrderType, quantity: Decimal) -> Order {
+        Order {
+            symbol: "BTCUSDT".to_string(),
+            order_type,
+            side,
+            quantity,
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn manager() -> RiskManager {
+        let limits = RiskLimits {
+            max_position: Decimal::from(2),
+            max_order_notional: Decimal::from(100_000),
+            max_open_orders: 3,
+            daily_loss_limit: Decimal::from(500),
+            max_drawdown: Decimal::new(1, 1),
+            max_orders_per_window: 2,
+            order_rate_window_secs: 1,
+            price_band: Decimal::new(5, 2),
+            ..RiskLimits::default()
+        };
+        let mut manager = RiskManager::new(limits, Decimal::from(10_000));
+        manager.update_ticker(&ticker("BTCUSDT", 50_000));
+        manager
+    }
+
+    #[test]
+    fn test_accepts_order_within_limits() {
+        let mut risk = manager();
+        let result = risk.check_order(&order(OrderSide::Buy, OrderType::Market, Decimal::ONE));
+        assert_eq!(result, Ok(()));
+        assert_eq!(risk.snapshot().open_orders, 1);
+    }
+
+    #[test]
+    fn test_rejects_without_reference_price() {
+        let mut risk = manager();
+        let mut eth = order(OrderSide::Buy, OrderType::Market, Decimal::ONE);
+        eth.symbol = "ETHUSDT".to_string();
+        assert_eq!(
+            risk.check_order(&eth),
+            Err(RiskViolation::NoReferencePrice("ETHUSDT".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_rejects_exchange_filter_violations() {
+        use crate::exchange::symbols::SymbolInfo;
+
+        let info = SymbolInfo {
+            symbol: "BTCUSDT".to_string(),
+            base_asset: "BTC".to_string(),
+            quote_asset: "USDT".to_string(),
+            status: "TRADING".to_string(),
+            tick_size: Decimal::new(1, 2),
+            min_price: Decimal::new(1, 2),
+            max_price: Decimal::from(1_000_000),
+            step_size: Decimal::new(1, 5),
+            min_qty: Decimal::new(1, 5),
+            max_qty: Decimal::from(9000),
+            market_step_size: Decimal::new(1, 5),
+            market_min_qty: Decimal::new(1, 5),
+            market_max_qty: Decimal::from(100),
+            min_notional: Decimal::from(5),
+        };
+        let mut risk = manager().with_symbols(SymbolRegistry::new(vec![info]));
+
+        let too_small = order(OrderSide::Buy, OrderType::Market, Decimal::new(1, 5));
+        assert!(matches!(
+            risk.check_order(&too_small),
+            Err(RiskViolation::ExchangeFilter(FilterViolation::MinNotional { .. }))
+        ));
+
+        let bad_tick = order(OrderSide::Buy, OrderType::Limit(Decimal::new(50_000_001, 3)), Decimal::ONE);
+        assert!(matches!(
+            risk.check_order(&bad_tick),
+            Err(RiskViolation::ExchangeFilter(FilterViolation::InvalidTickSize { .. }))
+        ));
+
+        risk.update_ticker(&ticker("ETHUSDT", 2_000));
+        let mut eth = order(OrderSide::Buy, OrderType::Market, Decimal::ONE);
+        eth.symbol = "ETHUSDT".to_string();
+        assert_eq!(
+            risk.check_order(&eth),
+            Err(RiskViolation::ExchangeFilter(FilterViolation::UnknownSymbol("ETHUSDT".to_string())))
+        );
+
+        assert!(risk.check_order(&order(OrderSide::Buy, OrderType::Market, Decimal::new(1, 2))).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_fat_finger_price() {
+        let mut risk = manager();
+        let limit = OrderType::Limit(Decimal::from(60_000));
+        let result = risk.check_order(&order(OrderSide::Buy, limit, Decimal::new(1, 1)));
+        assert!(matches!(result, Err(RiskViolation::PriceBandExceeded { .. })));
+    }
+
+    #[test]
+    fn test_rejects_notional_and_position() {
+        let mut risk = manager();
+        let result = risk.check_order(&order(OrderSide::Buy, OrderType::Market, Decimal::from(3)));
+        assert!(matches!(result, Err(RiskViolation::MaxNotionalExceeded { .. })));
+
+        risk.on_fill(&Trade {
+            symbol: "BTCUSDT".to_string(),
+            side: OrderSide::Buy,
+            quantity: Decimal::new(15, 1),
+            price: Decimal::from(50_000),
+            timestamp: Utc::now(),
+            commission: Decimal::ZERO,
+        });
+        let result = risk.check_order(&order(OrderSide::Buy, OrderType::Market, Decimal::ONE));
+        assert!(matches!(result, Err(RiskViolation::MaxPositionExceeded { .. })));
+    }
+
+    #[test]
+    fn test_counts_working_orders_in_position() {
+        let mut risk = manager();
+        let buy = order(OrderSide::Buy, OrderType::Market, Decimal::new(15, 1));
+        assert!(risk.check_order(&buy).is_ok());
+        // 第一笔尚未成交，两笔合计超过持仓上限
+        let result = risk.check_order(&order(OrderSide::Buy, OrderType::Market, Decimal::ONE));
+        assert!(matches!(result, Err(RiskViolation::MaxPositionExceeded { .. })));
+
+        // 撤单后释放未成交数量
+        risk.on_order_closed(&buy, buy.quantity);
+        assert!(risk.check_order(&order(OrderSide::Buy, OrderType::Market, Decimal::ONE)).is_ok());
+    }
+
+    #[test]
+    fn test_allows_reducing_orders_past_limits() {
+        let mut risk = manager();
+        risk.on_fill(&Trade {
+            symbol: "BTCUSDT".to_string(),
+            side: OrderSide::Buy,
+            quantity: Decimal::ONE,
+            price: Decimal::from(50_000),
+            timestamp: Utc::now(),
+            commission: Decimal::ZERO,
+        });
+        risk.update_equity(Decimal::from(8_000), Utc::now());
+
+        let result = risk.check_order(&order(OrderSide::Buy, OrderType::Market, Decimal::new(1, 2)));
+        assert!(matches!(result, Err(RiskViolation::DailyLossLimitExceeded { .. })));
+        assert!(risk.check_order(&order(OrderSide::Sell, OrderType::Market, Decimal::new(6, 1))).is_ok());
+
+        // 连同未成交的卖单会反向开仓，不再视为减仓
+        let result = risk.check_order(&order(OrderSide::Sell, OrderType::Market, Decimal::new(6, 1)));
+        assert!(matches!(result, Err(RiskViolation::DailyLossLimitExceeded { .. })));
+    }
+
+    #[test]
+    fn test_rejects_on_open_orders_and_rate() {
+        let mut risk = manager();
+        let small = Decimal::new(1, 2);
+        assert!(risk.check_order(&order(OrderSide::Buy, OrderType::Market, small)).is_ok());
+        assert!(risk.check_order(&order(OrderSide::Buy, OrderType::Market, small)).is_ok());
+        let result = risk.check_order(&order(OrderSide::Buy, OrderType::Market, small));
+        assert!(matches!(result, Err(RiskViolation::OrderRateExceeded { .. })));
+
+        let mut later = order(OrderSide::Buy, OrderType::Market, small);
+        later.timestamp += Duration::seconds(2);
+        assert!(risk.check_order(&later).is_ok());
+        later.timestamp += Duration::seconds(2);
+        let result = risk.check_order(&later);
+        assert!(matches!(result, Err(RiskViolation::MaxOpenOrdersExceeded { .. })));
+    }
+
+    #[test]
+    fn test_rejects_on_daily_loss_and_drawdown() {
+        let mut risk = manager();
+        let now = Utc::now();
+        risk.update_equity(Decimal::from(9_400), now);
+        let result = risk.check_order(&order(OrderSide::Buy, OrderType::Market, Decimal::new(1, 2)));
+        assert!(matches!(result, Err(RiskViolation::DailyLossLimitExceeded { .. })));
+
+        // 新的一天重置日内亏损，但回撤仍按历史峰值计算
+        risk.update_equity(Decimal::from(9_000), now + Duration::days(1));
+        let result = risk.check_order(&order(OrderSide::Buy, OrderType::Market, Decimal::new(1, 2)));
+        assert!(matches!(result, Err(RiskViolation::MaxDrawdownExceeded { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_kill_switch_flattens_positions() {
+        let mut risk = manager();
+        risk.on_fill(&Trade {
+            symbol: "BTCUSDT".to_string(),
+            side: OrderSide::Buy,
+            quantity: Decimal::ONE,
+            price: Decimal::from(50_000),
+            timestamp: Utc::now(),
+            commission: Decimal::ZERO,
+        });
+
+        let gateway = RecordingGateway::default();
+        let orders = risk.trigger_kill_switch(&gateway).await.unwrap();
+
+        assert!(*gateway.cancelled.lock().unwrap());
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].side, OrderSide::Sell);
+        assert_eq!(orders[0].quantity, Decimal::ONE);
+        assert_eq!(gateway.submitted.lock().unwrap().len(), 1);
+
+        let result = risk.check_order(&order(OrderSide::Buy, OrderType::Market, Decimal::new(1, 2)));
+        assert_eq!(result, Err(RiskViolation::KillSwitchActive));
+
+        risk.reset_kill_switch();
+        assert!(risk.check_order(&order(OrderSide::Buy, OrderType::Market, Decimal::new(1, 2))).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_kill_switch_flattens_remaining_positions_after_failure() {
+        let mut risk = manager();
+        for symbol in ["BTCUSDT", "ETHUSDT", "SOLUSDT"] {
+            risk.on_fill(&Trade {
+                symbol: symbol.to_string(),
+                side: OrderSide::Buy,
+                quantity: Decimal::ONE,
+                price: Decimal::from(100),
+                timestamp: Utc::now(),
+                commission: Decimal::ZERO,
+            });
+        }
+
+        let gateway = RecordingGateway {
+            failing: vec!["ETHUSDT".to_string()],
+            ..RecordingGateway::default()
+        };
+        let error = risk.trigger_kill_switch(&gateway).await.unwrap_err();
+
+        assert!(error.cancel_error.is_none());
+        assert_eq!(error.failed.len(), 1);
+        assert_eq!(error.failed[0].0.symbol, "ETHUSDT");
+        assert_eq!(error.submitted.len(), 2);
+        assert_eq!(gateway.submitted.lock().unwrap().len(), 2);
+        assert!(risk.is_kill_switch_active());
+    }
+}
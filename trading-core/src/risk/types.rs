@@ -0,0 +1,73 @@
+// trading-core/src/risk/types.rs
+
+use std::collections::HashMap;
+use crate::backtest::types::Order;
+use crate::exchange::types::ExchangeError;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+// 风控限额
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskLimits {
+    // 单个交易对的最大持仓（绝对数量），未单独配置的交易对使用默认值
+    pub max_position: Decimal,
+    pub max_position_per_symbol: HashMap<String, Decimal>,
+    // 单笔订单最大名义价值
+    pub max_order_notional: Decimal,
+    pub max_open_orders: usize,
+    // 当日最大亏损（金额）
+    pub daily_loss_limit: Decimal,
+    // 相对权益峰值的最大回撤（比例，0.2 表示 20%）
+    pub max_drawdown: Decimal,
+    // 下单频率限制：window 秒内最多 max_orders_per_window 笔
+    pub max_orders_per_window: usize,
+    pub order_rate_window_secs: u64,
+    // 胖手指保护：订单价格偏离最新成交价的最大比例
+    pub price_band: Decimal,
+}
+
+impl Default for RiskLimits {
+    fn default() -> Self {
+        Self {
+            max_position: Decimal::ONE,
+            max_position_per_symbol: HashMap::new(),
+            max_order_notional: Decimal::from(10_000),
+            max_open_orders: 20,
+            daily_loss_limit: Decimal::from(1_000),
+            max_drawdown: Decimal::new(2, 1),
+            max_orders_per_window: 10,
+            order_rate_window_secs: 1,
+            price_band: Decimal::new(5, 2),
+        }
+    }
+}
+
+impl RiskLimits {
+    pub fn position_limit(&self, symbol: &str) -> Decimal {
+        self.max_position_per_symbol
+            .get(symbol)
+            .copied()
+            .unwrap_or(self.max_position)
+    }
+}
+
+// 风控状态快照，用于展示和日志
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskSnapshot {
+    pub kill_switch_active: bool,
+    pub positions: HashMap<String, Decimal>,
+    pub open_orders: usize,
+    pub equity: Decimal,
+    pub peak_equity: Decimal,
+    pub daily_pnl: Decimal,
+}
+
+/// 下单通道，实盘和模拟盘各自实现；风控的 kill switch 通过它撤单和平仓
+#[async_trait::async_trait]
+pub trait OrderGateway: Send + Sync {
+    /// 撤销所有未成交订单
+    async fn cancel_all_orders(&self) -> Result<(), ExchangeError>;
+
+    /// 提交订单
+    async fn submit_order(&self, order: &Order) -> Result<(), ExchangeError>;
+}
@@ -0,0 +1,346 @@
+// trading-core/src/tests/mock_exchange.rs
+// 本地模拟的 Binance：REST 端口按路径返回预置的响应，WebSocket 端口按脚本回放推送消息。
+// 可注入限流响应、格式错误的消息和断线，用于离线的端到端测试。
+
+use crate::exchange::types::ExchangeEndpoints;
+use futures_util::{SinkExt, StreamExt};
+use reqwest::{StatusCode, Url};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockResponse {
+    pub fn json(body: impl Into<String>) -> Self {
+        Self::status(200, body)
+    }
+
+    pub fn status(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// 429，带 Retry-After 和 -1003 错误码
+    pub fn rate_limited(retry_after_secs: u64) -> Self {
+        Self::status(429, r#"{"code":-1003,"msg":"Too many requests."}"#)
+            .with_header("Retry-After", retry_after_secs.to_string())
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+// WebSocket 连接上依次执行的动作
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    // 原样发送的文本帧，也可以是格式错误的内容
+    Text(String),
+    Delay(Duration),
+    // 不发送关闭帧直接断开 TCP 连接
+    Disconnect,
+}
+
+impl StreamEvent {
+    /// 把录制的 JSONL 文件逐行转为文本帧
+    pub fn from_jsonl(fixture: &str) -> Vec<StreamEvent> {
+        fixture
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| StreamEvent::Text(line.to_string()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    routes: HashMap<String, MockResponse>,
+    // 优先于 routes，按顺序各使用一次
+    queued: HashMap<String, VecDeque<MockResponse>>,
+    // 每个新的 WebSocket 连接取出一个脚本，没有脚本时连接保持空闲
+    scripts: VecDeque<Vec<StreamEvent>>,
+    requests: Vec<String>,
+    stream_paths: Vec<String>,
+    // 客户端在 WebSocket 上发送的文本消息，例如订阅请求
+    stream_messages: Vec<String>,
+}
+
+pub struct MockExchange {
+    rest_addr: SocketAddr,
+    ws_addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl MockExchange {
+    /// 在随机端口上启动 REST 和 WebSocket 服务
+    pub async fn start() -> std::io::Result<Self> {
+        let rest_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let ws_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let rest_addr = rest_listener.local_addr()?;
+        let ws_addr = ws_listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let rest_state = state.clone();
+        let rest_task = tokio::spawn(async move {
+            while let Ok((stream, _)) = rest_listener.accept().await {
+                tokio::spawn(serve_http(stream, rest_state.clone()));
+            }
+        });
+        let ws_state = state.clone();
+        let ws_task = tokio::spawn(async move {
+            while let Ok((stream, _)) = ws_listener.accept().await {
+                tokio::spawn(serve_stream(stream, ws_state.clone()));
+            }
+        });
+
+        Ok(Self {
+            rest_addr,
+            ws_addr,
+            state,
+            tasks: vec![rest_task, ws_task],
+        })
+    }
+
+    pub fn endpoints(&self) -> ExchangeEndpoints {
+        ExchangeEndpoints {
+            rest_url: Url::parse(&format!("http://{}", self.rest_addr)).expect("valid mock URL"),
+            ws_url: Url::parse(&format!("ws://{}", self.ws_addr)).expect("valid mock URL"),
+        }
+    }
+
+    /// 该路径的默认响应
+    pub fn route(&self, path: &str, response: MockResponse) {
+        self.lock().routes.insert(path.to_string(), response);
+    }
+
+    /// 该路径的下一次请求使用此响应，之后恢复默认
+    pub fn respond_once(&self, path: &str, response: MockResponse) {
+        self.lock()
+            .queued
+            .entry(path.to_string())
+            .or_default()
+            .push_back(response);
+    }
+
+    /// 为下一个 WebSocket 连接添加回放脚本
+    pub fn push_stream(&self, events: Vec<StreamEvent>) {
+        self.lock().scripts.push_back(events);
+    }
+
+    /// 已收到的 REST 请求（路径和查询参数）
+    pub fn requests(&self) -> Vec<String> {
+        self.lock().requests.clone()
+    }
+
+    /// 已建立的 WebSocket 连接的路径
+    pub fn stream_connections(&self) -> Vec<String> {
+        self.lock().stream_paths.clone()
+    }
+
+    /// 客户端发来的 WebSocket 文本消息
+    pub fn stream_messages(&self) -> Vec<String> {
+        self.lock().stream_messages.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for MockExchange {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+async fn serve_http(mut stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    // 只处理不带请求体的 GET，读到头部结束即可
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buffer.windows(4).any(|w| w == b"\r\n\r\n") {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+        }
+    }
+
+    let request = String::from_utf8_lossy(&buffer);
+    let target = request
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or("/")
+        .to_string();
+    let path = target.split('?').next().unwrap_or("/").to_string();
+    debug!("Mock exchange request: {}", target);
+
+    let response = {
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        state.requests.push(target);
+        state
+            .queued
+            .get_mut(&path)
+            .and_then(|queue| queue.pop_front())
+            .or_else(|| state.routes.get(&path).cloned())
+            .unwrap_or_else(|| MockResponse::status(404, r#"{"code":-1,"msg":"No mock route."}"#))
+    };
+
+    let reason = StatusCode::from_u16(response.status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("Unknown");
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(response.body.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+// 握手回调的错误类型由 tungstenite 决定
+#[allow(clippy::result_large_err)]
+async fn serve_stream(stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    let path_state = state.clone();
+    let callback = move |request: &Request, response: Response| {
+        let mut state = path_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.stream_paths.push(request.uri().to_string());
+        Ok(response)
+    };
+    let ws = match tokio_tungstenite::accept_hdr_async(stream, callback).await {
+        Ok(ws) => ws,
+        Err(e) => {
+            warn!("Mock exchange WebSocket handshake failed: {}", e);
+            return;
+        }
+    };
+
+    let script = state
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .scripts
+        .pop_front()
+        .unwrap_or_default();
+    let (mut write, mut read) = ws.split();
+
+    // 回放脚本的同时记录客户端消息
+    let read_state = state.clone();
+    let reader = tokio::spawn(async move {
+        while let Some(Ok(message)) = read.next().await {
+            if let Message::Text(text) = message {
+                let mut state = read_state.lock().unwrap_or_else(|e| e.into_inner());
+                state.stream_messages.push(text);
+            }
+        }
+    });
+
+    for event in script {
+        match event {
+            StreamEvent::Text(text) => {
+                if write.send(Message::Text(text)).await.is_err() {
+                    reader.abort();
+                    return;
+                }
+            }
+            StreamEvent::Delay(delay) => tokio::time::sleep(delay).await,
+            // 丢弃读写两端即关闭底层 TCP 连接
+            StreamEvent::Disconnect => {
+                reader.abort();
+                return;
+            }
+        }
+    }
+
+    // 脚本结束后保持连接，直到客户端断开
+    let _ = reader.await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::binance::BinanceSpot;
+    use crate::exchange::types::{Exchange, ExchangeError};
+    use tokio::sync::mpsc;
+
+    const TICKER: &str = include_str!("../../tests/fixtures/binance/ticker_24hr.json");
+    const INVALID_SYMBOL: &str = include_str!("../../tests/fixtures/binance/error_invalid_symbol.json");
+    const TRADE: &str = include_str!("../../tests/fixtures/binance/trade.json");
+
+    #[tokio::test]
+    async fn test_rest_fixtures_and_injected_errors() {
+        let mock = MockExchange::start().await.unwrap();
+        let spot = BinanceSpot::new(None).with_endpoints(mock.endpoints());
+
+        mock.route("/api/v3/ticker/24hr", MockResponse::json(TICKER));
+        mock.respond_once("/api/v3/ticker/24hr", MockResponse::rate_limited(0));
+        let ticker = spot.get_ticker("BTCUSDT").await.unwrap();
+        assert_eq!(ticker.last_price.to_string(), "37250.50000000");
+        // 第一次被限流，重试后成功
+        assert_eq!(mock.requests().len(), 2);
+        assert_eq!(mock.requests()[1], "/api/v3/ticker/24hr?symbol=BTCUSDT");
+
+        mock.respond_once("/api/v3/ticker/24hr", MockResponse::status(400, INVALID_SYMBOL));
+        assert!(matches!(
+            spot.get_ticker("NOPE").await,
+            Err(ExchangeError::InvalidSymbol(_))
+        ));
+        assert!(matches!(
+            spot.get_ticker("BTCUSDT").await,
+            Ok(ticker) if ticker.symbol == "BTCUSDT"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stream_replay_with_malformed_frame_and_disconnect() {
+        let mock = MockExchange::start().await.unwrap();
+        let spot = BinanceSpot::new(None).with_endpoints(mock.endpoints());
+
+        let mut events = StreamEvent::from_jsonl(TRADE);
+        events.push(StreamEvent::Text("{not json".to_string()));
+        events.push(StreamEvent::Delay(Duration::from_millis(50)));
+        events.extend(StreamEvent::from_jsonl(TRADE));
+        events.push(StreamEvent::Disconnect);
+        mock.push_stream(events);
+
+        let (tx, mut rx) = mpsc::channel(10);
+        let symbols = vec!["btcusdt".to_string()];
+        let result = spot.subscribe_trades(&symbols, tx).await;
+
+        // 格式错误的消息被忽略，断线以错误返回以便调用方重连
+        assert!(matches!(result, Err(ExchangeError::NetworkError(_))));
+        assert_eq!(mock.stream_connections(), vec!["/ws/btcusdt@trade".to_string()]);
+        let mut received = 0;
+        while let Ok(tick) = rx.try_recv() {
+            assert_eq!(tick.trade_id, "3412093912");
+            received += 1;
+        }
+        assert_eq!(received, 2);
+    }
+}
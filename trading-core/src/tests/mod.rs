@@ -0,0 +1 @@
+pub mod mock_exchange;